@@ -0,0 +1,9 @@
+CREATE TABLE order_book_snapshots (
+    id SERIAL PRIMARY KEY,
+    pair TEXT NOT NULL,
+    best_bid FLOAT8 NOT NULL,
+    best_ask FLOAT8 NOT NULL,
+    bids JSONB NOT NULL,
+    asks JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,12 @@
+CREATE TABLE optimized_macds (
+  id SERIAL PRIMARY KEY,
+  pair TEXT NOT NULL,
+  fast_period INT NOT NULL,
+  slow_period INT NOT NULL,
+  signal_period INT NOT NULL,
+  offset_minutes INT NOT NULL,
+  win_rate_pct FLOAT8,
+  total INT,
+  wins INT,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1 @@
+ALTER TABLE orders ADD COLUMN contribution_jpy FLOAT8;
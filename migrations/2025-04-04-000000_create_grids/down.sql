@@ -0,0 +1,2 @@
+DROP TABLE grid_levels;
+DROP TABLE grids;
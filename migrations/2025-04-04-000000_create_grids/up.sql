@@ -0,0 +1,24 @@
+CREATE TABLE grids (
+    id SERIAL PRIMARY KEY,
+    pair TEXT NOT NULL,
+    lower_price FLOAT8 NOT NULL,
+    upper_price FLOAT8 NOT NULL,
+    levels INT NOT NULL,
+    order_amount FLOAT8 NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE grid_levels (
+    id SERIAL PRIMARY KEY,
+    grid_id INT NOT NULL REFERENCES grids(id) ON DELETE CASCADE,
+    level_index INT NOT NULL,
+    price FLOAT8 NOT NULL,
+    side TEXT NOT NULL,
+    state TEXT NOT NULL,
+    exchange_order_id BIGINT,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (grid_id, level_index)
+);
+
+SELECT diesel_manage_updated_at('grid_levels');
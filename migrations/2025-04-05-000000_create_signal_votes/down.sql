@@ -0,0 +1 @@
+DROP TABLE signal_votes;
@@ -0,0 +1,11 @@
+CREATE TABLE signal_votes (
+    id SERIAL PRIMARY KEY,
+    order_id INT NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    strategy TEXT NOT NULL,
+    vote TEXT NOT NULL,
+    weight FLOAT8 NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX index_signal_votes_on_order_id ON signal_votes (order_id);
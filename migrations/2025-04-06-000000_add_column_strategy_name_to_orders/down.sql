@@ -0,0 +1,2 @@
+ALTER TABLE orders DROP COLUMN strategy_version;
+ALTER TABLE orders DROP COLUMN strategy_name;
@@ -0,0 +1,2 @@
+ALTER TABLE orders ADD COLUMN strategy_name TEXT;
+ALTER TABLE orders ADD COLUMN strategy_version TEXT;
@@ -0,0 +1,3 @@
+ALTER TABLE orders DROP COLUMN signal_metadata;
+ALTER TABLE orders DROP COLUMN position_fraction;
+ALTER TABLE orders DROP COLUMN confidence;
@@ -0,0 +1,3 @@
+ALTER TABLE orders ADD COLUMN confidence FLOAT8;
+ALTER TABLE orders ADD COLUMN position_fraction FLOAT8;
+ALTER TABLE orders ADD COLUMN signal_metadata JSONB;
@@ -0,0 +1,3 @@
+ALTER TABLE optimized_mas DROP COLUMN expectancy_pct;
+ALTER TABLE optimized_mas DROP COLUMN profit_factor;
+ALTER TABLE optimized_mas DROP COLUMN avg_return_pct;
@@ -0,0 +1,3 @@
+ALTER TABLE optimized_mas ADD COLUMN avg_return_pct FLOAT8;
+ALTER TABLE optimized_mas ADD COLUMN profit_factor FLOAT8;
+ALTER TABLE optimized_mas ADD COLUMN expectancy_pct FLOAT8;
@@ -0,0 +1,6 @@
+ALTER TABLE optimized_mas DROP COLUMN promoted;
+ALTER TABLE optimized_mas DROP COLUMN oos_expectancy_pct;
+ALTER TABLE optimized_mas DROP COLUMN oos_profit_factor;
+ALTER TABLE optimized_mas DROP COLUMN oos_avg_return_pct;
+ALTER TABLE optimized_mas DROP COLUMN oos_win_rate_pct;
+ALTER TABLE optimized_mas DROP COLUMN oos_total;
@@ -0,0 +1,6 @@
+ALTER TABLE optimized_mas ADD COLUMN oos_total INT;
+ALTER TABLE optimized_mas ADD COLUMN oos_win_rate_pct FLOAT8;
+ALTER TABLE optimized_mas ADD COLUMN oos_avg_return_pct FLOAT8;
+ALTER TABLE optimized_mas ADD COLUMN oos_profit_factor FLOAT8;
+ALTER TABLE optimized_mas ADD COLUMN oos_expectancy_pct FLOAT8;
+ALTER TABLE optimized_mas ADD COLUMN promoted BOOLEAN;
@@ -0,0 +1,4 @@
+ALTER TABLE orders DROP COLUMN optimization_run_id;
+DROP INDEX index_optimized_mas_on_run_id;
+ALTER TABLE optimized_mas DROP COLUMN run_id;
+DROP TABLE optimization_runs;
@@ -0,0 +1,14 @@
+CREATE TABLE optimization_runs (
+    id SERIAL PRIMARY KEY,
+    pair TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    settings JSONB,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX index_optimization_runs_on_pair_and_kind ON optimization_runs (pair, kind, created_at);
+
+ALTER TABLE optimized_mas ADD COLUMN run_id INT REFERENCES optimization_runs(id) ON DELETE CASCADE;
+CREATE INDEX index_optimized_mas_on_run_id ON optimized_mas (run_id);
+
+ALTER TABLE orders ADD COLUMN optimization_run_id INT REFERENCES optimization_runs(id);
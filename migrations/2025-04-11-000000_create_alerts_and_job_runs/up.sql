@@ -0,0 +1,19 @@
+CREATE TABLE job_runs (
+    id SERIAL PRIMARY KEY,
+    job TEXT NOT NULL,
+    success BOOLEAN NOT NULL,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX index_job_runs_on_job ON job_runs (job, created_at);
+
+CREATE TABLE alerts (
+    id SERIAL PRIMARY KEY,
+    rule TEXT NOT NULL,
+    key TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX index_alerts_on_rule_and_key ON alerts (rule, key, created_at);
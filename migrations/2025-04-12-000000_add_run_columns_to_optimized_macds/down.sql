@@ -0,0 +1,5 @@
+DROP INDEX index_optimized_macds_on_run_id;
+ALTER TABLE optimized_macds DROP COLUMN run_id;
+ALTER TABLE optimized_macds DROP COLUMN expectancy_pct;
+ALTER TABLE optimized_macds DROP COLUMN profit_factor;
+ALTER TABLE optimized_macds DROP COLUMN avg_return_pct;
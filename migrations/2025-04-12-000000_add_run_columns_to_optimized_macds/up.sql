@@ -0,0 +1,5 @@
+ALTER TABLE optimized_macds ADD COLUMN avg_return_pct FLOAT8;
+ALTER TABLE optimized_macds ADD COLUMN profit_factor FLOAT8;
+ALTER TABLE optimized_macds ADD COLUMN expectancy_pct FLOAT8;
+ALTER TABLE optimized_macds ADD COLUMN run_id INT REFERENCES optimization_runs(id) ON DELETE CASCADE;
+CREATE INDEX index_optimized_macds_on_run_id ON optimized_macds (run_id);
@@ -0,0 +1,2 @@
+ALTER TABLE job_runs ALTER COLUMN created_at SET DEFAULT NOW();
+ALTER TABLE alerts ALTER COLUMN created_at SET DEFAULT NOW();
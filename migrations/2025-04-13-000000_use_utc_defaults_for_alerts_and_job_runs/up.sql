@@ -0,0 +1,3 @@
+-- アラートのクールダウンと連続失敗は、Utc::now()と比べるのでUTCで記録する
+ALTER TABLE alerts ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'utc');
+ALTER TABLE job_runs ALTER COLUMN created_at SET DEFAULT (NOW() AT TIME ZONE 'utc');
@@ -1,15 +1,13 @@
 use serde_json::Value;
-use::reqwest::Client;
 
 use crate::api::coincheck::{private, client};
 use crate::error::AppError;
 
 pub async fn find(coincheck_client: &client::CoincheckClient) -> Result<Value, AppError> {
     let endpoint = format!("{}{}", coincheck_client.base_url, "/api/accounts/balance");
-    let headers = private::headers(&endpoint, &coincheck_client, None)?;
+    let headers = private::headers(&endpoint, coincheck_client, None)?;
 
-    let client = Client::new();
-    let response = client
+    let response = coincheck_client.client
         .get(endpoint)
         .headers(headers)
         .send()
@@ -17,7 +15,8 @@ pub async fn find(coincheck_client: &client::CoincheckClient) -> Result<Value, A
 
     client::sleep()?;
 
-    let mut json: serde_json::Value = response.json().await?;
+    let (status, body) = client::read_response(response).await?;
+    let mut json = client::check_response(status, body)?;
 
     /* 
      * TODO: ウォレットからshibが消せないので、ここでハードコーディングで削除。
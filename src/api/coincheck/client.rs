@@ -4,9 +4,10 @@ use std::time::Duration;
 use std::env;
 use dotenvy::dotenv;
 
-use reqwest::Client;
+use reqwest::{Client, Proxy, Response, StatusCode};
+use serde_json::Value;
 
-use crate::error::AppError;
+use crate::error::{AppError, ExchangeErrorKind};
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -27,11 +28,99 @@ impl CoincheckClient {
             base_url,
             access_key,
             secret_key,
-            client: Client::new(),
+            client: build_http_client()?,
         })
     }
 }
 
+/*
+ * 全APIリクエストで使い回すreqwest::Clientを作成。
+ * コネクションプールを共有し、タイムアウトを設定して、cronが固まらないようにする。
+ *
+ * [envの設定]
+ * COINCHECK_HTTP_CONNECT_TIMEOUT_SECS=5
+ * COINCHECK_HTTP_TIMEOUT_SECS=30
+ * COINCHECK_HTTP_USER_AGENT=coincheck-bot/0.1.0
+ * COINCHECK_HTTP_PROXY=http://proxy.example.com:8080 (任意)
+ */
+pub fn build_http_client() -> Result<Client, AppError> {
+    dotenv().ok();
+
+    let connect_timeout = env_secs("COINCHECK_HTTP_CONNECT_TIMEOUT_SECS", 5)?;
+    let timeout = env_secs("COINCHECK_HTTP_TIMEOUT_SECS", 30)?;
+    let user_agent = env::var("COINCHECK_HTTP_USER_AGENT")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or(format!("coincheck-bot/{}", env!("CARGO_PKG_VERSION")));
+
+    let mut builder = Client::builder()
+        .connect_timeout(connect_timeout)
+        .timeout(timeout)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .user_agent(user_agent);
+
+    // .envに空で書かれている時は、プロキシなしとして扱う
+    if let Some(proxy_url) = env::var("COINCHECK_HTTP_PROXY").ok().filter(|v| !v.trim().is_empty()) {
+        builder = builder.proxy(Proxy::all(proxy_url.trim())?);
+    }
+
+    Ok(builder.build()?)
+}
+
+fn env_secs(key: &str, default: u64) -> Result<Duration, AppError> {
+    let secs = env::var(key)
+        .unwrap_or(default.to_string())
+        .parse::<u64>()
+        .map_err(|e| AppError::InvalidData(format!("Parse error: {}: {}", key, e)))?;
+
+    Ok(Duration::from_secs(secs))
+}
+
+/*
+ * レスポンスのstatusとbodyを読み込む。
+ * メンテナンス中の503などはHTMLが返るので、JSONとして読めなければ本文を文字列のまま返し、
+ * check_responseでstatusから分類できるようにする。
+ */
+pub async fn read_response(res: Response) -> Result<(StatusCode, Value), AppError> {
+    let status = res.status();
+    let text = res.text().await?;
+
+    Ok((status, parse_body(&text)))
+}
+
+pub fn parse_body(text: &str) -> Value {
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.trim().to_string()))
+}
+
+/*
+ * レスポンスのstatusとbodyを見て、失敗ならAppError::Exchangeに変換する。
+ * Coincheckは失敗時に`{"success": false, "error": "..."}`を返す。
+ */
+pub fn check_response(status: StatusCode, body: Value) -> Result<Value, AppError> {
+    let failed = !status.is_success()
+        || body.get("success").and_then(|v| v.as_bool()) == Some(false);
+
+    if !failed {
+        return Ok(body);
+    }
+
+    let message = match body.get("error") {
+        Some(Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+        // HTMLのページなどは長いので先頭だけ
+        None => match &body {
+            Value::String(s) => s.chars().take(200).collect(),
+            _ => body.to_string(),
+        },
+    };
+
+    Err(AppError::Exchange {
+        kind: ExchangeErrorKind::classify(status.as_u16(), &message),
+        message,
+        status: status.as_u16(),
+    })
+}
+
 pub fn sleep() -> Result<(), AppError> {
     dotenv().ok();
 
@@ -43,3 +132,48 @@ pub fn sleep() -> Result<(), AppError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn maintenance_html_is_classified_by_status() {
+        let body = parse_body("<html><body>ただいまメンテナンス中です</body></html>");
+
+        match check_response(StatusCode::SERVICE_UNAVAILABLE, body) {
+            Err(AppError::Exchange { kind, status, .. }) => {
+                assert_eq!(kind, ExchangeErrorKind::Maintenance);
+                assert!(kind.is_retryable());
+                assert_eq!(status, 503);
+            },
+            other => panic!("unexpected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn success_false_is_an_exchange_error() {
+        let body = parse_body(r#"{"success": false, "error": "Amount 0.0001 is less than the minimum"}"#);
+
+        match check_response(StatusCode::OK, body) {
+            Err(AppError::Exchange { kind, message, .. }) => {
+                assert_eq!(kind, ExchangeErrorKind::InvalidAmount);
+                assert_eq!(message, "Amount 0.0001 is less than the minimum");
+            },
+            other => panic!("unexpected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn json_body_is_returned_on_success() {
+        let body = parse_body(r#"{"success": true, "id": 12345}"#);
+
+        assert_eq!(check_response(StatusCode::OK, body).unwrap(), json!({"success": true, "id": 12345}));
+    }
+
+    #[test]
+    fn rate_in_message_is_not_an_invalid_amount() {
+        assert_eq!(ExchangeErrorKind::classify(400, "Failed to generate rate"), ExchangeErrorKind::Unknown);
+    }
+}
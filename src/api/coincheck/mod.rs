@@ -1,6 +1,11 @@
 pub mod private;
+pub mod nonce;
 pub mod rate;
 pub mod balance;
 pub mod ticker;
 pub mod client;
 pub mod order;
+pub mod websocket;
+#[cfg(test)]
+mod websocket_mock;
+pub mod order_book;
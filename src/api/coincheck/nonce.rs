@@ -0,0 +1,156 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use dotenvy::dotenv;
+
+use crate::error::AppError;
+
+/*
+ * private APIのACCESS-NONCEを払い出す。
+ *
+ * 時計が巻き戻った場合(NTPの補正など)や、orderとsummaryが同じキーで
+ * 同時に走った場合でも、必ず前回より大きい値を返す。
+ * 最後に払い出した値をファイルに保存し、ファイルロックでプロセス間の排他を取る。
+ *
+ * [envの設定]
+ * COINCHECK_NONCE_FILE=/var/tmp/coincheck_nonce (任意、未設定ならtmp配下)
+ */
+
+// 同一プロセス内のスレッド間の排他と、ファイルが消された時の保険
+static LAST_NONCE: Mutex<u64> = Mutex::new(0);
+
+pub fn next_nonce() -> Result<u64, AppError> {
+    let mut last = LAST_NONCE
+        .lock()
+        .map_err(|e| AppError::InvalidData(format!("Nonce lock error: {}", e)))?;
+
+    let nonce = issue(&nonce_file_path(), *last, now_nanos()?)?;
+
+    *last = nonce;
+    Ok(nonce)
+}
+
+/*
+ * pathに保存された前回値とlastの大きい方から次のnonceを決めて、pathに書き戻す。
+ */
+fn issue(path: &Path, last: u64, now: u64) -> Result<u64, AppError> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+
+    // ロックはfileのdropで解放される
+    file.lock()?;
+
+    let mut buf = String::new();
+    file.read_to_string(&mut buf)?;
+    let persisted = buf.trim().parse::<u64>().unwrap_or(0);
+
+    let nonce = next_value(now, persisted.max(last));
+
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write!(file, "{}", nonce)?;
+    file.sync_all()?;
+
+    Ok(nonce)
+}
+
+/*
+ * 現在時刻と前回値から次のnonceを決める。
+ * 時計が前回値より進んでいれば時刻を、そうでなければ前回値+1を使う。
+ */
+pub fn next_value(now: u64, last: u64) -> u64 {
+    now.max(last.saturating_add(1))
+}
+
+fn now_nanos() -> Result<u64, AppError> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| AppError::InvalidData(format!("Time error: {}", e)))?
+        .as_nanos();
+
+    u64::try_from(nanos).map_err(|e| AppError::InvalidData(format!("Time error: {}", e)))
+}
+
+fn nonce_file_path() -> PathBuf {
+    dotenv().ok();
+
+    env::var("COINCHECK_NONCE_FILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| env::temp_dir().join("coincheck_nonce"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_nonce_file(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("coincheck_nonce_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn next_value_uses_the_clock_when_it_is_ahead() {
+        assert_eq!(next_value(1_000, 500), 1_000);
+    }
+
+    #[test]
+    fn next_value_increments_when_the_clock_is_behind() {
+        assert_eq!(next_value(500, 1_000), 1_001);
+    }
+
+    #[test]
+    fn next_value_increments_on_equal_timestamps() {
+        assert_eq!(next_value(1_000, 1_000), 1_001);
+        assert_eq!(next_value(1_000, next_value(1_000, 1_000)), 1_002);
+    }
+
+    #[test]
+    fn next_value_does_not_overflow() {
+        assert_eq!(next_value(0, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn issue_persists_the_nonce_and_reloads_it() {
+        let path = temp_nonce_file("reload");
+
+        let first = issue(&path, 0, 1_000).unwrap();
+        assert_eq!(first, 1_000);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1000");
+
+        // 別プロセス(lastが0)で、時計が巻き戻っていても前回値より大きい
+        let second = issue(&path, 0, 900).unwrap();
+        assert_eq!(second, 1_001);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1001");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn issue_prefers_the_in_process_value_when_the_file_is_behind() {
+        let path = temp_nonce_file("behind");
+        std::fs::write(&path, "100").unwrap();
+
+        assert_eq!(issue(&path, 5_000, 200).unwrap(), 5_001);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn issue_treats_a_corrupt_file_as_empty() {
+        let path = temp_nonce_file("corrupt");
+        std::fs::write(&path, "not a number").unwrap();
+
+        assert_eq!(issue(&path, 0, 42).unwrap(), 42);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,8 +1,6 @@
 use chrono::Utc;
 
-use reqwest::Client;
-use serde::Serialize;
-use serde_json::Value;
+use serde::{Serialize, Deserialize};
 use log::{info, error};
 
 use crate::error::AppError;
@@ -51,9 +49,9 @@ pub async fn post_market_order(
     let json_string = serde_json::to_string(&order)?;
 
     let endpoint = format!("{}/api/exchange/orders", coincheck_client.base_url);
-    let headers = private::headers(&endpoint, &coincheck_client, Some(&json_string))?;
+    let headers = private::headers(&endpoint, coincheck_client, Some(&json_string))?;
 
-    let res = Client::new()
+    let res = coincheck_client.client
         .post(&endpoint)
         .headers(headers)
         .header("Content-Type", "application/json")
@@ -61,19 +59,169 @@ pub async fn post_market_order(
         .send()
         .await?;
 
-    let status = res.status();
-    let body: Value = res.json().await?;
+    let (status, body) = client::read_response(res).await?;
 
     let comment = format!("{}, [{}]: {}", new_order.comment.take().unwrap(), status, body);
     new_order.comment = Some(comment);
 
-    if status.is_success() {
-        info!("Status {}: {}", status, body);
-        new_order.api_call_success_at = Some(Utc::now().naive_utc());
-    } else {
+    client::sleep()?;
+
+    // 失敗時もcommentにはレスポンスを残したまま、呼び出し元にエラー種別を返す
+    if let Err(e) = client::check_response(status, body.clone()) {
         error!("Status {}: {}", status, body);
+        return Err(e);
     }
 
-    client::sleep()?;
+    info!("Status {}: {}", status, body);
+    new_order.api_call_success_at = Some(Utc::now().naive_utc());
+
     Ok(new_order.clone())
 }
+
+#[derive(Debug, Serialize)]
+pub struct LimitOrderRequest {
+    pub pair: String,
+    // buy or sell
+    pub order_type: String,
+    pub rate: f64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenOrder {
+    pub id: i64,
+    pub order_type: String,
+    pub rate: Option<String>,
+    pub pair: String,
+    pub pending_amount: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Deserialize)]
+struct FetchOpenOrders {
+    orders: Vec<OpenOrder>,
+}
+
+/*
+ * 指値注文を出して、取引所の注文IDを返す。
+ */
+pub async fn post_limit_order(
+    coincheck_client: &client::CoincheckClient,
+    order: &LimitOrderRequest,
+) -> Result<i64, AppError> {
+    let json_string = serde_json::to_string(order)?;
+
+    let endpoint = format!("{}/api/exchange/orders", coincheck_client.base_url);
+    let headers = private::headers(&endpoint, coincheck_client, Some(&json_string))?;
+
+    let res = coincheck_client.client
+        .post(&endpoint)
+        .headers(headers)
+        .header("Content-Type", "application/json")
+        .body(json_string)
+        .send()
+        .await?;
+
+    let (status, body) = client::read_response(res).await?;
+    let body = client::check_response(status, body)?;
+    info!("Status {}: {}", status, body);
+
+    client::sleep()?;
+
+    body.get("id")
+        .and_then(|v| v.as_i64())
+        .ok_or_else(|| AppError::InvalidData(format!("Order id not found: {}", body)))
+}
+
+pub async fn find_open_orders(
+    coincheck_client: &client::CoincheckClient,
+) -> Result<Vec<OpenOrder>, AppError> {
+    let endpoint = format!("{}/api/exchange/orders/opens", coincheck_client.base_url);
+    let headers = private::headers(&endpoint, coincheck_client, None)?;
+
+    let res = coincheck_client.client
+        .get(&endpoint)
+        .headers(headers)
+        .send()
+        .await?;
+
+    let (status, body) = client::read_response(res).await?;
+    let body = client::check_response(status, body)?;
+
+    client::sleep()?;
+
+    Ok(serde_json::from_value::<FetchOpenOrders>(body)?.orders)
+}
+
+pub async fn cancel_order(
+    coincheck_client: &client::CoincheckClient,
+    order_id: i64,
+) -> Result<(), AppError> {
+    let endpoint = format!("{}/api/exchange/orders/{}", coincheck_client.base_url, order_id);
+    let headers = private::headers(&endpoint, coincheck_client, None)?;
+
+    let res = coincheck_client.client
+        .delete(&endpoint)
+        .headers(headers)
+        .send()
+        .await?;
+
+    let (status, body) = client::read_response(res).await?;
+    client::check_response(status, body)?;
+
+    client::sleep()?;
+    Ok(())
+}
+
+/*
+ * 注文の状態。未約定一覧から消えた注文が、約定したのか取り消し・失効したのかを確認するのに使う。
+ * status: NEW, PARTIALLY_FILLED, FILLED, CANCELED, EXPIRED, PARTIALLY_FILLED_CANCELED, PARTIALLY_FILLED_EXPIRED
+ */
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderDetail {
+    pub id: i64,
+    pub pair: String,
+    pub status: String,
+    pub order_type: String,
+    pub rate: Option<String>,
+    pub executed_amount: Option<String>,
+}
+
+impl OrderDetail {
+    pub fn is_filled(&self) -> bool {
+        self.status == "FILLED"
+    }
+
+    // 取り消し・失効で、もう約定しないもの(一部約定していることもある)
+    pub fn is_closed(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            "CANCELED" | "EXPIRED" | "PARTIALLY_FILLED_CANCELED" | "PARTIALLY_FILLED_EXPIRED"
+        )
+    }
+
+    pub fn executed_amount(&self) -> f64 {
+        self.executed_amount.as_deref().and_then(|a| a.parse::<f64>().ok()).unwrap_or(0.0)
+    }
+}
+
+pub async fn find_order(
+    coincheck_client: &client::CoincheckClient,
+    order_id: i64,
+) -> Result<OrderDetail, AppError> {
+    let endpoint = format!("{}/api/exchange/orders/{}", coincheck_client.base_url, order_id);
+    let headers = private::headers(&endpoint, coincheck_client, None)?;
+
+    let res = coincheck_client.client
+        .get(&endpoint)
+        .headers(headers)
+        .send()
+        .await?;
+
+    let (status, body) = client::read_response(res).await?;
+    let body = client::check_response(status, body)?;
+
+    client::sleep()?;
+
+    Ok(serde_json::from_value::<OrderDetail>(body)?)
+}
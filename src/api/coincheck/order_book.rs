@@ -0,0 +1,48 @@
+use serde::Deserialize;
+
+use crate::api::coincheck::client;
+use crate::error::AppError;
+use crate::models::order_book::OrderBook;
+
+#[derive(Deserialize)]
+struct FetchOrderBook {
+    asks: Vec<(String, String)>,
+    bids: Vec<(String, String)>,
+}
+
+pub async fn find(
+    coincheck_client: &client::CoincheckClient,
+    currency: &str,
+) -> Result<OrderBook, AppError> {
+    let endpoint = format!("{}/api/order_books?pair={}_jpy", coincheck_client.base_url, currency);
+
+    let res = coincheck_client.client
+        .get(&endpoint)
+        .send()
+        .await?;
+
+    let (status, body) = client::read_response(res).await?;
+    let body = client::check_response(status, body)?;
+
+    client::sleep()?;
+
+    let fetched = serde_json::from_value::<FetchOrderBook>(body)?;
+
+    Ok(OrderBook::new(
+        currency.to_string(),
+        parse_levels(fetched.asks)?,
+        parse_levels(fetched.bids)?,
+    ))
+}
+
+fn parse_levels(levels: Vec<(String, String)>) -> Result<Vec<(f64, f64)>, AppError> {
+    levels.iter()
+        .map(|(rate, amount)| {
+            let rate = rate.parse::<f64>()
+                .map_err(|e| AppError::InvalidData(format!("Parse error: {}", e)))?;
+            let amount = amount.parse::<f64>()
+                .map_err(|e| AppError::InvalidData(format!("Parse error: {}", e)))?;
+            Ok((rate, amount))
+        })
+        .collect()
+}
@@ -1,11 +1,9 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use hex;
 use reqwest::header::{HeaderMap, HeaderValue};
 
-use crate::api::coincheck::client::CoincheckClient;
+use crate::api::coincheck::{client::CoincheckClient, nonce};
 use crate::error::AppError;
 
 type HmacSha256 = Hmac<Sha256>;
@@ -15,21 +13,10 @@ pub fn headers(
     client: &CoincheckClient,
     body: Option<&String>,
 ) -> Result<HeaderMap, AppError> {
-    let nonce = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| AppError::InvalidData(format!("Time error: {}", e)))?
-        .as_nanos()
-        .to_string();
-
-    let message = match body {
-        Some(json_body) => format!("{}{}{}", nonce, url, json_body),
-        None => format!("{}{}", nonce, url),
-    };
+    let nonce = nonce::next_nonce()?.to_string();
 
-    let mut mac = HmacSha256::new_from_slice(client.secret_key.as_bytes())
-        .map_err(|e| AppError::InvalidData(format!("Hmac error: {}", e)))?;
-    mac.update(message.as_bytes());
-    let signature = hex::encode(mac.finalize().into_bytes());
+    let message = signing_message(&nonce, url, body.map(|b| b.as_str()));
+    let signature = sign(&client.secret_key, &message)?;
 
     // ヘッダーの作成
     let mut headers = HeaderMap::new();
@@ -48,3 +35,70 @@ pub fn headers(
 
     Ok(headers)
 }
+
+/*
+ * 署名対象の文字列。nonce + URL + body(POST時のみ)を連結する。
+ */
+pub fn signing_message(nonce: &str, url: &str, body: Option<&str>) -> String {
+    match body {
+        Some(json_body) => format!("{}{}{}", nonce, url, json_body),
+        None => format!("{}{}", nonce, url),
+    }
+}
+
+pub fn sign(secret_key: &str, message: &str) -> Result<String, AppError> {
+    let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())
+        .map_err(|e| AppError::InvalidData(format!("Hmac error: {}", e)))?;
+    mac.update(message.as_bytes());
+
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signing_message_concatenates_nonce_url_and_body() {
+        let url = "https://coincheck.com/api/exchange/orders";
+        let body = r#"{"pair":"btc_jpy"}"#;
+
+        assert_eq!(
+            signing_message("1700000000000", url, Some(body)),
+            r#"1700000000000https://coincheck.com/api/exchange/orders{"pair":"btc_jpy"}"#,
+        );
+    }
+
+    #[test]
+    fn signing_message_without_body_is_nonce_and_url() {
+        let url = "https://coincheck.com/api/accounts/balance";
+
+        assert_eq!(
+            signing_message("1700000000000", url, None),
+            "1700000000000https://coincheck.com/api/accounts/balance",
+        );
+    }
+
+    // RFC 4231 test case 2
+    #[test]
+    fn sign_matches_known_hmac_sha256_vector() {
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?").unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        );
+    }
+
+    #[test]
+    fn sign_signs_the_signing_message() {
+        let message = signing_message(
+            "1700000000000",
+            "https://coincheck.com/api/exchange/orders",
+            Some(r#"{"pair":"btc_jpy"}"#),
+        );
+
+        assert_eq!(
+            sign("secret", &message).unwrap(),
+            "46da4347aa27658ce13e377ca4313129d8e775ead1b223a89535527821d5b556",
+        );
+    }
+}
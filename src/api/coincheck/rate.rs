@@ -1,4 +1,3 @@
-use reqwest;
 use serde::Deserialize;
 
 use crate::api::coincheck::client;
@@ -38,8 +37,8 @@ pub async fn find(client: &client::CoincheckClient, currency: &str) -> Result<Ra
         &currency
     );
 
-    let buy_rate = reqwest::get(&buy_endpoint).await?.json::<FetchRate>().await?.to_f64()?;
-    let sell_rate = reqwest::get(&sell_endpoint).await?.json::<FetchRate>().await?.to_f64()?;
+    let buy_rate = client.client.get(&buy_endpoint).send().await?.json::<FetchRate>().await?.to_f64()?;
+    let sell_rate = client.client.get(&sell_endpoint).send().await?.json::<FetchRate>().await?.to_f64()?;
     let spread_ratio = ((buy_rate - sell_rate) / sell_rate) * 100.0;
 
     let rate = Rate {
@@ -1,7 +1,5 @@
 use dotenvy::dotenv;
 
-use reqwest::Client;
-
 use crate::error::AppError;
 use crate::api::coincheck::client;
 use crate::models::ticker::NewTicker;
@@ -16,7 +14,7 @@ pub async fn find(
     let path = format!("/api/ticker?pair={}_jpy", currency);
     let endpoint = format!("{}{}", coincheck_client.base_url, path);
 
-    let ticker = Client::new()
+    let ticker = coincheck_client.client
         .get(&endpoint)
         .send()
         .await?
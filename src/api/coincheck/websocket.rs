@@ -0,0 +1,407 @@
+use std::env;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc, TimeZone};
+use dotenvy::dotenv;
+use futures_util::{SinkExt, StreamExt};
+use log::{info, error};
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use crate::error::AppError;
+
+/*
+ * Coincheckのpublic WebSocket(`{pair}-trades`, `{pair}-orderbook`)を購読して、
+ * パース済みのイベントをbroadcastチャンネルに流す。
+ *
+ * 切断された場合は、バックオフしながら再接続して、全チャンネルを再購読する。
+ * 定期的にpingを送り、一定時間何も受信しなければ(半開きの接続)切断として扱う。
+ * バックオフは購読に成功した時点で最初の間隔に戻す。
+ * 受信側はWebSocketFeed::subscribe()で何本でもReceiverを取れるので、
+ * ローソク足の生成や戦略からそれぞれ購読できる。
+ *
+ * [envの設定]
+ * COINCHECK_WS_URL=wss://ws-api.coincheck.com/
+ * COINCHECK_WS_PING_SECS=30
+ * COINCHECK_WS_READ_TIMEOUT_SECS=90 (pongも含め、これだけ受信がなければ再接続)
+ */
+
+const DEFAULT_WS_URL: &str = "wss://ws-api.coincheck.com/";
+
+#[derive(Debug, Clone, Copy)]
+pub struct FeedOptions {
+    pub ping_interval: Duration,
+    pub read_timeout: Duration,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(30),
+            read_timeout: Duration::from_secs(90),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl FeedOptions {
+    pub fn from_env() -> Result<Self, AppError> {
+        let secs = |key: &str, default: Duration| -> Result<Duration, AppError> {
+            match env::var(key) {
+                Ok(value) => value.parse::<u64>()
+                    .map(Duration::from_secs)
+                    .map_err(|e| AppError::InvalidData(format!("Parse error: {}: {}", key, e))),
+                Err(_) => Ok(default),
+            }
+        };
+
+        let default = Self::default();
+        Ok(Self {
+            ping_interval: secs("COINCHECK_WS_PING_SECS", default.ping_interval)?,
+            read_timeout: secs("COINCHECK_WS_READ_TIMEOUT_SECS", default.read_timeout)?,
+            ..default
+        })
+    }
+}
+
+// 再接続までの待ち時間。失敗する度に倍にして、max_backoffで止める
+#[derive(Debug)]
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    fn new(initial: Duration, max: Duration) -> Self {
+        Self { initial, max, current: initial }
+    }
+
+    fn next(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Channel {
+    Trades(String),
+    OrderBook(String),
+}
+
+impl Channel {
+    pub fn name(&self) -> String {
+        match self {
+            Channel::Trades(currency) => format!("{}_jpy-trades", currency),
+            Channel::OrderBook(currency) => format!("{}_jpy-orderbook", currency),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub id: String,
+    pub pair: String,
+    pub rate: f64,
+    pub amount: f64,
+    pub order_type: String,
+    pub timestamp: NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderBookUpdate {
+    pub pair: String,
+    // (rate, amount)。amountが0のレベルは板から消えたことを表す
+    pub bids: Vec<(f64, f64)>,
+    pub asks: Vec<(f64, f64)>,
+    pub last_update_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone)]
+pub enum WsEvent {
+    Trade(Trade),
+    OrderBook(OrderBookUpdate),
+}
+
+pub struct WebSocketFeed {
+    sender: broadcast::Sender<WsEvent>,
+    handle: JoinHandle<()>,
+}
+
+impl WebSocketFeed {
+    pub fn connect(channels: Vec<Channel>) -> Result<Self, AppError> {
+        dotenv().ok();
+
+        let url = env::var("COINCHECK_WS_URL").unwrap_or(DEFAULT_WS_URL.to_string());
+        Ok(Self::connect_to(url, channels, FeedOptions::from_env()?))
+    }
+
+    pub fn connect_to(url: String, channels: Vec<Channel>, options: FeedOptions) -> Self {
+        let (sender, _) = broadcast::channel(1024);
+        let handle = tokio::spawn(run(url, channels, sender.clone(), options));
+
+        Self { sender, handle }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WsEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn close(self) {
+        self.handle.abort();
+    }
+}
+
+async fn run(
+    url: String,
+    channels: Vec<Channel>,
+    sender: broadcast::Sender<WsEvent>,
+    options: FeedOptions,
+) {
+    let mut backoff = Backoff::new(options.initial_backoff, options.max_backoff);
+
+    loop {
+        match stream_once(&url, &channels, &sender, &options, &mut backoff).await {
+            Ok(()) => info!("WebSocket closed by server: {}", url),
+            Err(e) => error!("WebSocket error: {}", e),
+        }
+
+        let delay = backoff.next();
+        info!("WebSocket reconnecting in {:?}", delay);
+        time::sleep(delay).await;
+    }
+}
+
+async fn stream_once(
+    url: &str,
+    channels: &[Channel],
+    sender: &broadcast::Sender<WsEvent>,
+    options: &FeedOptions,
+    backoff: &mut Backoff,
+) -> Result<(), AppError> {
+    let (mut ws, _) = connect_async(url)
+        .await
+        .map_err(|e| AppError::InvalidData(format!("WebSocket connect error: {}", e)))?;
+
+    for channel in channels.iter() {
+        let subscribe = json!({ "type": "subscribe", "channel": channel.name() });
+        ws.send(Message::Text(subscribe.to_string()))
+            .await
+            .map_err(|e| AppError::InvalidData(format!("WebSocket send error: {}", e)))?;
+    }
+    info!("WebSocket subscribed: {:?}", channels.iter().map(|c| c.name()).collect::<Vec<_>>());
+    backoff.reset();
+
+    let mut ping = time::interval_at(Instant::now() + options.ping_interval, options.ping_interval);
+    let mut last_read_at = Instant::now();
+
+    loop {
+        let message = tokio::select! {
+            message = ws.next() => message,
+            _ = ping.tick() => {
+                ws.send(Message::Ping(Vec::new()))
+                    .await
+                    .map_err(|e| AppError::InvalidData(format!("WebSocket ping error: {}", e)))?;
+                continue;
+            },
+            _ = time::sleep_until(last_read_at + options.read_timeout) => {
+                return Err(AppError::InvalidData(format!("WebSocket read timeout: {:?}", options.read_timeout)));
+            },
+        };
+
+        let Some(message) = message else {
+            break;
+        };
+        let message = message
+            .map_err(|e| AppError::InvalidData(format!("WebSocket read error: {}", e)))?;
+        last_read_at = Instant::now();
+
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        match parse_message(&text) {
+            // 受信側がいなくても接続は維持する
+            Ok(events) => events.into_iter().for_each(|event| { let _ = sender.send(event); }),
+            Err(e) => error!("WebSocket parse error: {}: {}", e, text),
+        }
+    }
+
+    Ok(())
+}
+
+/*
+ * 受信したメッセージをイベントに変換する。
+ *
+ * trades:    [["1663318663","2357062","btc_jpy","2820896.0","5.0","sell","1193401","2078767","0"], ...]
+ * orderbook: ["btc_jpy",{"bids":[["148634.0","0"]],"asks":[["148834.0","0.0215"]],"last_update_at":"1659321701"}]
+ */
+pub fn parse_message(text: &str) -> Result<Vec<WsEvent>, AppError> {
+    let value: Value = serde_json::from_str(text)?;
+    let Some(items) = value.as_array() else {
+        return Err(AppError::InvalidData("WebSocket message is not an array".to_string()));
+    };
+
+    match (items.first(), items.get(1)) {
+        (Some(Value::String(pair)), Some(Value::Object(book))) => {
+            Ok(vec![WsEvent::OrderBook(OrderBookUpdate {
+                pair: pair.clone(),
+                bids: parse_levels(book.get("bids")),
+                asks: parse_levels(book.get("asks")),
+                last_update_at: book.get("last_update_at")
+                    .and_then(as_f64)
+                    .and_then(|t| unix_to_naive(t as i64)),
+            })])
+        },
+        (Some(Value::Array(_)), _) => {
+            items.iter()
+                .filter_map(|trade| trade.as_array())
+                .map(|trade| parse_trade(trade).map(WsEvent::Trade))
+                .collect()
+        },
+        _ => Err(AppError::InvalidData("Unknown WebSocket message".to_string())),
+    }
+}
+
+fn parse_trade(fields: &[Value]) -> Result<Trade, AppError> {
+    let field = |i: usize| fields.get(i)
+        .ok_or_else(|| AppError::InvalidData(format!("Trade field {} missing", i)));
+
+    let timestamp = as_f64(field(0)?)
+        .and_then(|t| unix_to_naive(t as i64))
+        .unwrap_or(Utc::now().naive_utc());
+
+    Ok(Trade {
+        id: as_string(field(1)?),
+        pair: as_string(field(2)?),
+        rate: as_f64(field(3)?)
+            .ok_or_else(|| AppError::InvalidData("Trade rate is not a number".to_string()))?,
+        amount: as_f64(field(4)?)
+            .ok_or_else(|| AppError::InvalidData("Trade amount is not a number".to_string()))?,
+        order_type: as_string(field(5)?),
+        timestamp,
+    })
+}
+
+fn parse_levels(levels: Option<&Value>) -> Vec<(f64, f64)> {
+    levels
+        .and_then(|v| v.as_array())
+        .map(|levels| {
+            levels.iter()
+                .filter_map(|level| {
+                    let level = level.as_array()?;
+                    Some((as_f64(level.first()?)?, as_f64(level.get(1)?)?))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Coincheckは数値を文字列で返すことが多いので、両方受け付ける
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(s) => s.parse::<f64>().ok(),
+        v => v.as_f64(),
+    }
+}
+
+fn as_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+fn unix_to_naive(timestamp: i64) -> Option<NaiveDateTime> {
+    Utc.timestamp_opt(timestamp, 0).single().map(|t| t.naive_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::coincheck::websocket_mock::MockWsServer;
+
+    const TRADE: &str = r#"[["1663318663","2357062","btc_jpy","2820896.0","5.0","sell","1193401","2078767","0"]]"#;
+
+    fn fast_options() -> FeedOptions {
+        FeedOptions {
+            ping_interval: Duration::from_millis(50),
+            read_timeout: Duration::from_millis(300),
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+        }
+    }
+
+    async fn wait_for_subscriptions(server: &MockWsServer, count: usize) -> Vec<String> {
+        time::timeout(Duration::from_secs(5), async {
+            loop {
+                let subscriptions = server.subscriptions();
+                if subscriptions.len() >= count {
+                    return subscriptions;
+                }
+                time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("subscriptions not received")
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_and_resets() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+
+        let delays: Vec<u64> = (0..5).map(|_| backoff.next().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+
+        backoff.reset();
+        assert_eq!(backoff.next(), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn reconnects_and_resubscribes_after_server_close() {
+        let server = MockWsServer::start(vec![TRADE.to_string()]).await.unwrap();
+        let channels = vec![Channel::Trades("btc".to_string()), Channel::OrderBook("btc".to_string())];
+        let feed = WebSocketFeed::connect_to(server.url(), channels, fast_options());
+        let mut events = feed.subscribe();
+
+        // サーバーは1件送る度に切断するので、2件目は再接続後に届く
+        for _ in 0..2 {
+            match time::timeout(Duration::from_secs(5), events.recv()).await {
+                Ok(Ok(WsEvent::Trade(trade))) => assert_eq!(trade.pair, "btc_jpy"),
+                other => panic!("unexpected: {:?}", other),
+            }
+        }
+
+        let subscriptions = wait_for_subscriptions(&server, 4).await;
+        assert_eq!(subscriptions[..4], ["btc_jpy-trades", "btc_jpy-orderbook", "btc_jpy-trades", "btc_jpy-orderbook"]);
+
+        feed.close();
+        server.stop();
+    }
+
+    #[tokio::test]
+    async fn reconnects_when_half_open_connection_times_out() {
+        let server = MockWsServer::silent().await.unwrap();
+        let feed = WebSocketFeed::connect_to(server.url(), vec![Channel::Trades("btc".to_string())], fast_options());
+
+        // pingに応答がないまま、read_timeoutで切断して再購読する
+        let subscriptions = wait_for_subscriptions(&server, 2).await;
+        assert_eq!(subscriptions[..2], ["btc_jpy-trades", "btc_jpy-trades"]);
+
+        feed.close();
+        server.stop();
+    }
+}
@@ -0,0 +1,117 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use log::error;
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+use crate::error::AppError;
+
+/*
+ * テスト用のCoincheck public WebSocketの代役。
+ *
+ * 接続毎に購読メッセージを受け取って記録し、用意したメッセージを全て送ったら切断する。
+ * 切断でクライアントの再接続と再購読が走るので、その確認にも使える。
+ * silent()は購読を記録した後、何も送らず読みもしない(pingにも応答しない半開きの接続)。
+ *
+ * let server = MockWsServer::start(vec![r#"[["1663318663","1","btc_jpy","100.0","0.1","buy"]]"#.to_string()]).await?;
+ * let feed = WebSocketFeed::connect_to(server.url(), vec![Channel::Trades("btc".to_string())], FeedOptions::default());
+ */
+
+// 購読メッセージを待つ時間。過ぎたら受け取った分だけで送信を始める
+const SUBSCRIBE_WAIT_MILLIS: u64 = 200;
+
+pub struct MockWsServer {
+    addr: SocketAddr,
+    subscriptions: Arc<Mutex<Vec<String>>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockWsServer {
+    pub async fn start(messages: Vec<String>) -> Result<Self, AppError> {
+        Self::start_with(messages, false).await
+    }
+
+    pub async fn silent() -> Result<Self, AppError> {
+        Self::start_with(Vec::new(), true).await
+    }
+
+    async fn start_with(messages: Vec<String>, silent: bool) -> Result<Self, AppError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let subscriptions = Arc::new(Mutex::new(Vec::new()));
+        let messages = Arc::new(messages);
+
+        let recorded = subscriptions.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (messages, recorded) = (messages.clone(), recorded.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &messages, silent, &recorded).await {
+                        error!("Mock WebSocket error: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { addr, subscriptions, handle })
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}/", self.addr)
+    }
+
+    // これまでに受け取った購読チャンネル名(再接続分も含む)
+    pub fn subscriptions(&self) -> Vec<String> {
+        self.subscriptions.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+async fn serve(
+    stream: tokio::net::TcpStream,
+    messages: &[String],
+    silent: bool,
+    subscriptions: &Arc<Mutex<Vec<String>>>,
+) -> Result<(), AppError> {
+    let mut ws = accept_async(stream)
+        .await
+        .map_err(|e| AppError::InvalidData(format!("Mock WebSocket accept error: {}", e)))?;
+
+    while let Ok(Some(Ok(Message::Text(text)))) =
+        tokio::time::timeout(Duration::from_millis(SUBSCRIBE_WAIT_MILLIS), ws.next()).await
+    {
+        let channel = serde_json::from_str::<Value>(&text)?
+            .get("channel")
+            .and_then(|c| c.as_str())
+            .map(|c| c.to_string());
+
+        if let (Some(channel), Ok(mut subscriptions)) = (channel, subscriptions.lock()) {
+            subscriptions.push(channel);
+        }
+    }
+
+    if silent {
+        // 接続は保持したまま放置する(abortで落ちるまで)
+        std::future::pending::<()>().await;
+    }
+
+    for message in messages.iter() {
+        ws.send(Message::Text(message.clone()))
+            .await
+            .map_err(|e| AppError::InvalidData(format!("Mock WebSocket send error: {}", e)))?;
+    }
+
+    ws.close(None)
+        .await
+        .map_err(|e| AppError::InvalidData(format!("Mock WebSocket close error: {}", e)))?;
+
+    Ok(())
+}
@@ -1,2 +1,4 @@
 pub mod coincheck;
 pub mod slack;
+#[cfg(test)]
+mod slack_mock;
@@ -1,102 +1,158 @@
 use std::env;
+use std::path::Path;
 use dotenvy::dotenv;
 
-use reqwest::Client;
-use serde_json::json;
+use log::info;
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
 
 use crate::error::AppError;
-use crate::models::summary::NewSummary;
-use crate::models::order::NewOrder;
-use crate::models::summary_record::NewSummaryRecord;
-
-pub async fn send_orderd_information(new_order: &NewOrder) -> Result<(), AppError> {
-    dotenv().ok();
-
-    let text = if new_order.order_type == "market_buy" {
-        format!(
-            ":coin: *[{}][購入]* {}JPY",
-            {new_order.pair.to_uppercase()},
-            {format!("{:.2}", new_order.jpy_amount)}
-        )
-    } else {
-        format!(
-            ":coin: *[{}][売却]* {}",
-            {new_order.pair.to_uppercase()},
-            {new_order.crypto_amount}
-        )
-    };
-
-    let url = env::var("SLACK_INCOMMING_WEBHOOK_URL")?;
-    let client = Client::new();
 
-    let payload = json!({
-        "blocks": [
-            {
-                "type": "section",
-                "text": {
-                    "type": "mrkdwn",
-                    "text": text
-                }
-            }
-        ]
-    });
+/*
+ * SlackのincomingウェブフックとWeb APIのクライアント。
+ * 何をどこに送るかはnotifierで決めて、ここは送るだけ。
+ *
+ * [envの設定]
+ * SLACK_INCOMMING_WEBHOOK_URL=
+ * SLACK_BOT_TOKEN= (xoxb-...。files:writeとchat:writeが必要。未設定ならwebhookでテキストのみ)
+ * SLACK_CHANNEL= (bot tokenで送る先のチャンネルID)
+ * SLACK_API_URL=https://slack.com/api/ (モックサーバーに向ける時に変える)
+ */
+
+pub const DEFAULT_API_URL: &str = "https://slack.com/api/";
 
-    let _res = client.post(url).json(&payload).send().await?;
+pub async fn post_webhook(url: &str, payload: &Value) -> Result<(), AppError> {
+    let client = Client::new();
+    let _res = client.post(url).json(payload).send().await?.error_for_status()?;
 
     Ok(())
 }
 
-#[allow(dead_code)]
-pub async fn send_summary(
-    title: &str, 
-    new_summary: &NewSummary,
-    new_summary_records: Vec<NewSummaryRecord>
-) -> Result<(), AppError> {
-    dotenv().ok();
-
-    let url = env::var("SLACK_INCOMMING_WEBHOOK_URL")?;
-    let client = Client::new();
-
-    let total_jpy_value = new_summary.total_jpy_value.round() as i32;
-    let pl = new_summary.pl.round() as i32;
-
-    let fields = make_currency_fields(new_summary_records);
-    let payload = json!({
-	    "blocks": [
-	    	{
-                "type": "section",
-                "text": {
-                    "type": "mrkdwn",
-                    "text": format!(
-                        ":moneybag: *{}*\n *Total invested:* {}円\n *Total JPY value:* {}円\n *P/L:* {}円",
-                        title,
-                        new_summary.total_invested,
-                        total_jpy_value,
-                        pl,
-                    )
-                }
-	    	},
-            {
-                "type": "section",
-                "fields": fields
-            }
-	    ]
-    });
-
-    let _res = client.post(&url).json(&payload).send().await?.error_for_status()?;
+/*
+ * bot tokenでWeb APIを呼ぶクライアント。
+ * Slackはエラーでも200で{"ok": false, "error": ...}を返すので、okを見てエラーにする。
+ */
+pub struct SlackBot {
+    pub token: String,
+    pub channel: String,
+    pub api_url: String,
+}
 
-    Ok(())
+impl SlackBot {
+    pub fn new(token: String, channel: String, api_url: String) -> Self {
+        Self { token, channel, api_url }
+    }
+
+    // SLACK_BOT_TOKENが未設定(または空)ならNone
+    pub fn from_env() -> Result<Option<Self>, AppError> {
+        dotenv().ok();
+
+        let token = env::var("SLACK_BOT_TOKEN").unwrap_or_default();
+        if token.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::new(
+            token,
+            env::var("SLACK_CHANNEL")?,
+            env::var("SLACK_API_URL").unwrap_or(DEFAULT_API_URL.to_string()),
+        )))
+    }
+
+    fn endpoint(&self, method: &str) -> String {
+        format!("{}/{}", self.api_url.trim_end_matches('/'), method)
+    }
+
+    async fn call(&self, request: RequestBuilder) -> Result<Value, AppError> {
+        let body: Value = request
+            .bearer_auth(&self.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if body.get("ok").and_then(|ok| ok.as_bool()) != Some(true) {
+            return Err(AppError::InvalidData(format!("Slack API error: {}", body)));
+        }
+
+        Ok(body)
+    }
+
+    // payloadにchannelを足して、chat.postMessageで送る
+    pub async fn post_message(&self, mut payload: Value) -> Result<Value, AppError> {
+        payload["channel"] = json!(self.channel);
+
+        let client = Client::new();
+        self.call(client.post(self.endpoint("chat.postMessage")).json(&payload)).await
+    }
+
+    /*
+     * files.getUploadURLExternalで取ったURLにファイル毎にアップロードしてから、
+     * files.completeUploadExternalでまとめてチャンネルに共有する。
+     */
+    pub async fn upload_files(&self, paths: &[&Path], comment: &str) -> Result<(), AppError> {
+        let client = Client::new();
+        let mut files = Vec::new();
+
+        for path in paths.iter() {
+            let bytes = std::fs::read(path)?;
+            let filename = path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or("chart.png".to_string());
+
+            let upload = self.call(client.post(self.endpoint("files.getUploadURLExternal"))
+                .form(&[("filename", filename.clone()), ("length", bytes.len().to_string())]))
+                .await?;
+            let (Some(upload_url), Some(file_id)) = (
+                upload.get("upload_url").and_then(|u| u.as_str()),
+                upload.get("file_id").and_then(|f| f.as_str()),
+            ) else {
+                return Err(AppError::InvalidData(format!("Slack upload url not found: {}", upload)));
+            };
+
+            client.post(upload_url).body(bytes).send().await?.error_for_status()?;
+            files.push(json!({"id": file_id, "title": filename}));
+        }
+
+        let payload = json!({
+            "files": files,
+            "channel_id": self.channel,
+            "initial_comment": comment,
+        });
+        self.call(client.post(self.endpoint("files.completeUploadExternal")).json(&payload)).await?;
+
+        info!("Slack uploaded {} files", paths.len());
+        Ok(())
+    }
 }
 
-#[allow(dead_code)]
-pub fn make_currency_fields(new_summary_records: Vec<NewSummaryRecord>) -> serde_json::Value {
-    let mut fields: Vec<serde_json::Value> = Vec::new();
-    for record in new_summary_records {
-        fields.push(json!({
-            "type": "mrkdwn",
-            "text": format!("*{}*\n{}", record.currency.to_uppercase(), record.amount)
-        }));
-    };
-
-    json!(fields)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::slack_mock::MockSlackServer;
+
+    #[tokio::test]
+    async fn upload_files_gets_url_uploads_and_completes() {
+        let server = MockSlackServer::start().await.unwrap();
+        let path = env::temp_dir().join(format!("slack_upload_test_{}.png", std::process::id()));
+        std::fs::write(&path, b"png").unwrap();
+
+        let bot = SlackBot::new("xoxb-test".to_string(), "C0123".to_string(), server.api_url());
+        bot.upload_files(&[path.as_path()], "チャート").await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let requests = server.requests();
+        let paths: Vec<&str> = requests.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, ["/api/files.getUploadURLExternal", "/upload/F1", "/api/files.completeUploadExternal"]);
+        assert!(requests.iter().all(|r| r.method == "POST"));
+        assert_eq!(requests[0].authorization.as_deref(), Some("Bearer xoxb-test"));
+        assert_eq!(requests[1].body, b"png");
+
+        let complete: Value = serde_json::from_slice(&requests[2].body).unwrap();
+        assert_eq!(complete["channel_id"], "C0123");
+        assert_eq!(complete["files"][0]["id"], "F1");
+
+        server.stop();
+    }
 }
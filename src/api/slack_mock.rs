@@ -0,0 +1,141 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use log::error;
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::error::AppError;
+
+/*
+ * テスト用のSlack Web APIの代役。
+ *
+ * chat.postMessage / files.getUploadURLExternal / アップロード先 / files.completeUploadExternal に
+ * ok: trueを返し、受け取ったリクエストを順に記録する。
+ * keep-aliveで同じ接続に続けて来るリクエストにも応答する。
+ *
+ * let server = MockSlackServer::start().await?;
+ * let bot = SlackBot::new("xoxb-test".to_string(), "C0123".to_string(), server.api_url());
+ * bot.upload_files(&[Path::new("chart_images/equity.png")], "チャート").await?;
+ * let paths: Vec<String> = server.requests().iter().map(|r| r.path.clone()).collect();
+ */
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    // クエリ文字列を除いたパス
+    pub path: String,
+    pub authorization: Option<String>,
+    pub body: Vec<u8>,
+}
+
+pub struct MockSlackServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockSlackServer {
+    pub async fn start() -> Result<Self, AppError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, addr, &recorded).await {
+                        error!("Mock Slack error: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { addr, requests, handle })
+    }
+
+    // SLACK_API_URLに設定するURL
+    pub fn api_url(&self) -> String {
+        format!("http://{}/api/", self.addr)
+    }
+
+    // これまでに受け取ったリクエスト(古い順)
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    pub fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+async fn serve(
+    stream: TcpStream,
+    addr: SocketAddr,
+    requests: &Arc<Mutex<Vec<MockRequest>>>,
+) -> Result<(), AppError> {
+    let mut reader = BufReader::new(stream);
+
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await? == 0 {
+            return Ok(());
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().split('?').next().unwrap_or_default().to_string();
+
+        let mut content_length = 0;
+        let mut authorization = None;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).await?;
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':') {
+                match name.to_lowercase().as_str() {
+                    "content-length" => content_length = value.trim().parse().unwrap_or(0),
+                    "authorization" => authorization = Some(value.trim().to_string()),
+                    _ => {},
+                }
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await?;
+
+        let count = requests.lock().map(|mut r| {
+            r.push(MockRequest { method, path: path.clone(), authorization, body });
+            r.len()
+        }).unwrap_or(0);
+
+        let response = match path.as_str() {
+            "/api/files.getUploadURLExternal" => json!({
+                "ok": true,
+                "upload_url": format!("http://{}/upload/F{}", addr, count),
+                "file_id": format!("F{}", count),
+            }),
+            p if p.starts_with("/upload/") => json!({"ok": true}),
+            "/api/chat.postMessage" => json!({"ok": true, "ts": format!("{}.000100", count)}),
+            "/api/files.completeUploadExternal" => json!({"ok": true, "files": []}),
+            _ => json!({"ok": false, "error": "unknown_method"}),
+        }
+        .to_string();
+
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            response.len(),
+        );
+        let stream = reader.get_mut();
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(response.as_bytes()).await?;
+        stream.flush().await?;
+    }
+}
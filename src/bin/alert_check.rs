@@ -0,0 +1,45 @@
+use dotenvy::dotenv;
+
+use log::error;
+use simplelog::{Config, LevelFilter, SimpleLogger};
+
+use coincheck::error::AppError;
+use coincheck::db::establish_connection;
+use coincheck::api;
+use coincheck::models::ticker::Ticker;
+use coincheck::notifier;
+use coincheck::repositories;
+
+/*
+ * cargo run --bin alert_check で、取引中の通貨のtickerの停止と、最新のtickerのスプレッドを確認
+ */
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
+
+    let result = run().await;
+    if let Err(e) = &result {
+        error!("Error occurred: {}", e);
+    }
+    repositories::alert::record_job("alert_check", &result).await;
+}
+
+async fn run() -> Result<(), AppError> {
+    let pool = establish_connection();
+    let mut conn = pool.get()?;
+    let client = api::coincheck::client::CoincheckClient::new()?;
+    let notifier = notifier::router::NotifierRouter::from_env()?;
+    let config = repositories::alert::AlertConfig::from_env()?;
+
+    let currencies = repositories::balance::my_trading_currencies(&client).await?;
+    repositories::alert::check_stale_tickers(&mut conn, &notifier, &config, &currencies).await?;
+
+    for currency in currencies.iter() {
+        if let Some((_, bid, ask)) = Ticker::latest_quote(&mut conn, currency)? {
+            repositories::alert::check_spread(&mut conn, &notifier, &config, currency, bid, ask).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use dotenvy::dotenv;
+
+use log::{error, info};
+use simplelog::{Config, LevelFilter, SimpleLogger};
+
+use coincheck::error::AppError;
+use coincheck::db::establish_connection;
+use coincheck::repositories;
+
+/*
+ * cargo run --bin chart で、価格・資産推移・配分のチャートをCHART_DIRに書き出す
+ */
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
+
+    let result = run().await;
+    if let Err(e) = &result {
+        error!("Error occurred: {}", e);
+    }
+    repositories::alert::record_job("chart", &result).await;
+}
+
+async fn run() -> Result<(), AppError> {
+    let pool = establish_connection();
+    let mut conn = pool.get()?;
+
+    for path in repositories::chart::render_all(&mut conn)?.iter() {
+        info!("chart: {}", path.display());
+    }
+    Ok(())
+}
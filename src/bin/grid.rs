@@ -0,0 +1,31 @@
+use dotenvy::dotenv;
+
+use log::error;
+use simplelog::{Config, LevelFilter, SimpleLogger};
+
+use coincheck::error::AppError;
+use coincheck::db::establish_connection;
+use coincheck::api;
+use coincheck::repositories;
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
+
+    let result = run().await;
+    if let Err(e) = &result {
+        error!("Error occurred: {}", e);
+    }
+    repositories::alert::record_job("grid", &result).await;
+}
+
+async fn run() -> Result<(), AppError> {
+    let pool = establish_connection();
+    let mut conn = pool.get().expect("Failed to get DB connection");
+    let client = api::coincheck::client::CoincheckClient::new()?;
+
+    repositories::grid::run(&mut conn, &client).await?;
+
+    Ok(())
+}
@@ -30,17 +30,16 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut conn = pool.get().expect("Failed to get DB connection");
 
     let file_path = "./transactions.csv";
-    let file = File::open(&file_path)?;
+    let file = File::open(file_path)?;
     let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
 
     let mut order_id = 1;
     for result in rdr.deserialize::<CsvTransaction>() {
         let record: CsvTransaction = result?;
 
-        let operation: String;
-        match record.operation.as_str() {
-            "Buy" => { operation = "buy".to_string() },
-            "Sell" => { operation = "sell".to_string() },
+        let operation = match record.operation.as_str() {
+            "Buy" => "buy".to_string(),
+            "Sell" => "sell".to_string(),
             _ => continue
         };
 
@@ -74,7 +73,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 
         let _ = Transaction::create(&mut conn, new_transaction);
 
-        order_id = order_id + 1;
+        order_id += 1;
     }
 
     Ok(())
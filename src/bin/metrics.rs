@@ -0,0 +1,44 @@
+use std::env;
+use dotenvy::dotenv;
+
+use log::{error, info};
+use simplelog::{Config, LevelFilter, SimpleLogger};
+
+use coincheck::error::AppError;
+use coincheck::db::establish_connection;
+use coincheck::repositories;
+
+/*
+ * cargo run --bin metrics で、直近の成績をバイ&ホールドと並べて表示
+ * cargo run --bin metrics -- --json で、JSONを出力
+ */
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
+
+    let result = run().await;
+    if let Err(e) = &result {
+        error!("Error occurred: {}", e);
+    }
+    repositories::alert::record_job("metrics", &result).await;
+}
+
+async fn run() -> Result<(), AppError> {
+    let json = env::args().any(|arg| arg == "--json");
+
+    let pool = establish_connection();
+    let mut conn = pool.get()?;
+
+    let Some(metrics) = repositories::metrics::calculate(&mut conn)? else {
+        info!("summariesが2件未満のため、計算できません");
+        return Ok(());
+    };
+
+    if json {
+        println!("{}", metrics.to_json()?);
+    } else {
+        println!("{}", metrics.to_table());
+    }
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use dotenvy::dotenv;
+
+use log::error;
+use simplelog::{Config, LevelFilter, SimpleLogger};
+
+use coincheck::error::AppError;
+use coincheck::db::establish_connection;
+use coincheck::repositories;
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
+
+    let result = run().await;
+    if let Err(e) = &result {
+        error!("Error occurred: {}", e);
+    }
+    repositories::alert::record_job("optimization_drift", &result).await;
+}
+
+async fn run() -> Result<(), AppError> {
+    let pool = establish_connection();
+    let mut conn = pool.get()?;
+
+    repositories::optimized_ma::drift_report(&mut conn)?;
+    Ok(())
+}
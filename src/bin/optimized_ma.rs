@@ -2,10 +2,10 @@ use dotenvy::dotenv;
 
 use log::error;
 use simplelog::{Config, LevelFilter, SimpleLogger};
-use tokio;
 
 use coincheck::error::AppError;
 use coincheck::db::establish_connection;
+use coincheck::api;
 use coincheck::repositories;
 
 #[tokio::main]
@@ -13,15 +13,18 @@ async fn main() {
     dotenv().ok();
     SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
 
-    if let Err(e) = run().await {
+    let result = run().await;
+    if let Err(e) = &result {
         error!("Error occurred: {}", e);
     }
+    repositories::alert::record_job("optimized_ma", &result).await;
 }
 
 async fn run() -> Result<(), AppError> {
     let pool = establish_connection();
     let mut conn = pool.get().expect("Failed to get DB connection");
+    let client = api::coincheck::client::CoincheckClient::new()?;
 
-    repositories::optimized_ma::calc_crossover(&mut conn).await?;
+    repositories::optimized_ma::calc_crossover(&mut conn, &client).await?;
     Ok(())
 }
@@ -2,11 +2,11 @@ use dotenvy::dotenv;
 
 use log::error;
 use simplelog::{Config, LevelFilter, SimpleLogger};
-use tokio;
 
 use coincheck::error::AppError;
 use coincheck::db::establish_connection;
 use coincheck::api;
+use coincheck::notifier;
 use coincheck::repositories;
 
 #[tokio::main]
@@ -14,17 +14,20 @@ async fn main() {
     dotenv().ok();
     SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
 
-    if let Err(e) = run().await {
+    let result = run().await;
+    if let Err(e) = &result {
         error!("Error occurred: {}", e);
     }
+    repositories::alert::record_job("order", &result).await;
 }
 
 async fn run() -> Result<(), AppError> {
     let pool = establish_connection();
     let mut conn = pool.get().expect("Failed to get DB connection");
     let client = api::coincheck::client::CoincheckClient::new()?;
+    let notifier = notifier::router::NotifierRouter::from_env()?;
 
-    repositories::order::post_market_order(&mut conn, &client).await?;
+    repositories::order::post_market_order(&mut conn, &client, &notifier).await?;
 
     Ok(())
 }
@@ -0,0 +1,39 @@
+use std::env;
+use dotenvy::dotenv;
+
+use log::error;
+use simplelog::{Config, LevelFilter, SimpleLogger};
+
+use coincheck::error::AppError;
+use coincheck::db::establish_connection;
+use coincheck::api;
+use coincheck::notifier;
+use coincheck::repositories;
+
+/*
+ * cargo run --bin rebalance -- --dry-run で、注文せずにリバランス内容だけ表示
+ */
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
+
+    let result = run().await;
+    if let Err(e) = &result {
+        error!("Error occurred: {}", e);
+    }
+    repositories::alert::record_job("rebalance", &result).await;
+}
+
+async fn run() -> Result<(), AppError> {
+    let dry_run = env::args().any(|arg| arg == "--dry-run");
+
+    let pool = establish_connection();
+    let mut conn = pool.get().expect("Failed to get DB connection");
+    let client = api::coincheck::client::CoincheckClient::new()?;
+
+    let notifier = notifier::router::NotifierRouter::from_env()?;
+    repositories::rebalance::run(&mut conn, &client, &notifier, dry_run).await?;
+
+    Ok(())
+}
@@ -2,12 +2,11 @@ use dotenvy::dotenv;
 use log::error;
 use simplelog::{Config, LevelFilter, SimpleLogger};
 
-use tokio;
-
 use coincheck::{
     api,
     db::establish_connection,
     error::AppError,
+    notifier,
     repositories,
 };
 
@@ -16,17 +15,20 @@ async fn main() {
     dotenv().ok();
     SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
 
-    if let Err(e) = run().await {
+    let result = run().await;
+    if let Err(e) = &result {
         error!("Error occurred: {}", e);
     }
+    repositories::alert::record_job("summary", &result).await;
 }
 
 async fn run() -> Result<(), AppError> {
     let pool = establish_connection();
     let mut conn = pool.get()?;
     let client = api::coincheck::client::CoincheckClient::new()?;
+    let notifier = notifier::router::NotifierRouter::from_env()?;
 
-    repositories::summary::reporing(&mut conn, &client).await?;
+    repositories::summary::reporing(&mut conn, &client, &notifier).await?;
 
     Ok(())
 }
@@ -3,21 +3,30 @@ use dotenvy::dotenv;
 
 use log::{info, error};
 use simplelog::{Config, LevelFilter, SimpleLogger};
-use tokio;
 
 use coincheck::error::AppError;
 use coincheck::db::establish_connection;
 use coincheck::api;
 use coincheck::repositories;
-
+use coincheck::config;
+
+/*
+ * cargo run --bin ticker_fetcher で、取引中の通貨のtickerと板を保存して、古いtickerを消す
+ *
+ * [envの設定]
+ * TICKER_RETENTION_ROWS=10000 (全通貨合わせたtickersの保持件数。2分毎なら1通貨1日720件で、
+ *   ウォークフォワードにはMA_OPT_TRAIN_HOURS + MA_OPT_TEST_HOURS分が通貨毎に必要)
+ */
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
 
-    if let Err(e) = run().await {
+    let result = run().await;
+    if let Err(e) = &result {
         error!("Error occurred: {}", e);
     }
+    repositories::alert::record_job("ticker_fetcher", &result).await;
 }
 
 async fn run() -> Result<(), AppError> {
@@ -30,12 +39,18 @@ async fn run() -> Result<(), AppError> {
     let my_trading_currencies = repositories::balance::my_trading_currencies(&client).await?;
 
     for currency in my_trading_currencies.iter() {
-        let mut new_ticker = api::coincheck::ticker::find(&client, &currency).await?;
+        let mut new_ticker = api::coincheck::ticker::find(&client, currency).await?;
         new_ticker.pair = Some(currency.to_string());
         repositories::ticker::create(&mut conn, new_ticker)?;
+
+        // 板のスナップショットは補助的な情報なので、失敗しても他の通貨のtickerは取り続ける
+        if let Err(e) = repositories::order_book::save_snapshot(&mut conn, &client, currency).await {
+            error!("[{}] order book snapshot failed: {}", currency, e);
+        }
     };
 
-    let deleted_count = models::ticker::Ticker::delete_oldest(&mut conn)?;
+    let retention_rows: i64 = config::parse_var("TICKER_RETENTION_ROWS", "10000")?;
+    let deleted_count = models::ticker::Ticker::delete_oldest(&mut conn, retention_rows)?;
 
     info!("Execute ticker_fetcher successful and [record deleted {}].", deleted_count);
 
@@ -0,0 +1,53 @@
+use dotenvy::dotenv;
+
+use log::{info, error};
+use simplelog::{Config, LevelFilter, SimpleLogger};
+use tokio::sync::broadcast::error::RecvError;
+
+use coincheck::error::AppError;
+use coincheck::api::coincheck::websocket::{Channel, WebSocketFeed, WsEvent};
+
+/*
+ * public WebSocketを購読して、受信したイベントをログに出す。
+ * cargo run --bin ws_feed -- btc eth
+ */
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    SimpleLogger::init(LevelFilter::Info, Config::default()).unwrap();
+
+    if let Err(e) = run().await {
+        error!("Error occurred: {}", e);
+    }
+}
+
+async fn run() -> Result<(), AppError> {
+    let mut currencies: Vec<String> = std::env::args().skip(1).collect();
+    if currencies.is_empty() {
+        currencies.push("btc".to_string());
+    }
+
+    let channels = currencies.iter()
+        .flat_map(|c| [Channel::Trades(c.clone()), Channel::OrderBook(c.clone())])
+        .collect();
+
+    let feed = WebSocketFeed::connect(channels)?;
+    let mut events = feed.subscribe();
+
+    loop {
+        match events.recv().await {
+            Ok(WsEvent::Trade(trade)) => {
+                info!("[trade] {} {} {}@{}", trade.pair, trade.order_type, trade.amount, trade.rate);
+            },
+            Ok(WsEvent::OrderBook(book)) => {
+                info!("[orderbook] {} bids:{} asks:{}", book.pair, book.bids.len(), book.asks.len());
+            },
+            Err(RecvError::Lagged(skipped)) => {
+                error!("WebSocket feed lagged, {} events skipped", skipped);
+            },
+            Err(e) => {
+                return Err(AppError::InvalidData(format!("WebSocket feed error: {}", e)));
+            },
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+use plotters::coord::Shift;
+use plotters::coord::types::RangedDateTime;
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+
+use crate::error::AppError;
+use crate::metrics::EquityPoint;
+use crate::strategies::indicator;
+
+/*
+ * plottersでチャートを描画する。拡張子が.svgならSVG、それ以外はPNGで書き出す。
+ * データの読み込みはrepositories::chartで行い、ここでは渡された値を描くだけ。
+ */
+
+const SIZE: (u32, u32) = (1200, 600);
+
+// 注文の約定位置
+#[derive(Debug, Clone, Copy)]
+pub struct Marker {
+    pub at: NaiveDateTime,
+    pub price: f64,
+    // trueなら買い、falseなら売り
+    pub buy: bool,
+}
+
+pub struct PriceChart<'a> {
+    pub pair: &'a str,
+    // 古い順
+    pub series: &'a [(NaiveDateTime, f64)],
+    pub short: usize,
+    pub long: usize,
+    pub markers: &'a [Marker],
+}
+
+/*
+ * 価格に短期・長期のSMAを重ねて、買いを緑の▲、売りを赤の▼で表示する。
+ */
+pub fn price(path: &Path, chart: &PriceChart) -> Result<(), AppError> {
+    if chart.series.len() < 2 {
+        return Err(AppError::ChartError(format!("{}: 価格が2件未満", chart.pair)));
+    }
+
+    if is_svg(path) {
+        draw_price(SVGBackend::new(path, SIZE).into_drawing_area(), chart)
+    } else {
+        draw_price(BitMapBackend::new(path, SIZE).into_drawing_area(), chart)
+    }
+}
+
+/*
+ * 総資産と、そのうち暗号資産で持っている分の推移。
+ */
+pub fn equity(path: &Path, points: &[EquityPoint]) -> Result<(), AppError> {
+    if points.len() < 2 {
+        return Err(AppError::ChartError("資産推移が2件未満".to_string()));
+    }
+
+    if is_svg(path) {
+        draw_equity(SVGBackend::new(path, SIZE).into_drawing_area(), points)
+    } else {
+        draw_equity(BitMapBackend::new(path, SIZE).into_drawing_area(), points)
+    }
+}
+
+/*
+ * 通貨毎の(通貨, 総資産に対する割合(%))を棒グラフにする。
+ */
+pub fn allocation(path: &Path, allocations: &[(String, f64)]) -> Result<(), AppError> {
+    if allocations.is_empty() {
+        return Err(AppError::ChartError("配分のデータなし".to_string()));
+    }
+
+    if is_svg(path) {
+        draw_allocation(SVGBackend::new(path, SIZE).into_drawing_area(), allocations)
+    } else {
+        draw_allocation(BitMapBackend::new(path, SIZE).into_drawing_area(), allocations)
+    }
+}
+
+/*
+ * 価格系列のSMAを、各SMAの最後の価格の時刻に並べる。期間に満たなければ空。
+ */
+pub fn sma_points(series: &[(NaiveDateTime, f64)], period: usize) -> Vec<(NaiveDateTime, f64)> {
+    let prices: Vec<f64> = series.iter().map(|(_, p)| *p).collect();
+    let sma = indicator::sma_series(&prices, period);
+
+    series[series.len() - sma.len()..].iter().map(|(t, _)| *t).zip(sma).collect()
+}
+
+fn is_svg(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "svg")
+}
+
+fn chart_error<E: std::fmt::Display>(e: E) -> AppError {
+    AppError::ChartError(e.to_string())
+}
+
+// 最小値と最大値に、上下5%の余白を付けた範囲
+fn padded_range(values: impl Iterator<Item = f64>) -> std::ops::Range<f64> {
+    let (min, max) = values.fold((f64::MAX, f64::MIN), |(min, max), v| (min.min(v), max.max(v)));
+    let padding = ((max - min) * 0.05).max(max.abs() * 0.001).max(1e-9);
+    (min - padding)..(max + padding)
+}
+
+fn draw_price<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, chart: &PriceChart) -> Result<(), AppError> {
+    let series = chart.series;
+    let prices: Vec<f64> = series.iter().map(|(_, p)| *p).collect();
+    let (from, to) = (series[0].0, series[series.len() - 1].0);
+
+    root.fill(&WHITE).map_err(chart_error)?;
+    let mut ctx = ChartBuilder::on(&root)
+        .caption(format!("{} price", chart.pair), ("sans-serif", 24))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(90)
+        .build_cartesian_2d(RangedDateTime::from(from..to), padded_range(prices.iter().copied()))
+        .map_err(chart_error)?;
+
+    ctx.configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|t| t.format("%m/%d %H:%M").to_string())
+        .draw()
+        .map_err(chart_error)?;
+
+    ctx.draw_series(LineSeries::new(series.iter().copied(), &BLACK))
+        .map_err(chart_error)?
+        .label("price")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK));
+
+    for (period, color) in [(chart.short, BLUE), (chart.long, RED)] {
+        let sma = sma_points(series, period);
+        if sma.is_empty() {
+            continue;
+        }
+
+        ctx.draw_series(LineSeries::new(sma, &color))
+            .map_err(chart_error)?
+            .label(format!("SMA{}", period))
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    let in_range = chart.markers.iter().filter(|m| m.at >= from && m.at <= to);
+    ctx.draw_series(in_range.clone().filter(|m| m.buy).map(|m| TriangleMarker::new((m.at, m.price), 8, GREEN.filled())))
+        .map_err(chart_error)?;
+    ctx.draw_series(in_range.filter(|m| !m.buy).map(|m| {
+        EmptyElement::at((m.at, m.price)) + Polygon::new(vec![(-7, -5), (7, -5), (0, 7)], RED.filled())
+    }))
+    .map_err(chart_error)?;
+
+    ctx.configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(chart_error)?;
+
+    root.present().map_err(chart_error)
+}
+
+fn draw_equity<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, points: &[EquityPoint]) -> Result<(), AppError> {
+    let (from, to) = (points[0].at, points[points.len() - 1].at);
+    let max = points.iter().map(|p| p.value).fold(0.0, f64::max);
+
+    root.fill(&WHITE).map_err(chart_error)?;
+    let mut ctx = ChartBuilder::on(&root)
+        .caption("portfolio value (JPY)", ("sans-serif", 24))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(90)
+        .build_cartesian_2d(RangedDateTime::from(from..to), 0.0..max * 1.05 + 1.0)
+        .map_err(chart_error)?;
+
+    ctx.configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|t| t.format("%m/%d").to_string())
+        .y_label_formatter(&|v| format!("{:.0}", v))
+        .draw()
+        .map_err(chart_error)?;
+
+    ctx.draw_series(AreaSeries::new(points.iter().map(|p| (p.at, p.invested)), 0.0, BLUE.mix(0.2)))
+        .map_err(chart_error)?
+        .label("crypto")
+        .legend(|(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], BLUE.mix(0.2).filled()));
+
+    ctx.draw_series(LineSeries::new(points.iter().map(|p| (p.at, p.value)), &BLACK))
+        .map_err(chart_error)?
+        .label("total")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK));
+
+    ctx.configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(chart_error)?;
+
+    root.present().map_err(chart_error)
+}
+
+fn draw_allocation<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, allocations: &[(String, f64)]) -> Result<(), AppError> {
+    root.fill(&WHITE).map_err(chart_error)?;
+    let mut ctx = ChartBuilder::on(&root)
+        .caption("allocation (%)", ("sans-serif", 24))
+        .margin(10)
+        .x_label_area_size(10)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0.0..allocations.len() as f64, 0.0..100.0)
+        .map_err(chart_error)?;
+
+    // 通貨名は棒の上に出すので、x軸の目盛りは出さない
+    ctx.configure_mesh()
+        .disable_x_mesh()
+        .x_labels(0)
+        .draw()
+        .map_err(chart_error)?;
+
+    ctx.draw_series(allocations.iter().enumerate().map(|(i, (_, pct))| {
+        Rectangle::new([(i as f64 + 0.1, 0.0), (i as f64 + 0.9, *pct)], Palette99::pick(i).filled())
+    }))
+    .map_err(chart_error)?;
+
+    ctx.draw_series(allocations.iter().enumerate().map(|(i, (currency, pct))| {
+        let style = ("sans-serif", 18).into_text_style(&root).pos(Pos::new(HPos::Center, VPos::Bottom));
+        Text::new(format!("{} {:.1}%", currency, pct), (i as f64 + 0.5, (pct + 1.0).min(95.0)), style)
+    }))
+    .map_err(chart_error)?;
+
+    root.present().map_err(chart_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+
+    fn series(prices: &[f64]) -> Vec<(NaiveDateTime, f64)> {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        prices.iter().enumerate().map(|(i, p)| (start + Duration::minutes(i as i64), *p)).collect()
+    }
+
+    #[test]
+    fn sma_points_are_aligned_to_the_last_price_of_each_window() {
+        let series = series(&[1.0, 2.0, 3.0, 4.0]);
+
+        assert_eq!(sma_points(&series, 2), vec![(series[1].0, 1.5), (series[2].0, 2.5), (series[3].0, 3.5)]);
+        assert_eq!(sma_points(&series, 4), vec![(series[3].0, 2.5)]);
+    }
+
+    #[test]
+    fn sma_points_are_empty_when_the_period_does_not_fit() {
+        let series = series(&[1.0, 2.0, 3.0]);
+
+        assert!(sma_points(&series, 4).is_empty());
+        assert!(sma_points(&series, 0).is_empty());
+        assert!(sma_points(&[], 2).is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+use dotenvy::dotenv;
+
+use crate::error::AppError;
+
+/*
+ * envの設定を読み込む。戦略のパラメータも、repositoriesやbinの設定もここを通す。
+ */
+
+/*
+ * 通貨毎のパラメータを読み込む。通貨毎の設定`{KEY}_{CURRENCY}`があればそちらを優先する。
+ *
+ * RSI_PERIOD=14
+ * RSI_PERIOD_XRP=7  (xrpだけ7)
+ */
+pub fn param(key: &str, currency: &str) -> Result<String, env::VarError> {
+    dotenv().ok();
+
+    env::var(format!("{}_{}", key, currency.to_uppercase())).or_else(|_| env::var(key))
+}
+
+/*
+ * param()の値をパースする。未設定ならdefault。
+ */
+pub fn parse_param<T: FromStr>(key: &str, currency: &str, default: &str) -> Result<T, AppError>
+where
+    T::Err: Display,
+{
+    parse(key, param(key, currency).ok(), default)
+}
+
+/*
+ * 通貨に依らない設定をパースする。未設定ならdefault。
+ */
+pub fn parse_var<T: FromStr>(key: &str, default: &str) -> Result<T, AppError>
+where
+    T::Err: Display,
+{
+    dotenv().ok();
+
+    parse(key, env::var(key).ok(), default)
+}
+
+/*
+ * 既定値のない、必須のパラメータ。未設定ならエラー。
+ */
+pub fn require_param<T: FromStr>(key: &str, currency: &str) -> Result<T, AppError>
+where
+    T::Err: Display,
+{
+    let value = param(key, currency)
+        .map_err(|_| AppError::InvalidData(format!("Environment variable missing: {}", key)))?;
+    parse(key, Some(value), "")
+}
+
+/*
+ * 任意のパラメータ。未設定ならNone、設定されていればパースする。
+ */
+pub fn optional_param<T: FromStr>(key: &str, currency: &str) -> Result<Option<T>, AppError>
+where
+    T::Err: Display,
+{
+    param(key, currency).ok().map(|value| parse(key, Some(value), "")).transpose()
+}
+
+/*
+ * 通貨に依らない任意の設定。未設定ならNone。
+ */
+pub fn optional_var<T: FromStr>(key: &str) -> Result<Option<T>, AppError>
+where
+    T::Err: Display,
+{
+    dotenv().ok();
+
+    env::var(key).ok().map(|value| parse(key, Some(value), "")).transpose()
+}
+
+fn parse<T: FromStr>(key: &str, value: Option<String>, default: &str) -> Result<T, AppError>
+where
+    T::Err: Display,
+{
+    value.as_deref()
+        .unwrap_or(default)
+        .trim()
+        .parse::<T>()
+        .map_err(|e| AppError::InvalidData(format!("Parse error: {}: {}", key, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // テスト毎にキーを分けて、並列実行でenvが干渉しないようにする
+    #[test]
+    fn parse_param_prefers_the_currency_specific_key() {
+        env::set_var("CONFIG_TEST_PERIOD", "14");
+        env::set_var("CONFIG_TEST_PERIOD_XRP", "7");
+
+        assert_eq!(parse_param::<usize>("CONFIG_TEST_PERIOD", "xrp", "1").unwrap(), 7);
+        assert_eq!(parse_param::<usize>("CONFIG_TEST_PERIOD", "btc", "1").unwrap(), 14);
+    }
+
+    #[test]
+    fn parse_var_uses_the_default_when_unset() {
+        assert_eq!(parse_var::<f64>("CONFIG_TEST_UNSET_DEFAULT", "2.5").unwrap(), 2.5);
+    }
+
+    #[test]
+    fn parse_error_names_the_key() {
+        env::set_var("CONFIG_TEST_BAD", "abc");
+
+        let error = parse_var::<i64>("CONFIG_TEST_BAD", "1").unwrap_err().to_string();
+        assert!(error.contains("CONFIG_TEST_BAD"), "{}", error);
+    }
+
+    #[test]
+    fn require_param_fails_when_unset() {
+        let error = require_param::<f64>("CONFIG_TEST_REQUIRED", "btc").unwrap_err().to_string();
+        assert!(error.contains("CONFIG_TEST_REQUIRED"), "{}", error);
+    }
+
+    #[test]
+    fn optional_values_are_none_when_unset_and_parsed_when_set() {
+        env::set_var("CONFIG_TEST_OPTIONAL_BTC", "3");
+
+        assert_eq!(optional_param::<i32>("CONFIG_TEST_OPTIONAL", "btc").unwrap(), Some(3));
+        assert_eq!(optional_param::<i32>("CONFIG_TEST_OPTIONAL", "eth").unwrap(), None);
+        assert_eq!(optional_var::<i32>("CONFIG_TEST_OPTIONAL_UNSET").unwrap(), None);
+    }
+}
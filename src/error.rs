@@ -20,6 +20,68 @@ pub enum AppError {
     #[error("reqwet header to str error: {0}")]
     ToStrError(#[from] reqwest::header::ToStrError),
 
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Coincheck API error [{status}] {kind:?}: {message}")]
+    Exchange {
+        kind: ExchangeErrorKind,
+        message: String,
+        status: u16,
+    },
+
+    #[error("Chart error: {0}")]
+    ChartError(String),
+
     #[error("Invalid data: {0}")]
     InvalidData(String),
 }
+
+/*
+ * Coincheckの`{success: false, error: ...}`を呼び出し元で判別できるように分類したもの。
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExchangeErrorKind {
+    InsufficientFunds,
+    InvalidAmount,
+    Unauthorized,
+    InvalidNonce,
+    RateLimited,
+    Maintenance,
+    Unknown,
+}
+
+impl ExchangeErrorKind {
+    pub fn classify(status: u16, message: &str) -> Self {
+        let msg = message.to_lowercase();
+
+        if msg.contains("nonce") {
+            ExchangeErrorKind::InvalidNonce
+        } else if status == 401 || msg.contains("authentication") || msg.contains("signature") {
+            ExchangeErrorKind::Unauthorized
+        } else if status == 429 || msg.contains("too many") {
+            ExchangeErrorKind::RateLimited
+        } else if status == 503 || msg.contains("maintenance") || msg.contains("メンテナンス") {
+            ExchangeErrorKind::Maintenance
+        } else if msg.contains("insufficient") || msg.contains("balance") || msg.contains("残高") {
+            ExchangeErrorKind::InsufficientFunds
+        } else if msg.contains("amount") || msg.contains("数量") || msg.contains("最低") {
+            ExchangeErrorKind::InvalidAmount
+        } else {
+            ExchangeErrorKind::Unknown
+        }
+    }
+
+    // 少し待てば通る可能性があるもの
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ExchangeErrorKind::RateLimited | ExchangeErrorKind::Maintenance | ExchangeErrorKind::InvalidNonce
+        )
+    }
+
+    // 後続の注文も全て失敗するので、処理を止めるべきもの
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, ExchangeErrorKind::Unauthorized)
+    }
+}
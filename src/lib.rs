@@ -4,4 +4,8 @@ pub mod repositories;
 pub mod models;
 pub mod api;
 pub mod error;
+pub mod config;
 pub mod strategies;
+pub mod chart;
+pub mod metrics;
+pub mod notifier;
@@ -1,17 +1,8 @@
-mod repositories;
-mod api;
-mod schema;
-mod models;
-mod error;
-mod strategies;
-
 use dotenvy::dotenv;
 
-use tokio;
-
-use error::AppError;
-use api::coincheck::client::CoincheckClient;
-use repositories::balance;
+use coincheck::error::AppError;
+use coincheck::api::coincheck::client::CoincheckClient;
+use coincheck::repositories::balance;
 
 #[tokio::main]
 async fn main() -> Result<(), AppError> {
@@ -35,9 +26,9 @@ async fn main() -> Result<(), AppError> {
 
 async fn print_my_balances(client: &CoincheckClient) -> Result<(), AppError> {
     println!("#-- 通貨保有量 ");
-    let my_balances = balance::my_balancies(&client).await?;
+    let my_balances = balance::my_balancies(client).await?;
     println!("{:#?}", my_balances);
-    println!("");
+    println!();
 
     Ok(())
 }
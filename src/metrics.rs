@@ -0,0 +1,362 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/*
+ * 資産推移(equity curve)と売買(trade)の一覧から、成績の指標を計算する。
+ * 同じ期間のバイ&ホールド(最初の資産で全額買って持ち続けた場合)とも比べる。
+ * バックテストでも実運用の履歴でも、EquityPointとTradeに直せば使える。
+ * リスクフリーレートは0として、年率換算は資産推移の平均間隔から行う。
+ */
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct EquityPoint {
+    pub at: NaiveDateTime,
+    // 総資産(JPY)
+    pub value: f64,
+    // そのうち暗号資産で持っている分(JPY)
+    pub invested: f64,
+}
+
+// 買ってから売るまでの1回の売買
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub pair: String,
+    pub entry_at: NaiveDateTime,
+    pub exit_at: NaiveDateTime,
+    pub entry_price: f64,
+    pub exit_price: f64,
+    // 暗号資産の数量
+    pub amount: f64,
+}
+
+impl Trade {
+    pub fn pnl(&self) -> f64 {
+        (self.exit_price - self.entry_price) * self.amount
+    }
+
+    pub fn holding_hours(&self) -> f64 {
+        (self.exit_at - self.entry_at).num_seconds() as f64 / 3600.0
+    }
+
+    // 買いと売りの売買代金(JPY)
+    pub fn notional(&self) -> f64 {
+        (self.entry_price + self.exit_price) * self.amount
+    }
+}
+
+// 資産推移だけから計算できる指標
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CurveMetrics {
+    pub total_return_pct: f64,
+    // 期間が短すぎる、または最初の資産が0の時はNone
+    pub cagr_pct: Option<f64>,
+    pub sharpe: Option<f64>,
+    pub sortino: Option<f64>,
+    pub max_drawdown_pct: f64,
+    // 高値を付けてから、それを回復する(または期間が終わる)までの最長時間
+    pub max_drawdown_hours: f64,
+}
+
+impl CurveMetrics {
+    pub fn from_values(values: &[(NaiveDateTime, f64)]) -> Option<Self> {
+        let (&(first_at, first), &(last_at, last)) = (values.first()?, values.last()?);
+        if values.len() < 2 || first <= 0.0 {
+            return None;
+        }
+
+        let seconds = (last_at - first_at).num_seconds() as f64;
+        let years = seconds / SECONDS_PER_YEAR;
+        let cagr_pct = if years > 0.0 && last > 0.0 {
+            Some(((last / first).powf(1.0 / years) - 1.0) * 100.0)
+        } else {
+            None
+        };
+
+        let returns: Vec<f64> = values.windows(2)
+            .filter(|w| w[0].1 > 0.0)
+            .map(|w| w[1].1 / w[0].1 - 1.0)
+            .collect();
+        let periods_per_year = if seconds > 0.0 { SECONDS_PER_YEAR / (seconds / (values.len() - 1) as f64) } else { 0.0 };
+        let (sharpe, sortino) = risk_adjusted(&returns, periods_per_year);
+
+        let mut peak = (first_at, first);
+        let mut max_drawdown_pct: f64 = 0.0;
+        let mut max_drawdown_hours: f64 = 0.0;
+        for &(at, value) in values.iter() {
+            if value >= peak.1 {
+                peak = (at, value);
+            } else if peak.1 > 0.0 {
+                max_drawdown_pct = max_drawdown_pct.max((peak.1 - value) / peak.1 * 100.0);
+            }
+            max_drawdown_hours = max_drawdown_hours.max((at - peak.0).num_seconds() as f64 / 3600.0);
+        }
+
+        Some(Self {
+            total_return_pct: (last / first - 1.0) * 100.0,
+            cagr_pct,
+            sharpe,
+            sortino,
+            max_drawdown_pct,
+            max_drawdown_hours,
+        })
+    }
+}
+
+/*
+ * 期間毎のリターンから、年率換算したシャープレシオとソルティノレシオ。
+ * ばらつき(下方のばらつき)が0の時はNone。
+ */
+fn risk_adjusted(returns: &[f64], periods_per_year: f64) -> (Option<f64>, Option<f64>) {
+    if returns.len() < 2 || periods_per_year <= 0.0 {
+        return (None, None);
+    }
+
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let std = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+    let downside = (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / n).sqrt();
+    let annualize = periods_per_year.sqrt();
+
+    (
+        (std > 0.0).then(|| mean / std * annualize),
+        (downside > 0.0).then(|| mean / downside * annualize),
+    )
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TradeMetrics {
+    pub trades: usize,
+    pub win_rate_pct: Option<f64>,
+    // 負けがなければNone
+    pub profit_factor: Option<f64>,
+    pub avg_holding_hours: Option<f64>,
+}
+
+impl TradeMetrics {
+    pub fn from_trades(trades: &[Trade]) -> Self {
+        if trades.is_empty() {
+            return Self { trades: 0, win_rate_pct: None, profit_factor: None, avg_holding_hours: None };
+        }
+
+        let n = trades.len() as f64;
+        let wins = trades.iter().filter(|t| t.pnl() > 0.0).count();
+        let gross_profit: f64 = trades.iter().map(|t| t.pnl()).filter(|p| *p > 0.0).sum();
+        let gross_loss: f64 = -trades.iter().map(|t| t.pnl()).filter(|p| *p < 0.0).sum::<f64>();
+
+        Self {
+            trades: trades.len(),
+            win_rate_pct: Some(wins as f64 / n * 100.0),
+            profit_factor: if gross_loss > 0.0 { Some(gross_profit / gross_loss) } else { None },
+            avg_holding_hours: Some(trades.iter().map(|t| t.holding_hours()).sum::<f64>() / n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Metrics {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub strategy: CurveMetrics,
+    // 比較対象の価格が期間内に2つ以上なければNone
+    pub buy_and_hold: Option<CurveMetrics>,
+    pub trades: TradeMetrics,
+    // 暗号資産で持っていた割合の時間平均(%)
+    pub exposure_pct: f64,
+    // 期間内の売買代金 / 平均資産
+    pub turnover: f64,
+}
+
+impl Metrics {
+    /*
+     * equityは古い順。benchmarkはバイ&ホールドで持つ通貨の(時刻, 価格)で、equityの期間内だけ使う。
+     * 資産推移が2点未満なら計算できないのでNone。
+     */
+    pub fn calculate(
+        equity: &[EquityPoint],
+        trades: &[Trade],
+        benchmark: &[(NaiveDateTime, f64)],
+    ) -> Option<Self> {
+        let values: Vec<(NaiveDateTime, f64)> = equity.iter().map(|p| (p.at, p.value)).collect();
+        let strategy = CurveMetrics::from_values(&values)?;
+        let (from, to) = (equity[0].at, equity[equity.len() - 1].at);
+
+        let in_period: Vec<&(NaiveDateTime, f64)> = benchmark.iter()
+            .filter(|(at, _)| *at >= from && *at <= to)
+            .collect();
+        let buy_and_hold = in_period.first()
+            .filter(|(_, price)| *price > 0.0)
+            .and_then(|(_, first_price)| {
+                let hold: Vec<(NaiveDateTime, f64)> = in_period.iter()
+                    .map(|(at, price)| (*at, equity[0].value * price / first_price))
+                    .collect();
+                CurveMetrics::from_values(&hold)
+            });
+
+        let mut exposed_seconds = 0.0;
+        let mut total_seconds = 0.0;
+        for w in equity.windows(2) {
+            let seconds = (w[1].at - w[0].at).num_seconds() as f64;
+            if w[0].value > 0.0 {
+                exposed_seconds += seconds * (w[0].invested / w[0].value).clamp(0.0, 1.0);
+            }
+            total_seconds += seconds;
+        }
+
+        let average_value = equity.iter().map(|p| p.value).sum::<f64>() / equity.len() as f64;
+        // 売買の指標と回転率は、期間に重なるトレードだけで計算する
+        let period_trades: Vec<Trade> = trades.iter()
+            .filter(|t| t.exit_at >= from && t.entry_at <= to)
+            .cloned()
+            .collect();
+        // 空のsum()は-0.0になり、表で-0.00と出るのでfoldで0.0から足す
+        let notional = period_trades.iter().fold(0.0, |sum, t| sum + t.notional());
+
+        Some(Self {
+            from,
+            to,
+            strategy,
+            buy_and_hold,
+            trades: TradeMetrics::from_trades(&period_trades),
+            exposure_pct: if total_seconds > 0.0 { exposed_seconds / total_seconds * 100.0 } else { 0.0 },
+            turnover: if average_value > 0.0 { notional / average_value } else { 0.0 },
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, AppError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    // 戦略とバイ&ホールドを並べた表
+    pub fn to_table(&self) -> String {
+        let fmt = |value: Option<f64>| value.map(|v| format!("{:.2}", v)).unwrap_or("-".to_string());
+        let hold = self.buy_and_hold.as_ref();
+
+        let rows = [
+            ("total return (%)", Some(self.strategy.total_return_pct), hold.map(|h| h.total_return_pct)),
+            ("CAGR (%)", self.strategy.cagr_pct, hold.and_then(|h| h.cagr_pct)),
+            ("sharpe", self.strategy.sharpe, hold.and_then(|h| h.sharpe)),
+            ("sortino", self.strategy.sortino, hold.and_then(|h| h.sortino)),
+            ("max drawdown (%)", Some(self.strategy.max_drawdown_pct), hold.map(|h| h.max_drawdown_pct)),
+            ("max drawdown (h)", Some(self.strategy.max_drawdown_hours), hold.map(|h| h.max_drawdown_hours)),
+            ("trades", Some(self.trades.trades as f64), None),
+            ("win rate (%)", self.trades.win_rate_pct, None),
+            ("profit factor", self.trades.profit_factor, None),
+            ("avg holding (h)", self.trades.avg_holding_hours, None),
+            ("exposure (%)", Some(self.exposure_pct), Some(100.0)),
+            ("turnover", Some(self.turnover), None),
+        ];
+
+        let mut table = format!("{} - {}\n", self.from, self.to);
+        table.push_str(&format!("{:<18} {:>14} {:>14}\n", "", "strategy", "buy & hold"));
+        for (name, strategy, hold) in rows.iter() {
+            table.push_str(&format!("{:<18} {:>14} {:>14}\n", name, fmt(*strategy), fmt(*hold)));
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate};
+
+    fn start() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap()
+    }
+
+    // start()から1日毎の資産推移
+    fn daily(values: &[f64]) -> Vec<(NaiveDateTime, f64)> {
+        values.iter().enumerate().map(|(i, v)| (start() + Duration::days(i as i64), *v)).collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn curve_metrics_need_two_points() {
+        assert!(CurveMetrics::from_values(&[]).is_none());
+        assert!(CurveMetrics::from_values(&daily(&[100.0])).is_none());
+        assert!(CurveMetrics::from_values(&daily(&[0.0, 100.0])).is_none());
+    }
+
+    #[test]
+    fn cagr_annualizes_the_total_return() {
+        let values = [(start(), 100.0), (start() + Duration::days(365), 121.0)];
+        let metrics = CurveMetrics::from_values(&values).unwrap();
+
+        assert_close(metrics.total_return_pct, 21.0);
+        assert_close(metrics.cagr_pct.unwrap(), 21.0);
+
+        // 2年で1.21倍なら年率10%
+        let values = [(start(), 100.0), (start() + Duration::days(730), 121.0)];
+        assert_close(CurveMetrics::from_values(&values).unwrap().cagr_pct.unwrap(), 10.0);
+    }
+
+    #[test]
+    fn cagr_is_none_without_elapsed_time() {
+        let values = [(start(), 100.0), (start(), 110.0)];
+        let metrics = CurveMetrics::from_values(&values).unwrap();
+
+        assert_eq!(metrics.cagr_pct, None);
+        assert_eq!(metrics.sharpe, None);
+    }
+
+    #[test]
+    fn flat_returns_have_no_sharpe_or_sortino() {
+        let metrics = CurveMetrics::from_values(&daily(&[100.0, 100.0, 100.0, 100.0])).unwrap();
+
+        assert_eq!(metrics.sharpe, None);
+        assert_eq!(metrics.sortino, None);
+        assert_close(metrics.max_drawdown_pct, 0.0);
+    }
+
+    #[test]
+    fn steady_gains_have_no_sortino() {
+        // 下方のばらつきがない
+        let metrics = CurveMetrics::from_values(&daily(&[100.0, 101.0, 103.0, 104.0])).unwrap();
+
+        assert!(metrics.sharpe.unwrap() > 0.0);
+        assert_eq!(metrics.sortino, None);
+    }
+
+    #[test]
+    fn risk_adjusted_matches_a_known_series() {
+        let returns = [0.01, -0.02, 0.03, 0.0];
+        let mean = 0.005;
+        let std = ((0.005f64.powi(2) + 0.025f64.powi(2) + 0.025f64.powi(2) + 0.005f64.powi(2)) / 3.0).sqrt();
+        let downside = (0.02f64.powi(2) / 4.0).sqrt();
+
+        let (sharpe, sortino) = risk_adjusted(&returns, 365.0);
+        assert_close(sharpe.unwrap(), mean / std * 365f64.sqrt());
+        assert_close(sortino.unwrap(), mean / downside * 365f64.sqrt());
+    }
+
+    #[test]
+    fn risk_adjusted_needs_two_returns() {
+        assert_eq!(risk_adjusted(&[], 365.0), (None, None));
+        assert_eq!(risk_adjusted(&[0.01], 365.0), (None, None));
+        assert_eq!(risk_adjusted(&[0.01, 0.02], 0.0), (None, None));
+    }
+
+    #[test]
+    fn max_drawdown_is_the_deepest_fall_from_a_peak() {
+        // 120 -> 90で25%、回復しないまま終わる
+        let metrics = CurveMetrics::from_values(&daily(&[100.0, 120.0, 90.0, 110.0, 100.0])).unwrap();
+
+        assert_close(metrics.max_drawdown_pct, 25.0);
+        assert_close(metrics.max_drawdown_hours, 72.0);
+    }
+
+    #[test]
+    fn max_drawdown_duration_ends_at_recovery() {
+        let metrics = CurveMetrics::from_values(&daily(&[100.0, 80.0, 100.0, 110.0])).unwrap();
+
+        assert_close(metrics.max_drawdown_pct, 20.0);
+        assert_close(metrics.max_drawdown_hours, 24.0);
+    }
+}
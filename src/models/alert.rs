@@ -0,0 +1,54 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::error::AppError;
+use crate::schema::alerts;
+use crate::schema::alerts::dsl::*;
+
+/*
+ * 送ったアラート。同じ(rule, key)をクールダウン中に何度も送らないために使う。
+ */
+#[derive(Debug, Queryable, Serialize, Deserialize)]
+#[diesel(table_name = alerts)]
+pub struct Alert {
+    pub id: i32,
+    // stale_ticker など、どのルールか
+    pub rule: String,
+    // 通貨やジョブ名など、ルールの中で何についてか
+    pub key: String,
+    pub message: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = alerts)]
+pub struct NewAlert {
+    pub rule: String,
+    pub key: String,
+    pub message: String,
+}
+
+impl Alert {
+    pub fn create(conn: &mut PgConnection, new_alert: &NewAlert) -> Result<(), AppError> {
+        diesel::insert_into(alerts)
+            .values(new_alert)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn last_sent_at(
+        conn: &mut PgConnection,
+        rule_str: &str,
+        key_str: &str,
+    ) -> Result<Option<NaiveDateTime>, AppError> {
+        let result = alerts
+            .filter(rule.eq(rule_str))
+            .filter(key.eq(key_str))
+            .select(diesel::dsl::max(created_at))
+            .first::<Option<NaiveDateTime>>(conn)?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::error::AppError;
+use crate::schema::{grids, grid_levels};
+
+/*
+ * グリッド注文の状態。再起動しても続きから再開できるように、
+ * グリッドの設定(grids)と各レベルの状態(grid_levels)を保存する。
+ *
+ * grid_levels.state
+ *   pending: 次回の実行で指値を出す
+ *   armed:   指値を出して約定待ち(exchange_order_idに注文ID)
+ *   idle:    何も出していない
+ */
+
+#[derive(Debug, Queryable, Serialize, Deserialize)]
+#[diesel(table_name = grids)]
+pub struct Grid {
+    pub id: i32,
+    pub pair: String,
+    pub lower_price: f64,
+    pub upper_price: f64,
+    pub levels: i32,
+    pub order_amount: f64,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = grids)]
+pub struct NewGrid {
+    pub pair: String,
+    pub lower_price: f64,
+    pub upper_price: f64,
+    pub levels: i32,
+    pub order_amount: f64,
+}
+
+#[derive(Debug, Queryable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = grid_levels)]
+pub struct GridLevel {
+    pub id: i32,
+    pub grid_id: i32,
+    pub level_index: i32,
+    pub price: f64,
+    pub side: String,
+    pub state: String,
+    pub exchange_order_id: Option<i64>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = grid_levels)]
+pub struct NewGridLevel {
+    pub grid_id: i32,
+    pub level_index: i32,
+    pub price: f64,
+    pub side: String,
+    pub state: String,
+}
+
+impl Grid {
+    pub fn find_active(
+        conn: &mut PgConnection,
+        pair_str: &str,
+    ) -> Result<Option<Grid>, AppError> {
+        use crate::schema::grids::dsl::*;
+
+        let result = grids
+            .filter(pair.eq(pair_str))
+            .filter(active.eq(true))
+            .order(created_at.desc())
+            .first::<Grid>(conn)
+            .optional()?;
+
+        Ok(result)
+    }
+
+    /*
+     * グリッドと全レベルを1トランザクションで作成する。
+     * new_levelsのgrid_idは作成したグリッドのIDで上書きする。
+     */
+    pub fn create(
+        conn: &mut PgConnection,
+        new_grid: &NewGrid,
+        mut new_levels: Vec<NewGridLevel>,
+    ) -> Result<Grid, AppError> {
+        conn.transaction::<_, AppError, _>(|conn| {
+            let inserted: Grid = diesel::insert_into(grids::table)
+                .values(new_grid)
+                .get_result(conn)?;
+
+            for level in new_levels.iter_mut() {
+                level.grid_id = inserted.id;
+            }
+
+            diesel::insert_into(grid_levels::table)
+                .values(&new_levels)
+                .execute(conn)?;
+
+            Ok(inserted)
+        })
+    }
+
+    pub fn deactivate(conn: &mut PgConnection, grid_id: i32) -> Result<(), AppError> {
+        use crate::schema::grids::dsl::*;
+
+        diesel::update(grids.filter(id.eq(grid_id)))
+            .set(active.eq(false))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+impl GridLevel {
+    pub fn for_grid(
+        conn: &mut PgConnection,
+        grid: i32,
+    ) -> Result<Vec<GridLevel>, AppError> {
+        use crate::schema::grid_levels::dsl::*;
+
+        let result = grid_levels
+            .filter(grid_id.eq(grid))
+            .order(level_index.asc())
+            .load::<GridLevel>(conn)?;
+
+        Ok(result)
+    }
+
+    pub fn update_state(
+        conn: &mut PgConnection,
+        level_id: i32,
+        new_side: &str,
+        new_state: &str,
+        order_id: Option<i64>,
+    ) -> Result<(), AppError> {
+        use crate::schema::grid_levels::dsl::*;
+
+        diesel::update(grid_levels.filter(id.eq(level_id)))
+            .set((
+                side.eq(new_side),
+                state.eq(new_state),
+                exchange_order_id.eq(order_id),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
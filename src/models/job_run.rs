@@ -0,0 +1,74 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::error::AppError;
+use crate::schema::job_runs;
+use crate::schema::job_runs::dsl::*;
+
+/*
+ * cronで動かすbinの実行結果。連続した失敗の検知に使う。
+ */
+#[derive(Debug, Queryable, Serialize, Deserialize)]
+#[diesel(table_name = job_runs)]
+pub struct JobRun {
+    pub id: i32,
+    pub job: String,
+    pub success: bool,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = job_runs)]
+pub struct NewJobRun {
+    pub job: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+impl JobRun {
+    pub fn create(conn: &mut PgConnection, new_job_run: &NewJobRun) -> Result<(), AppError> {
+        diesel::insert_into(job_runs)
+            .values(new_job_run)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /*
+     * 直前から数えて、何回続けて失敗しているか。
+     */
+    pub fn consecutive_failures(conn: &mut PgConnection, job_str: &str) -> Result<usize, AppError> {
+        let recent = job_runs
+            .filter(job.eq(job_str))
+            .order(created_at.desc())
+            .limit(100)
+            .select(success)
+            .load::<bool>(conn)?;
+
+        Ok(failure_streak(&recent))
+    }
+}
+
+// 新しい順の実行結果(成功ならtrue)から、直前から続いている失敗の回数
+pub fn failure_streak(recent: &[bool]) -> usize {
+    recent.iter().take_while(|s| !**s).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failure_streak_counts_failures_until_the_last_success() {
+        assert_eq!(failure_streak(&[false, false, true, false]), 2);
+        assert_eq!(failure_streak(&[true, false, false]), 0);
+    }
+
+    #[test]
+    fn failure_streak_covers_all_runs_without_a_success() {
+        assert_eq!(failure_streak(&[false, false, false]), 3);
+        assert_eq!(failure_streak(&[]), 0);
+    }
+}
@@ -4,4 +4,11 @@ pub mod ticker;
 pub mod summary;
 pub mod summary_record;
 pub mod order;
+pub mod optimization_run;
 pub mod optimized_ma;
+pub mod optimized_macd;
+pub mod order_book;
+pub mod grid;
+pub mod signal_vote;
+pub mod alert;
+pub mod job_run;
@@ -0,0 +1,89 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::error::AppError;
+use crate::schema::optimization_runs;
+use crate::schema::optimization_runs::dsl::*;
+
+/*
+ * 最適化の1回分の実行。結果(optimized_masなど)は子として持ち、古い結果も消さずに残す。
+ * ordersのoptimization_run_idから、注文時にどの実行結果のパラメータを使ったかを追える。
+ */
+#[derive(Debug, Queryable, Serialize, Deserialize)]
+#[diesel(table_name = optimization_runs)]
+pub struct OptimizationRun {
+    pub id: i32,
+    pub pair: String,
+    // ma など、何の最適化か
+    pub kind: String,
+    // 探索範囲や選び方など、実行時の設定
+    pub settings: Option<Value>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = optimization_runs)]
+pub struct NewOptimizationRun {
+    pub pair: String,
+    pub kind: String,
+    pub settings: Option<Value>,
+}
+
+impl OptimizationRun {
+    pub fn create(
+        conn: &mut PgConnection,
+        new_run: &NewOptimizationRun,
+    ) -> Result<i32, AppError> {
+        let run_id = diesel::insert_into(optimization_runs)
+            .values(new_run)
+            .returning(id)
+            .get_result::<i32>(conn)?;
+
+        Ok(run_id)
+    }
+
+    pub fn latest(
+        conn: &mut PgConnection,
+        pair_str: &str,
+        kind_str: &str,
+    ) -> Result<Option<OptimizationRun>, AppError> {
+        let result = optimization_runs
+            .filter(pair.eq(pair_str))
+            .filter(kind.eq(kind_str))
+            .order(created_at.desc())
+            .first::<OptimizationRun>(conn)
+            .optional()?;
+
+        Ok(result)
+    }
+
+    // since以降の実行を古い順に
+    pub fn since(
+        conn: &mut PgConnection,
+        pair_str: &str,
+        kind_str: &str,
+        since: NaiveDateTime,
+    ) -> Result<Vec<OptimizationRun>, AppError> {
+        let result = optimization_runs
+            .filter(pair.eq(pair_str))
+            .filter(kind.eq(kind_str))
+            .filter(created_at.ge(since))
+            .order(created_at.asc())
+            .load::<OptimizationRun>(conn)?;
+
+        Ok(result)
+    }
+
+    pub fn pairs(conn: &mut PgConnection, kind_str: &str) -> Result<Vec<String>, AppError> {
+        let result = optimization_runs
+            .filter(kind.eq(kind_str))
+            .select(pair)
+            .distinct()
+            .order(pair.asc())
+            .load::<String>(conn)?;
+
+        Ok(result)
+    }
+}
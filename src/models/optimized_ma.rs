@@ -1,23 +1,22 @@
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDateTime};
 use diesel::prelude::*;
-use diesel::sql_query;
-use diesel::sql_types::{Text, Integer, BigInt, Numeric};
-use bigdecimal::{ToPrimitive, BigDecimal};
+use rayon::prelude::*;
 use serde::{Serialize, Deserialize};
 
 use crate::error::AppError;
+use crate::models::optimization_run::{NewOptimizationRun, OptimizationRun};
+use crate::models::ticker::Ticker;
+use crate::models::util::MAX_BIND_PARAMS;
 use crate::schema::optimized_mas;
+use crate::strategies::crossover;
+use crate::strategies::ma_optimizer::{MaSearchSpace, MaSelection, MaWalkForward};
 
-#[derive(Debug, QueryableByName)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
-struct CrossoverStats {
-    #[diesel(sql_type = BigInt)]
-    total_crosses: i64,
-    #[diesel(sql_type = BigInt)]
-    wins_count: i64,
-    #[diesel(sql_type = Numeric)]
-    win_rate_pct: BigDecimal,
-}
+// optimization_runsのkind
+pub const RUN_KIND: &str = "ma";
+
+// NewOptimizedMaの列数。1回のINSERTは、バインドパラメータが上限に収まる行数ずつにする
+const NEW_OPTIMIZED_MA_COLUMNS: usize = 17;
+const INSERT_CHUNK_ROWS: usize = MAX_BIND_PARAMS / NEW_OPTIMIZED_MA_COLUMNS;
 
 #[derive(Debug, Queryable, Serialize, Deserialize)]
 #[diesel(table_name = optimized_mas)]
@@ -31,6 +30,16 @@ pub struct OptimizedMa {
     pub total: Option<i32>,
     pub wins: Option<i32>,
     pub created_at: NaiveDateTime,
+    pub avg_return_pct: Option<f64>,
+    pub profit_factor: Option<f64>,
+    pub expectancy_pct: Option<f64>,
+    pub oos_total: Option<i32>,
+    pub oos_win_rate_pct: Option<f64>,
+    pub oos_avg_return_pct: Option<f64>,
+    pub oos_profit_factor: Option<f64>,
+    pub oos_expectancy_pct: Option<f64>,
+    pub promoted: Option<bool>,
+    pub run_id: Option<i32>,
 }
 
 #[derive(Debug, Insertable, Serialize, Deserialize, Clone)]
@@ -43,141 +52,349 @@ pub struct NewOptimizedMa {
     pub win_rate_pct: f64,
     pub total: i32,
     pub wins: i32,
+    pub avg_return_pct: f64,
+    pub profit_factor: Option<f64>,
+    pub expectancy_pct: f64,
+    pub oos_total: Option<i32>,
+    pub oos_win_rate_pct: Option<f64>,
+    pub oos_avg_return_pct: Option<f64>,
+    pub oos_profit_factor: Option<f64>,
+    pub oos_expectancy_pct: Option<f64>,
+    pub promoted: Option<bool>,
+    pub run_id: Option<i32>,
 }
 
 impl OptimizedMa {
+    /*
+     * 最新の最適化(optimization_runs)の結果から、best_in_runで選んだものを返す。
+     */
     pub fn find_best_for_ma(
         conn: &mut PgConnection,
         pair_str: &str,
-    ) -> Result<Option<(i32, i32, f64)>, AppError> {
+        selection: &MaSelection,
+    ) -> Result<Option<OptimizedMa>, AppError> {
+        let Some(run) = OptimizationRun::latest(conn, pair_str, RUN_KIND)? else {
+            return Ok(None);
+        };
+
+        Self::best_in_run(conn, run.id, selection)
+    }
+
+    /*
+     * horizon_minutesに最も近いoffset_minutesで評価した結果のうち、
+     * サンプル数(total)がmin_samples以上で優位性のあるものから、objectiveが最も高いものを返す。
+     * 注文のcron間隔を渡すと、次の注文までの値動きで評価したパラメータが選ばれる。
+     */
+    pub fn best_in_run(
+        conn: &mut PgConnection,
+        run: i32,
+        selection: &MaSelection,
+    ) -> Result<Option<OptimizedMa>, AppError> {
         use crate::schema::optimized_mas::dsl::{
             optimized_mas, 
-            pair, 
-            win_rate_pct,
+            offset_minutes,
+            promoted,
+            run_id,
+            total,
         };
 
-        let result = optimized_mas
-            .filter(pair.eq(pair_str))
-            .filter(win_rate_pct.ge(50.0))
-            .order(win_rate_pct.desc())
-            .first::<OptimizedMa>(conn)
-            .optional()?;
-
-        if let Some(record) = result {
-            Ok(Some((record.short_ma, record.long_ma, record.win_rate_pct.unwrap())))
-        } else {
-            Ok(None)
+        let horizon_minutes = selection.horizon_minutes;
+
+        let offsets = optimized_mas
+            .filter(run_id.eq(run))
+            .select(offset_minutes)
+            .distinct()
+            .load::<i32>(conn)?;
+        let Some(offset) = offsets.into_iter().min_by_key(|o| (o - horizon_minutes).abs()) else {
+            return Ok(None);
+        };
+
+        let mut query = optimized_mas
+            .filter(run_id.eq(run))
+            .filter(offset_minutes.eq(offset))
+            .filter(total.ge(selection.min_samples))
+            .into_boxed();
+        if selection.promoted_only {
+            query = query.filter(promoted.eq(true));
         }
+        let candidates = query.load::<OptimizedMa>(conn)?;
+
+        let result = candidates
+            .into_iter()
+            .filter_map(|record| selection.objective.score(&record).map(|score| (score, record)))
+            .filter(|(score, _)| selection.objective.has_edge(*score))
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, record)| record);
+
+        Ok(result)
     }
 
-    #[allow(dead_code)]
+    /*
+     * tickersの価格系列を一度だけ読み込み、累積和から全ての移動平均を計算して、
+     * (short, long)の組み合わせ毎にクロスのoffset分後の勝率を並列に計算、まとめて保存する。
+     * 組み合わせとoffsetは、search_spaceの範囲。
+     * walk_forwardがあれば、検証期間(out-of-sample)の成績と採用可否も保存する。
+     * 結果は今回のoptimization_runsに紐付けて保存し、過去の結果は消さない。作成したrunのidを返す。
+     */
     pub fn create(
         conn: &mut PgConnection, 
         pair_str: &str,
-        offset: i32,
-    ) -> Result<(), AppError> {
-        for short in 5..=10 {
-            for long in (short + 5)..=30 {
-                let result = sql_query(
-                    r#"
-                    WITH base AS (
-                      SELECT
-                        id,
-                        pair,
-                        timestamp,
-                        last,
-                        ROW_NUMBER() OVER (ORDER BY timestamp) AS rn
-                      FROM tickers
-                      WHERE pair = $1
-                    ),
-                    sma_calc AS (
-                      SELECT
-                        b1.timestamp,
-                        b1.last,
-                        b1.rn,
-                        (SELECT AVG(b2.last)
-                         FROM base b2
-                         WHERE b2.rn BETWEEN b1.rn - ($2 - 1) AND b1.rn) AS sma_short,
-                        (SELECT AVG(b3.last)
-                         FROM base b3
-                         WHERE b3.rn BETWEEN b1.rn - ($3 - 1) AND b1.rn) AS sma_long
-                      FROM base b1
-                      WHERE b1.rn >= $3
-                    ),
-                    sma_with_diff AS (
-                      SELECT *,
-                        sma_short - sma_long AS diff,
-                        LAG(sma_short - sma_long) OVER (ORDER BY timestamp) AS prev_diff
-                      FROM sma_calc
-                    ),
-                    cross_events AS (
-                      SELECT timestamp AS cross_time, rn AS cross_rn, last AS cross_last, 'GC' AS cross_type
-                      FROM sma_with_diff
-                      WHERE prev_diff < 0 AND diff >= 0
-                      UNION ALL
-                      SELECT timestamp, rn, last, 'DC'
-                      FROM sma_with_diff
-                      WHERE prev_diff > 0 AND diff <= 0
-                    ),
-                    cross_with_result AS (
-                      SELECT
-                        g.cross_time,
-                        g.cross_type,
-                        g.cross_last,
-                        b2.timestamp AS after_time,
-                        b2.last AS after_last,
-                        CASE
-                          WHEN g.cross_type = 'GC' AND b2.last > g.cross_last THEN true
-                          WHEN g.cross_type = 'DC' AND b2.last < g.cross_last THEN true
-                          ELSE false
-                        END AS is_win
-                      FROM cross_events g
-                      JOIN base b2 
-                        ON b2.timestamp = (
-                          SELECT MIN(b3.timestamp)
-                          FROM base b3
-                          WHERE b3.timestamp >= g.cross_time + ($4 || ' minutes')::interval
-                        )
-                    )
-                    SELECT
-                      COUNT(*) AS total_crosses,
-                      COUNT(*) FILTER (WHERE is_win) AS wins_count,
-                      ROUND(COUNT(*) FILTER (WHERE is_win) * 100.0 / COUNT(*), 2) AS win_rate_pct
-                    FROM cross_with_result
-                    "#
-                )
-                .bind::<Text, _>(pair_str)
-                .bind::<Integer, _>(short)
-                .bind::<Integer, _>(long)
-                .bind::<Integer, _>(offset)
-                .get_result::<CrossoverStats>(conn)
-                .optional()?;
-
-                if let Some(r) = result {
-                    if r.total_crosses == 0 {
-                        continue;
+        search_space: &MaSearchSpace,
+        walk_forward: Option<&MaWalkForward>,
+    ) -> Result<i32, AppError> {
+        let series = Ticker::price_series(conn, pair_str)?;
+        let prices: Vec<f64> = series.iter().map(|(_, p)| *p).collect();
+        let spread_pct = Ticker::average_spread_pct(conn, pair_str)?.unwrap_or(0.0);
+
+        // prefix[i]は、prices[0..i]の合計
+        let mut prefix = Vec::with_capacity(prices.len() + 1);
+        prefix.push(0.0);
+        for price in prices.iter() {
+            prefix.push(prefix[prefix.len() - 1] + price);
+        }
+
+        let evaluated: Vec<Evaluated> = search_space.combinations()
+            .par_iter()
+            .flat_map_iter(|&(short, long)| {
+                let crosses = find_ma_crosses(&series, &prefix, short as usize, long as usize);
+
+                search_space.offsets.iter().map(|&offset| {
+                    Evaluated {
+                        short,
+                        long,
+                        offset,
+                        returns: crossover::indexed_returns(&series, &crosses, offset as i64),
                     }
+                }).collect::<Vec<_>>()
+            })
+            .collect();
+
+        let oos_returns = match walk_forward {
+            Some(walk_forward) => Some(walk_forward_returns(pair_str, &series, &evaluated, walk_forward, spread_pct)?),
+            None => None,
+        };
+
+        let mut new_optimized_mas = Vec::new();
+        for (i, evaluated) in evaluated.iter().enumerate() {
+            let returns: Vec<f64> = evaluated.returns.iter().map(|(_, r)| *r).collect();
+            let Some(stats) = crossover::CrossStats::from_returns(&returns, spread_pct) else {
+                continue;
+            };
+
+            let mut new_optimized_ma = NewOptimizedMa {
+                pair: pair_str.to_string(),
+                short_ma: evaluated.short,
+                long_ma: evaluated.long,
+                offset_minutes: evaluated.offset,
+                win_rate_pct: stats.win_rate_pct,
+                total: stats.total,
+                wins: stats.wins,
+                avg_return_pct: stats.avg_return_pct,
+                profit_factor: stats.profit_factor,
+                expectancy_pct: stats.expectancy_pct,
+                oos_total: None,
+                oos_win_rate_pct: None,
+                oos_avg_return_pct: None,
+                oos_profit_factor: None,
+                oos_expectancy_pct: None,
+                promoted: None,
+                run_id: None,
+            };
+
+            if let (Some(oos_returns), Some(walk_forward)) = (oos_returns.as_ref(), walk_forward) {
+                let oos_stats = crossover::CrossStats::from_returns(&oos_returns[i], spread_pct);
+                let objective = walk_forward.selection.objective;
+
+                new_optimized_ma.oos_total = Some(oos_stats.map(|s| s.total).unwrap_or(0));
+                new_optimized_ma.oos_win_rate_pct = oos_stats.map(|s| s.win_rate_pct);
+                new_optimized_ma.oos_avg_return_pct = oos_stats.map(|s| s.avg_return_pct);
+                new_optimized_ma.oos_profit_factor = oos_stats.and_then(|s| s.profit_factor);
+                new_optimized_ma.oos_expectancy_pct = oos_stats.map(|s| s.expectancy_pct);
+                new_optimized_ma.promoted = Some(oos_stats.is_some_and(|s| {
+                    s.total >= walk_forward.min_oos_samples && objective.has_edge(objective.score_stats(&s))
+                }));
+            }
+
+            new_optimized_mas.push(new_optimized_ma);
+        }
+
+        conn.transaction::<_, AppError, _>(|conn| {
+            let settings = serde_json::json!({
+                "search_space": search_space,
+                "walk_forward": walk_forward,
+            });
+            let run_id = OptimizationRun::create(conn, &NewOptimizationRun {
+                pair: pair_str.to_string(),
+                kind: RUN_KIND.to_string(),
+                settings: Some(settings),
+            })?;
+
+            for new_optimized_ma in new_optimized_mas.iter_mut() {
+                new_optimized_ma.run_id = Some(run_id);
+            }
+
+            for chunk in new_optimized_mas.chunks(INSERT_CHUNK_ROWS) {
+                diesel::insert_into(optimized_mas::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+
+            Ok(run_id)
+        })
+    }
+}
+
+// (short, long)をoffsetで評価した、クロス毎の(価格系列でのindex, リターン)
+struct Evaluated {
+    short: i32,
+    long: i32,
+    offset: i32,
+    returns: Vec<(usize, f64)>,
+}
+
+/*
+ * ウォークフォワード検証。学習期間毎に、クロスがmin_samples以上で優位性のある中から
+ * objectiveが最も高い組み合わせをoffset毎に選び、直後の検証期間でのリターンをその組み合わせに積み上げる。
+ * 戻り値はevaluatedと同じ順で、組み合わせ毎の検証期間のリターン。一度も選ばれなければ空。
+ * tickersが学習期間+検証期間に満たなければ、全てのパラメータが採用されなくなるのでエラーにする。
+ */
+fn walk_forward_returns(
+    pair_str: &str,
+    series: &[(NaiveDateTime, f64)],
+    evaluated: &[Evaluated],
+    walk_forward: &MaWalkForward,
+    spread_pct: f64,
+) -> Result<Vec<Vec<f64>>, AppError> {
+    let mut oos_returns = vec![Vec::new(); evaluated.len()];
+    let required_hours = walk_forward.train_hours + walk_forward.test_hours;
+    let available_hours = match (series.first(), series.last()) {
+        (Some((first, _)), Some((last, _))) => (*last - *first).num_hours(),
+        _ => 0,
+    };
+    if available_hours < required_hours {
+        return Err(AppError::InvalidData(format!(
+            "[{}] ウォークフォワードにはtickersが{}時間分必要ですが、{}時間分しかありません。MA_OPT_TRAIN_HOURS / MA_OPT_TEST_HOURSかTICKER_RETENTION_ROWSを見直してください",
+            pair_str, required_hours, available_hours
+        )));
+    }
+    let (first, last) = (series[0].0, series[series.len() - 1].0);
 
-                    let new_optimized_ma = NewOptimizedMa {
-                        pair: pair_str.to_string(),
-                        short_ma: short,
-                        long_ma: long,
-                        offset_minutes: offset,
-                        win_rate_pct: r.win_rate_pct.to_f64().unwrap_or(0.0),
-                        total: r.total_crosses as i32,
-                        wins: r.wins_count as i32,
-                    };
-
-                    diesel::insert_into(optimized_mas::table)
-                        .values(new_optimized_ma)
-                        .execute(conn)?;
-                }
+    let index_at = |time: NaiveDateTime| series.partition_point(|(t, _)| *t < time);
+    let objective = walk_forward.selection.objective;
+    let mut offsets: Vec<i32> = evaluated.iter().map(|e| e.offset).collect();
+    offsets.sort();
+    offsets.dedup();
+
+    let mut train_start = first;
+    loop {
+        let train_end = train_start + Duration::hours(walk_forward.train_hours);
+        let test_end = train_end + Duration::hours(walk_forward.test_hours);
+        if test_end > last {
+            break;
+        }
+        let (train_from, test_from, test_to) = (index_at(train_start), index_at(train_end), index_at(test_end));
+
+        for offset in offsets.iter() {
+            let best = evaluated.iter()
+                .enumerate()
+                .filter(|(_, e)| e.offset == *offset)
+                .filter_map(|(i, e)| {
+                    let in_sample: Vec<f64> = e.returns.iter()
+                        .filter(|(index, _)| (train_from..test_from).contains(index))
+                        .map(|(_, r)| *r)
+                        .collect();
+                    let stats = crossover::CrossStats::from_returns(&in_sample, spread_pct)?;
+                    let score = objective.score_stats(&stats);
+                    (stats.total >= walk_forward.selection.min_samples && objective.has_edge(score)).then_some((i, score))
+                })
+                .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            if let Some((i, _)) = best {
+                oos_returns[i].extend(evaluated[i].returns.iter()
+                    .filter(|(index, _)| (test_from..test_to).contains(index))
+                    .map(|(_, r)| *r));
             }
         }
 
-        diesel::sql_query("DELETE FROM optimized_mas WHERE created_at < NOW() - INTERVAL '1h'")
-            .execute(conn)?;
+        train_start += Duration::hours(walk_forward.test_hours);
+    }
+
+    Ok(oos_returns)
+}
+
+/*
+ * 長期MAが計算できる位置から、短期MA - 長期MAの符号の変化をクロスとして返す。
+ * 移動平均は累積和の差から求めるので、1組あたりO(n)。
+ */
+fn find_ma_crosses(
+    series: &[(NaiveDateTime, f64)],
+    prefix: &[f64],
+    short: usize,
+    long: usize,
+) -> Vec<crossover::Cross> {
+    if series.len() < long {
+        return Vec::new();
+    }
+
+    let sma = |period: usize, i: usize| (prefix[i + 1] - prefix[i + 1 - period]) / period as f64;
+    let diffs: Vec<f64> = ((long - 1)..series.len())
+        .map(|i| sma(short, i) - sma(long, i))
+        .collect();
+
+    crossover::find_crosses(&diffs, long - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_optimized_ma() -> NewOptimizedMa {
+        NewOptimizedMa {
+            pair: "btc".to_string(),
+            short_ma: 5,
+            long_ma: 20,
+            offset_minutes: 15,
+            win_rate_pct: 50.0,
+            total: 10,
+            wins: 5,
+            avg_return_pct: 0.1,
+            profit_factor: None,
+            expectancy_pct: 0.05,
+            oos_total: None,
+            oos_win_rate_pct: None,
+            oos_avg_return_pct: None,
+            oos_profit_factor: None,
+            oos_expectancy_pct: None,
+            promoted: None,
+            run_id: None,
+        }
+    }
+
+    #[test]
+    fn column_count_matches_new_optimized_ma() {
+        let value = serde_json::to_value(new_optimized_ma()).unwrap();
+        assert_eq!(value.as_object().unwrap().len(), NEW_OPTIMIZED_MA_COLUMNS);
+    }
+
+    #[test]
+    fn large_search_space_is_inserted_in_chunks_under_the_bind_limit() {
+        let search_space = MaSearchSpace {
+            short_min: 1,
+            short_max: 100,
+            short_step: 1,
+            long_gap: 1,
+            long_max: 200,
+            long_step: 1,
+            offsets: vec![15, 30, 60],
+        };
+        let rows = vec![new_optimized_ma(); search_space.combinations().len() * search_space.offsets.len()];
+
+        // 1回でINSERTすると上限を超える大きさ
+        assert!(rows.len() * NEW_OPTIMIZED_MA_COLUMNS > MAX_BIND_PARAMS);
 
-        Ok(())
+        let chunks: Vec<&[NewOptimizedMa]> = rows.chunks(INSERT_CHUNK_ROWS).collect();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.len() * NEW_OPTIMIZED_MA_COLUMNS <= MAX_BIND_PARAMS));
+        assert_eq!(chunks.iter().map(|chunk| chunk.len()).sum::<usize>(), rows.len());
     }
 }
@@ -0,0 +1,164 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::error::AppError;
+use crate::models::optimization_run::{NewOptimizationRun, OptimizationRun};
+use crate::models::util::MAX_BIND_PARAMS;
+use crate::schema::optimized_macds;
+use crate::strategies::macd::{MacdSearchSpace, MacdSelection};
+
+// optimization_runsのkind
+pub const RUN_KIND: &str = "macd";
+
+// NewOptimizedMacdの列数。1回のINSERTは、バインドパラメータが上限に収まる行数ずつにする
+const NEW_OPTIMIZED_MACD_COLUMNS: usize = 12;
+const INSERT_CHUNK_ROWS: usize = MAX_BIND_PARAMS / NEW_OPTIMIZED_MACD_COLUMNS;
+
+#[derive(Debug, Queryable, Serialize, Deserialize)]
+#[diesel(table_name = optimized_macds)]
+pub struct OptimizedMacd {
+    pub id: i32,
+    pub pair: String,
+    pub fast_period: i32,
+    pub slow_period: i32,
+    pub signal_period: i32,
+    pub offset_minutes: i32,
+    pub win_rate_pct: Option<f64>,
+    pub total: Option<i32>,
+    pub wins: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub avg_return_pct: Option<f64>,
+    pub profit_factor: Option<f64>,
+    pub expectancy_pct: Option<f64>,
+    pub run_id: Option<i32>,
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = optimized_macds)]
+pub struct NewOptimizedMacd {
+    pub pair: String,
+    pub fast_period: i32,
+    pub slow_period: i32,
+    pub signal_period: i32,
+    pub offset_minutes: i32,
+    pub win_rate_pct: f64,
+    pub total: i32,
+    pub wins: i32,
+    pub avg_return_pct: f64,
+    pub profit_factor: Option<f64>,
+    pub expectancy_pct: f64,
+    pub run_id: Option<i32>,
+}
+
+impl OptimizedMacd {
+    /*
+     * 最新の最適化(optimization_runs)の結果のうち、horizon_minutesに最も近いoffsetで評価したものから、
+     * サンプル数(total)がmin_samples以上で優位性のあるものの中で、objectiveが最も高いものを返す。
+     */
+    pub fn find_best(
+        conn: &mut PgConnection,
+        pair_str: &str,
+        selection: &MacdSelection,
+    ) -> Result<Option<OptimizedMacd>, AppError> {
+        use crate::schema::optimized_macds::dsl::{
+            optimized_macds,
+            offset_minutes,
+            run_id,
+            total,
+        };
+
+        let Some(run) = OptimizationRun::latest(conn, pair_str, RUN_KIND)? else {
+            return Ok(None);
+        };
+
+        let horizon_minutes = selection.horizon_minutes;
+
+        let offsets = optimized_macds
+            .filter(run_id.eq(run.id))
+            .select(offset_minutes)
+            .distinct()
+            .load::<i32>(conn)?;
+        let Some(offset) = offsets.into_iter().min_by_key(|o| (o - horizon_minutes).abs()) else {
+            return Ok(None);
+        };
+
+        let candidates = optimized_macds
+            .filter(run_id.eq(run.id))
+            .filter(offset_minutes.eq(offset))
+            .filter(total.ge(selection.min_samples))
+            .load::<OptimizedMacd>(conn)?;
+
+        let result = candidates
+            .into_iter()
+            .filter_map(|record| record.score(selection).map(|score| (score, record)))
+            .filter(|(score, _)| selection.objective.has_edge(*score))
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, record)| record);
+
+        Ok(result)
+    }
+
+    fn score(&self, selection: &MacdSelection) -> Option<f64> {
+        selection.objective.score_columns(self.win_rate_pct, self.avg_return_pct, self.profit_factor, self.expectancy_pct)
+    }
+
+    /*
+     * 最適化結果を今回のoptimization_runsに紐付けて保存し、作成したrunのidを返す。過去の結果は消さない。
+     */
+    pub fn create(
+        conn: &mut PgConnection,
+        pair_str: &str,
+        search_space: &MacdSearchSpace,
+        new_optimized_macds: &[NewOptimizedMacd],
+    ) -> Result<i32, AppError> {
+        conn.transaction::<_, AppError, _>(|conn| {
+            let settings = serde_json::json!({ "search_space": search_space });
+            let run_id = OptimizationRun::create(conn, &NewOptimizationRun {
+                pair: pair_str.to_string(),
+                kind: RUN_KIND.to_string(),
+                settings: Some(settings),
+            })?;
+
+            let new_optimized_macds: Vec<NewOptimizedMacd> = new_optimized_macds
+                .iter()
+                .cloned()
+                .map(|new_optimized_macd| NewOptimizedMacd { run_id: Some(run_id), ..new_optimized_macd })
+                .collect();
+
+            for chunk in new_optimized_macds.chunks(INSERT_CHUNK_ROWS) {
+                diesel::insert_into(optimized_macds::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+
+            Ok(run_id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_count_matches_new_optimized_macd() {
+        let new_optimized_macd = NewOptimizedMacd {
+            pair: "btc".to_string(),
+            fast_period: 12,
+            slow_period: 26,
+            signal_period: 9,
+            offset_minutes: 15,
+            win_rate_pct: 50.0,
+            total: 10,
+            wins: 5,
+            avg_return_pct: 0.1,
+            profit_factor: None,
+            expectancy_pct: 0.05,
+            run_id: None,
+        };
+
+        let value = serde_json::to_value(new_optimized_macd).unwrap();
+        assert_eq!(value.as_object().unwrap().len(), NEW_OPTIMIZED_MACD_COLUMNS);
+    }
+}
@@ -2,6 +2,7 @@ use chrono::NaiveDateTime;
 
 use diesel::prelude::*;
 use serde::{Serialize, Deserialize};
+use serde_json::Value;
 
 use crate::error::AppError;
 use crate::schema::orders;
@@ -13,19 +14,26 @@ pub struct Order {
     pub id: i32,
     pub rate: f64,
     pub crypto_amount: f64,
-    pub jpy_amount: f64,
     pub order_type: String,
-    pub pari: f64,
+    pub pair: String,
+    pub created_at: NaiveDateTime,
     pub buy_rate: Option<f64>,
     pub sell_rate: Option<f64>,
     pub spread_ratio: Option<f64>,
+    pub jpy_amount: Option<f64>,
+    pub comment: Option<String>,
     pub spread_threshold: Option<f64>,
+    pub api_call_success_at: Option<NaiveDateTime>,
     pub ma_short: Option<i32>,
     pub ma_long: Option<i32>,
     pub ma_win_rate: Option<f64>,
-    pub comment: Option<String>,
-    pub api_call_success_at: Option<NaiveDateTime>,
-    pub created_at: NaiveDateTime,
+    pub contribution_jpy: Option<f64>,
+    pub strategy_name: Option<String>,
+    pub strategy_version: Option<String>,
+    pub confidence: Option<f64>,
+    pub position_fraction: Option<f64>,
+    pub signal_metadata: Option<Value>,
+    pub optimization_run_id: Option<i32>,
 }
 
 impl Order {
@@ -33,13 +41,48 @@ impl Order {
     pub fn create(
         conn: &mut PgConnection, 
         new_order: &NewOrder
-     ) -> Result<(), AppError> {
+     ) -> Result<i32, AppError> {
 
-        diesel::insert_into(orders)
+        let order_id = diesel::insert_into(orders)
             .values(new_order)
-            .execute(conn)?;
+            .returning(id)
+            .get_result::<i32>(conn)?;
+
+        Ok(order_id)
+    }
+
+    /*
+     * 積立(DCA)で最後に約定した時刻。
+     */
+    pub fn last_contribution_at(
+        conn: &mut PgConnection,
+        pair_str: &str,
+    ) -> Result<Option<NaiveDateTime>, AppError> {
+        let result = orders
+            .filter(pair.eq(pair_str))
+            .filter(contribution_jpy.is_not_null())
+            .filter(api_call_success_at.is_not_null())
+            .select(diesel::dsl::max(created_at))
+            .first::<Option<NaiveDateTime>>(conn)?;
+
+        Ok(result)
+    }
+
+    /*
+     * since以降に注文が通った成行注文(market_buy / market_sell)を古い順で返す。
+     */
+    pub fn filled_market_since(
+        conn: &mut PgConnection,
+        since: NaiveDateTime,
+    ) -> Result<Vec<Order>, AppError> {
+        let result = orders
+            .filter(created_at.ge(since))
+            .filter(api_call_success_at.is_not_null())
+            .filter(order_type.eq_any(["market_buy", "market_sell"]))
+            .order(created_at.asc())
+            .load::<Order>(conn)?;
 
-        Ok(())
+        Ok(result)
     }
 }
 
@@ -60,6 +103,13 @@ pub struct NewOrder {
     pub ma_win_rate: Option<f64>,
     pub comment: Option<String>,
     pub api_call_success_at: Option<NaiveDateTime>,
+    pub contribution_jpy: Option<f64>,
+    pub strategy_name: Option<String>,
+    pub strategy_version: Option<String>,
+    pub confidence: Option<f64>,
+    pub position_fraction: Option<f64>,
+    pub signal_metadata: Option<Value>,
+    pub optimization_run_id: Option<i32>,
 }
 
 impl NewOrder {
@@ -79,6 +129,13 @@ impl NewOrder {
             ma_win_rate: Some(0.0),
             comment: None,
             api_call_success_at: None,
+            contribution_jpy: None,
+            strategy_name: None,
+            strategy_version: None,
+            confidence: None,
+            position_fraction: None,
+            signal_metadata: None,
+            optimization_run_id: None,
         }
     }
 }
@@ -0,0 +1,265 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+use crate::schema::order_book_snapshots;
+
+/*
+ * 板情報。asksは安い順、bidsは高い順に並べて保持する。
+ * 各レベルは(rate, amount)。
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderBook {
+    pub pair: String,
+    pub asks: Vec<(f64, f64)>,
+    pub bids: Vec<(f64, f64)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum FillAmount {
+    Jpy(f64),
+    Crypto(f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct FillEstimate {
+    pub best_price: f64,
+    pub avg_price: f64,
+    pub crypto_amount: f64,
+    pub jpy_amount: f64,
+    // 最良気配からの平均約定価格の乖離(%)
+    pub slippage_pct: f64,
+    // 板の厚みが足りず、全量約定しない場合はfalse
+    pub fully_filled: bool,
+}
+
+impl OrderBook {
+    pub fn new(pair: String, mut asks: Vec<(f64, f64)>, mut bids: Vec<(f64, f64)>) -> Self {
+        asks.sort_by(|a, b| a.0.total_cmp(&b.0));
+        bids.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        Self { pair, asks, bids }
+    }
+
+    // 成行買いはasksを食っていく
+    pub fn estimate_buy(&self, amount: FillAmount) -> Option<FillEstimate> {
+        estimate_fill(&self.asks, amount)
+    }
+
+    // 成行売りはbidsを食っていく
+    pub fn estimate_sell(&self, amount: FillAmount) -> Option<FillEstimate> {
+        estimate_fill(&self.bids, amount)
+    }
+
+    /*
+     * 想定スリッページが上限を超える場合に、見送りの理由を返す。
+     * market_buyのamountはJPY、market_sellのamountは仮想通貨の量。
+     */
+    pub fn slippage_rejection(
+        &self,
+        order_type: &str,
+        amount: f64,
+        max_slippage_pct: f64,
+    ) -> Option<String> {
+        let estimate = match order_type {
+            "market_buy" => self.estimate_buy(FillAmount::Jpy(amount)),
+            "market_sell" => self.estimate_sell(FillAmount::Crypto(amount)),
+            _ => return None,
+        };
+
+        match estimate {
+            None => Some("板情報なし、見送り".to_string()),
+            Some(e) if !e.fully_filled => Some("板の厚み不足で全量約定しない、見送り".to_string()),
+            Some(e) if e.slippage_pct > max_slippage_pct => Some(format!(
+                "想定スリッページ:[{:.3}% > {}%]、見送り",
+                e.slippage_pct,
+                max_slippage_pct,
+            )),
+            Some(_) => None,
+        }
+    }
+}
+
+fn estimate_fill(levels: &[(f64, f64)], amount: FillAmount) -> Option<FillEstimate> {
+    let best_price = levels.first()?.0;
+
+    let target = match amount {
+        FillAmount::Jpy(v) | FillAmount::Crypto(v) => v,
+    };
+    if target <= 0.0 {
+        return None;
+    }
+
+    let mut crypto_amount = 0.0;
+    let mut jpy_amount = 0.0;
+    let mut remaining = target;
+
+    for &(rate, size) in levels.iter() {
+        if remaining <= 0.0 {
+            break;
+        }
+
+        let (take_crypto, take_jpy) = match amount {
+            FillAmount::Jpy(_) => {
+                let take_jpy = remaining.min(rate * size);
+                (take_jpy / rate, take_jpy)
+            },
+            FillAmount::Crypto(_) => {
+                let take_crypto = remaining.min(size);
+                (take_crypto, take_crypto * rate)
+            },
+        };
+
+        crypto_amount += take_crypto;
+        jpy_amount += take_jpy;
+        remaining -= match amount {
+            FillAmount::Jpy(_) => take_jpy,
+            FillAmount::Crypto(_) => take_crypto,
+        };
+    }
+
+    if crypto_amount <= 0.0 {
+        return None;
+    }
+
+    let avg_price = jpy_amount / crypto_amount;
+
+    Some(FillEstimate {
+        best_price,
+        avg_price,
+        crypto_amount,
+        jpy_amount,
+        slippage_pct: ((avg_price - best_price) / best_price).abs() * 100.0,
+        fully_filled: remaining <= target * 1e-9,
+    })
+}
+
+#[derive(Debug, Queryable, Serialize, Deserialize)]
+#[diesel(table_name = order_book_snapshots)]
+pub struct OrderBookSnapshot {
+    pub id: i32,
+    pub pair: String,
+    pub best_bid: f64,
+    pub best_ask: f64,
+    pub bids: Value,
+    pub asks: Value,
+    pub created_at: NaiveDateTime,
+}
+
+impl OrderBookSnapshot {
+    pub fn create(
+        conn: &mut PgConnection,
+        new_snapshot: &NewOrderBookSnapshot,
+    ) -> Result<(), AppError> {
+        diesel::insert_into(order_book_snapshots::table)
+            .values(new_snapshot)
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize)]
+#[diesel(table_name = order_book_snapshots)]
+pub struct NewOrderBookSnapshot {
+    pub pair: String,
+    pub best_bid: f64,
+    pub best_ask: f64,
+    pub bids: Value,
+    pub asks: Value,
+}
+
+impl NewOrderBookSnapshot {
+    pub fn from_order_book(order_book: &OrderBook) -> Option<Self> {
+        Some(Self {
+            pair: order_book.pair.clone(),
+            best_bid: order_book.bids.first()?.0,
+            best_ask: order_book.asks.first()?.0,
+            bids: json!(order_book.bids),
+            asks: json!(order_book.asks),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // asks: 100円に1枚、110円に1枚
+    fn book() -> OrderBook {
+        OrderBook::new(
+            "btc".to_string(),
+            vec![(110.0, 1.0), (100.0, 1.0)],
+            vec![(90.0, 1.0), (95.0, 2.0)],
+        )
+    }
+
+    #[test]
+    fn new_sorts_asks_ascending_and_bids_descending() {
+        let book = book();
+        assert_eq!(book.asks, vec![(100.0, 1.0), (110.0, 1.0)]);
+        assert_eq!(book.bids, vec![(95.0, 2.0), (90.0, 1.0)]);
+    }
+
+    #[test]
+    fn buy_within_the_best_level_has_no_slippage() {
+        let estimate = book().estimate_buy(FillAmount::Jpy(50.0)).unwrap();
+        assert_eq!(estimate.avg_price, 100.0);
+        assert_eq!(estimate.crypto_amount, 0.5);
+        assert_eq!(estimate.slippage_pct, 0.0);
+        assert!(estimate.fully_filled);
+    }
+
+    #[test]
+    fn buy_across_levels_averages_the_price() {
+        // 100円で1枚(100JPY)、110円で0.5枚(55JPY)
+        let estimate = book().estimate_buy(FillAmount::Jpy(155.0)).unwrap();
+        assert!((estimate.crypto_amount - 1.5).abs() < 1e-9);
+        assert!((estimate.avg_price - 155.0 / 1.5).abs() < 1e-9);
+        assert!((estimate.slippage_pct - (155.0 / 1.5 - 100.0)).abs() < 1e-9);
+        assert!(estimate.fully_filled);
+    }
+
+    #[test]
+    fn sell_beyond_the_depth_is_partial() {
+        let estimate = book().estimate_sell(FillAmount::Crypto(5.0)).unwrap();
+        assert_eq!(estimate.crypto_amount, 3.0);
+        assert_eq!(estimate.jpy_amount, 95.0 * 2.0 + 90.0);
+        assert!(!estimate.fully_filled);
+    }
+
+    #[test]
+    fn empty_book_or_zero_amount_has_no_estimate() {
+        let empty = OrderBook::new("btc".to_string(), Vec::new(), Vec::new());
+        assert!(empty.estimate_buy(FillAmount::Jpy(100.0)).is_none());
+        assert!(book().estimate_buy(FillAmount::Jpy(0.0)).is_none());
+    }
+
+    #[test]
+    fn slippage_rejection_rejects_an_empty_book() {
+        let empty = OrderBook::new("btc".to_string(), Vec::new(), Vec::new());
+        assert_eq!(empty.slippage_rejection("market_buy", 100.0, 1.0), Some("板情報なし、見送り".to_string()));
+    }
+
+    #[test]
+    fn slippage_rejection_rejects_partial_depth() {
+        let reason = book().slippage_rejection("market_sell", 5.0, 100.0).unwrap();
+        assert!(reason.contains("板の厚み不足"));
+    }
+
+    #[test]
+    fn slippage_rejection_compares_with_the_threshold() {
+        // 155JPY分の買いは約3.33%のスリッページ
+        assert!(book().slippage_rejection("market_buy", 155.0, 3.0).unwrap().contains("想定スリッページ"));
+        assert!(book().slippage_rejection("market_buy", 155.0, 3.5).is_none());
+        assert!(book().slippage_rejection("market_buy", 50.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn slippage_rejection_ignores_other_order_types() {
+        let empty = OrderBook::new("btc".to_string(), Vec::new(), Vec::new());
+        assert!(empty.slippage_rejection("hold", 100.0, 1.0).is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::error::AppError;
+use crate::schema::signal_votes;
+
+/*
+ * アンサンブル戦略の各メンバーの投票。どの戦略が注文を決めたかを後から追えるようにする。
+ */
+#[derive(Debug, Queryable, Serialize, Deserialize)]
+#[diesel(table_name = signal_votes)]
+pub struct SignalVote {
+    pub id: i32,
+    pub order_id: i32,
+    pub strategy: String,
+    pub vote: String,
+    pub weight: f64,
+    pub reason: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl SignalVote {
+    /*
+     * new_signal_votesのorder_idを上書きして保存する。
+     */
+    pub fn create_for_order(
+        conn: &mut PgConnection,
+        order_id: i32,
+        new_signal_votes: &mut [NewSignalVote],
+    ) -> Result<(), AppError> {
+        if new_signal_votes.is_empty() {
+            return Ok(());
+        }
+
+        for vote in new_signal_votes.iter_mut() {
+            vote.order_id = order_id;
+        }
+
+        diesel::insert_into(signal_votes::table)
+            .values(&*new_signal_votes)
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Insertable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = signal_votes)]
+pub struct NewSignalVote {
+    pub order_id: i32,
+    pub strategy: String,
+    pub vote: String,
+    pub weight: f64,
+    pub reason: Option<String>,
+}
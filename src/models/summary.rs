@@ -42,6 +42,30 @@ impl Summary {
 
         Ok(())
     }
+
+    pub fn latest(conn: &mut PgConnection) -> Result<Option<Summary>, AppError> {
+        let result = summaries
+            .order(created_at.desc())
+            .first::<Summary>(conn)
+            .optional()?;
+
+        Ok(result)
+    }
+
+    /*
+     * since以降のsummariesを古い順で返す。
+     */
+    pub fn since(
+        conn: &mut PgConnection,
+        since: NaiveDateTime,
+    ) -> Result<Vec<Summary>, AppError> {
+        let result = summaries
+            .filter(created_at.ge(since))
+            .order(created_at.asc())
+            .load::<Summary>(conn)?;
+
+        Ok(result)
+    }
 }
 
 #[derive(Debug, Insertable, Serialize, Deserialize)]
@@ -31,6 +31,20 @@ impl SummaryRecord {
 
         Ok(())
     }
+
+    /*
+     * summary_idsのいずれかに紐付くsummary_recordsを返す。
+     */
+    pub fn for_summaries(
+        conn: &mut PgConnection,
+        summary_ids: &[i32],
+    ) -> Result<Vec<SummaryRecord>, AppError> {
+        let result = summary_records
+            .filter(summary_id.eq_any(summary_ids))
+            .load::<SummaryRecord>(conn)?;
+
+        Ok(result)
+    }
 }
 
 #[derive(Debug, Insertable, Serialize, Deserialize)]
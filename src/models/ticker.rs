@@ -35,9 +35,124 @@ impl Ticker {
         Ok(())
     }
 
+    /*
+     * 直近limit件のlastを古い順で返す。
+     */
+    pub fn recent_prices(
+        conn: &mut PgConnection,
+        currency: &str,
+        limit: i64,
+    ) -> Result<Vec<f64>, AppError> {
+        let mut prices = tickers
+            .filter(pair.eq(currency))
+            .order(timestamp.desc())
+            .limit(limit)
+            .select(last)
+            .load::<f64>(conn)?;
+
+        prices.reverse();
+        Ok(prices)
+    }
+
+    /*
+     * pairの全期間の(timestamp, last)を古い順で返す。
+     */
+    pub fn price_series(
+        conn: &mut PgConnection,
+        currency: &str,
+    ) -> Result<Vec<(NaiveDateTime, f64)>, AppError> {
+        let rows = tickers
+            .filter(pair.eq(currency))
+            .filter(timestamp.is_not_null())
+            .order(timestamp.asc())
+            .select((timestamp, last))
+            .load::<(Option<NaiveDateTime>, f64)>(conn)?;
+
+        Ok(rows.into_iter().filter_map(|(t, p)| t.map(|t| (t, p))).collect())
+    }
+
+    /*
+     * pairの最新の(timestamp, bid, ask)。
+     */
+    pub fn latest_quote(
+        conn: &mut PgConnection,
+        currency: &str,
+    ) -> Result<Option<(NaiveDateTime, f64, f64)>, AppError> {
+        let row = tickers
+            .filter(pair.eq(currency))
+            .filter(timestamp.is_not_null())
+            .order(timestamp.desc())
+            .select((timestamp, bid, ask))
+            .first::<(Option<NaiveDateTime>, f64, f64)>(conn)
+            .optional()?;
+
+        Ok(row.and_then(|(t, b, a)| t.map(|t| (t, b, a))))
+    }
+
+    /*
+     * since以降の(timestamp, last)を古い順で返す。
+     */
+    pub fn price_series_since(
+        conn: &mut PgConnection,
+        currency: &str,
+        since: NaiveDateTime,
+    ) -> Result<Vec<(NaiveDateTime, f64)>, AppError> {
+        let rows = tickers
+            .filter(pair.eq(currency))
+            .filter(timestamp.ge(since))
+            .order(timestamp.asc())
+            .select((timestamp, last))
+            .load::<(Option<NaiveDateTime>, f64)>(conn)?;
+
+        Ok(rows.into_iter().filter_map(|(t, p)| t.map(|t| (t, p))).collect())
+    }
+
+    /*
+     * (ask - bid) / bid の平均(%)。データがなければNone。
+     */
+    pub fn average_spread_pct(
+        conn: &mut PgConnection,
+        currency: &str,
+    ) -> Result<Option<f64>, AppError> {
+        let result = tickers
+            .filter(pair.eq(currency))
+            .filter(bid.gt(0.0))
+            .select(diesel::dsl::avg((ask - bid) / bid * 100.0))
+            .first::<Option<f64>>(conn)?;
+
+        Ok(result)
+    }
+
+    /*
+     * since以降のlastの平均。データがなければNone。
+     */
+    pub fn average_since(
+        conn: &mut PgConnection,
+        currency: &str,
+        since: NaiveDateTime,
+    ) -> Result<Option<f64>, AppError> {
+        let result = tickers
+            .filter(pair.eq(currency))
+            .filter(timestamp.ge(since))
+            .select(diesel::dsl::avg(last))
+            .first::<Option<f64>>(conn)?;
+
+        Ok(result)
+    }
+
+    pub fn pairs(conn: &mut PgConnection) -> Result<Vec<String>, AppError> {
+        let result = tickers
+            .select(pair)
+            .distinct()
+            .order(pair.asc())
+            .load::<String>(conn)?;
+
+        Ok(result)
+    }
+
+    // 全通貨合わせてretention_threshold件以上になったら、古いものから1割消す
     #[allow(dead_code)]
-    pub fn delete_oldest(conn: &mut PgConnection) -> Result<usize, AppError> {
-        let retention_threshold = 1000;
+    pub fn delete_oldest(conn: &mut PgConnection, retention_threshold: i64) -> Result<usize, AppError> {
         let purge_ratio = 0.10;
 
         let total: i64 = tickers
@@ -3,6 +3,9 @@ use chrono::{NaiveDateTime, Utc, TimeZone};
 use serde::Serializer;
 use serde::Deserializer;
 
+// Postgresの1文あたりのバインドパラメータの上限。まとめてINSERTする行数はこれに収める
+pub const MAX_BIND_PARAMS: usize = 65_535;
+
 pub fn serialize_naive_datetime<S>(datetime: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
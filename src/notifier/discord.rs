@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use reqwest::multipart::{Form, Part};
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+use crate::notifier::notification::{EventKind, Notification};
+use crate::notifier::notifier_trait::Notifier;
+
+/*
+ * DiscordのWebhookにembedで送る。添付があればmultipartでファイルも付ける。
+ */
+pub struct DiscordNotifier {
+    pub webhook_url: String,
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), AppError> {
+        let client = Client::new();
+        let payload = payload(notification);
+
+        if notification.attachments.is_empty() {
+            client.post(&self.webhook_url).json(&payload).send().await?.error_for_status()?;
+            return Ok(());
+        }
+
+        let mut form = Form::new().text("payload_json", payload.to_string());
+        for (i, path) in notification.attachments.iter().enumerate() {
+            let filename = path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or(format!("file{}", i));
+            form = form.part(format!("files[{}]", i), Part::bytes(std::fs::read(path)?).file_name(filename));
+        }
+        client.post(&self.webhook_url).multipart(form).send().await?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+// embedのfieldsは25個まで
+pub fn payload(notification: &Notification) -> Value {
+    let color = match notification.kind {
+        EventKind::Fill => 0x2ecc71,
+        EventKind::Error => 0xe74c3c,
+        EventKind::DailyReport => 0x3498db,
+        EventKind::RiskHalt => 0xe67e22,
+        EventKind::Alert => 0xf1c40f,
+    };
+
+    let fields: Vec<Value> = notification.fields.iter()
+        .take(25)
+        .map(|field| json!({"name": field.name, "value": field.value, "inline": true}))
+        .collect();
+
+    json!({
+        "embeds": [{
+            "title": notification.title,
+            "description": notification.text,
+            "color": color,
+            "fields": fields,
+        }]
+    })
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::error::AppError;
+use crate::notifier::notification::Notification;
+use crate::notifier::notifier_trait::Notifier;
+
+/*
+ * SMTPでメールを送る。STARTTLSで接続し、userがあれば認証する。
+ * 本文はtextとfields、添付はそのままファイルとして付ける。
+ */
+pub struct EmailNotifier {
+    pub host: String,
+    pub port: u16,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn email_error<E: std::fmt::Display>(e: E) -> AppError {
+    AppError::InvalidData(format!("Email error: {}", e))
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), AppError> {
+        let mut builder = Message::builder()
+            .from(self.from.parse::<Mailbox>().map_err(email_error)?)
+            .subject(format!("[{}] {}", notification.kind, notification.title));
+        for to in self.to.iter() {
+            builder = builder.to(to.parse::<Mailbox>().map_err(email_error)?);
+        }
+
+        let mut body = notification.text.clone();
+        for field in notification.fields.iter() {
+            body.push_str(&format!("\n{}: {}", field.name, field.value));
+        }
+
+        let mut multipart = MultiPart::mixed().singlepart(SinglePart::plain(body));
+        for path in notification.attachments.iter() {
+            let filename = path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or("attachment".to_string());
+            let content_type = if path.extension().is_some_and(|ext| ext == "png") {
+                ContentType::parse("image/png").map_err(email_error)?
+            } else {
+                ContentType::parse("application/octet-stream").map_err(email_error)?
+            };
+            multipart = multipart.singlepart(Attachment::new(filename).body(std::fs::read(path)?, content_type));
+        }
+        let message = builder.multipart(multipart).map_err(email_error)?;
+
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.host)
+            .map_err(email_error)?
+            .port(self.port);
+        if let (Some(user), Some(password)) = (self.user.clone(), self.password.clone()) {
+            transport = transport.credentials(Credentials::new(user, password));
+        }
+        transport.build().send(message).await.map_err(email_error)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+pub mod notifier_trait;
+pub mod notification;
+pub mod router;
+pub mod slack;
+pub mod discord;
+pub mod email;
+pub mod webhook;
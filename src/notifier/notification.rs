@@ -0,0 +1,157 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::models::order::NewOrder;
+use crate::models::summary::NewSummary;
+use crate::models::summary_record::NewSummaryRecord;
+
+// 送り先を振り分けるためのイベントの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    // 約定と、約定後のレポート
+    Fill,
+    Error,
+    DailyReport,
+    // 注文の停止
+    RiskHalt,
+    // repositories::alertのルールに引っかかったもの
+    Alert,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 5] = [EventKind::Fill, EventKind::Error, EventKind::DailyReport, EventKind::RiskHalt, EventKind::Alert];
+
+    pub fn parse(name: &str) -> Result<Self, AppError> {
+        match name.trim() {
+            "fill" => Ok(EventKind::Fill),
+            "error" => Ok(EventKind::Error),
+            "daily_report" => Ok(EventKind::DailyReport),
+            "risk_halt" => Ok(EventKind::RiskHalt),
+            "alert" => Ok(EventKind::Alert),
+            other => Err(AppError::InvalidData(format!("Invalid event kind: {}", other))),
+        }
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EventKind::Fill => "fill",
+            EventKind::Error => "error",
+            EventKind::DailyReport => "daily_report",
+            EventKind::RiskHalt => "risk_halt",
+            EventKind::Alert => "alert",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+}
+
+/*
+ * 送り先に依存しない通知の内容。各Notifierが、Block KitやEmbed、メール本文に組み立てる。
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub kind: EventKind,
+    pub title: String,
+    // 改行区切りのプレーンテキスト
+    pub text: String,
+    pub fields: Vec<Field>,
+    // チャートの画像など。送れる送り先だけ添付する
+    pub attachments: Vec<PathBuf>,
+}
+
+impl Notification {
+    pub fn new(kind: EventKind, title: &str, text: &str) -> Self {
+        Self {
+            kind,
+            title: title.to_string(),
+            text: text.to_string(),
+            fields: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    pub fn fill(new_order: &NewOrder) -> Self {
+        if new_order.order_type == "market_buy" {
+            Self::new(
+                EventKind::Fill,
+                &format!("[{}][購入]", new_order.pair.to_uppercase()),
+                &format!("{:.2}JPY", new_order.jpy_amount),
+            )
+        } else {
+            Self::new(
+                EventKind::Fill,
+                &format!("[{}][売却]", new_order.pair.to_uppercase()),
+                &new_order.crypto_amount.to_string(),
+            )
+        }
+    }
+
+    // summariesの内容。通貨毎の数量はfieldsに入れる
+    pub fn report(
+        kind: EventKind,
+        title: &str,
+        new_summary: &NewSummary,
+        new_summary_records: &[NewSummaryRecord],
+        charts: Vec<PathBuf>,
+    ) -> Self {
+        let text = format!(
+            "Total invested: {}円\nTotal JPY value: {}円\nP/L: {}円",
+            new_summary.total_invested,
+            new_summary.total_jpy_value.round() as i64,
+            new_summary.pl.round() as i64,
+        );
+
+        let mut notification = Self::new(kind, title, &text);
+        notification.fields = new_summary_records.iter()
+            .map(|record| Field { name: record.currency.to_uppercase(), value: record.amount.to_string() })
+            .collect();
+        notification.attachments = charts;
+        notification
+    }
+
+    pub fn error(title: &str, error: &AppError) -> Self {
+        Self::new(EventKind::Error, title, &error.to_string())
+    }
+
+    pub fn risk_halt(title: &str, reason: &str) -> Self {
+        Self::new(EventKind::RiskHalt, title, reason)
+    }
+
+    pub fn alert(title: &str, message: &str) -> Self {
+        Self::new(EventKind::Alert, title, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_kind_parses_every_name_it_displays() {
+        for kind in EventKind::ALL.iter() {
+            assert_eq!(EventKind::parse(&kind.to_string()).unwrap(), *kind);
+        }
+    }
+
+    #[test]
+    fn event_kind_parse_trims_whitespace() {
+        assert_eq!(EventKind::parse(" risk_halt ").unwrap(), EventKind::RiskHalt);
+    }
+
+    #[test]
+    fn event_kind_parse_rejects_unknown_names() {
+        assert!(EventKind::parse("fills").is_err());
+        assert!(EventKind::parse("").is_err());
+    }
+}
@@ -0,0 +1,13 @@
+use async_trait::async_trait;
+
+use crate::error::AppError;
+use crate::notifier::notification::Notification;
+
+#[async_trait]
+pub trait Notifier {
+    /*
+     * notificationを送る。どのイベントをどこに送るかはrouterが決めるので、ここでは届けるだけ。
+     * attachmentsを送れない送り先は、添付を無視してテキストだけ送る。
+     */
+    async fn send(&self, notification: &Notification) -> Result<(), AppError>;
+}
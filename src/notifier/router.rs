@@ -0,0 +1,244 @@
+use std::env;
+
+use dotenvy::dotenv;
+use log::{error, info};
+
+use crate::api::slack::{self, SlackBot};
+use crate::config;
+use crate::error::AppError;
+use crate::notifier::{
+    discord::DiscordNotifier,
+    email::EmailNotifier,
+    notification::{EventKind, Notification},
+    notifier_trait::Notifier,
+    slack::SlackNotifier,
+    webhook::WebhookNotifier,
+};
+
+/*
+ * イベントの種類毎に、どの送り先に送るかを振り分ける。
+ * 設定はジョブの開始時に一度だけ読み込み、repositoriesには作ったrouterを渡す。
+ *
+ * [envの設定]
+ * NOTIFY_CHANNELS=main,ops (送り先の名前をカンマ区切り。未設定ならSLACK_*のSlackに全イベントを送る)
+ * NOTIFY_{NAME}_TYPE=slack (slack, discord, email, webhook)
+ * NOTIFY_{NAME}_EVENTS=fill,error,daily_report,risk_halt,alert (未設定なら全て)
+ * NOTIFY_{NAME}_URL= (slackのincoming webhook、discordのwebhook、webhookの送り先)
+ * NOTIFY_{NAME}_BOT_TOKEN= / NOTIFY_{NAME}_CHANNEL= (slackでチャートも送る場合。SLACK_API_URLも使う)
+ * NOTIFY_{NAME}_SMTP_HOST= / NOTIFY_{NAME}_SMTP_PORT=587 / NOTIFY_{NAME}_SMTP_USER= / NOTIFY_{NAME}_SMTP_PASSWORD=
+ * NOTIFY_{NAME}_FROM= / NOTIFY_{NAME}_TO= (emailの差出人と宛先。宛先はカンマ区切り)
+ * ({NAME}は大文字。NOTIFY_CHANNELS=mainなら、NOTIFY_MAIN_TYPE)
+ */
+pub struct Route {
+    pub name: String,
+    pub kinds: Vec<EventKind>,
+    pub notifier: Box<dyn Notifier + Send + Sync>,
+}
+
+pub struct NotifierRouter {
+    pub routes: Vec<Route>,
+}
+
+impl NotifierRouter {
+    pub fn new(routes: Vec<Route>) -> Self {
+        Self { routes }
+    }
+
+    pub fn from_env() -> Result<Self, AppError> {
+        dotenv().ok();
+
+        let Ok(channels) = env::var("NOTIFY_CHANNELS") else {
+            return Self::default_slack();
+        };
+
+        let mut routes = Vec::new();
+        for name in channels.split(',').map(|n| n.trim()).filter(|n| !n.is_empty()) {
+            routes.push(route_from_env(name)?);
+        }
+
+        Ok(Self::new(routes))
+    }
+
+    // NOTIFY_CHANNELSを使う前からの設定(SLACK_INCOMMING_WEBHOOK_URL / SLACK_BOT_TOKEN)
+    fn default_slack() -> Result<Self, AppError> {
+        let webhook_url = env::var("SLACK_INCOMMING_WEBHOOK_URL").ok();
+        let bot = SlackBot::from_env()?;
+        if webhook_url.is_none() && bot.is_none() {
+            info!("通知の送り先が未設定のため、通知しません");
+            return Ok(Self::new(Vec::new()));
+        }
+
+        Ok(Self::new(vec![Route {
+            name: "slack".to_string(),
+            kinds: EventKind::ALL.to_vec(),
+            notifier: Box::new(SlackNotifier { webhook_url, bot }),
+        }]))
+    }
+
+    /*
+     * notification.kindを受け取る全ての送り先に送る。
+     * 通知の失敗で本来の処理を止めないように、失敗はログに残すだけにする。
+     * どれか一つの送り先にでも届いたらtrue。
+     */
+    pub async fn notify(&self, notification: &Notification) -> bool {
+        let mut delivered = false;
+        for route in self.routes.iter().filter(|route| route.kinds.contains(&notification.kind)) {
+            match route.notifier.send(notification).await {
+                Ok(()) => delivered = true,
+                Err(e) => error!("Notification to {} failed ({}): {}", route.name, notification.kind, e),
+            }
+        }
+        delivered
+    }
+}
+
+fn route_from_env(name: &str) -> Result<Route, AppError> {
+    let prefix = format!("NOTIFY_{}", name.to_uppercase());
+    let var = |key: &str| env::var(format!("{}_{}", prefix, key));
+    let optional = |key: &str| var(key).ok().filter(|v| !v.is_empty());
+
+    let kinds = match optional("EVENTS") {
+        Some(events) => events.split(',').map(EventKind::parse).collect::<Result<Vec<_>, _>>()?,
+        None => EventKind::ALL.to_vec(),
+    };
+
+    let notifier: Box<dyn Notifier + Send + Sync> = match var("TYPE")?.as_str() {
+        "slack" => {
+            let bot = match optional("BOT_TOKEN") {
+                Some(token) => Some(SlackBot::new(
+                    token,
+                    var("CHANNEL")?,
+                    env::var("SLACK_API_URL").unwrap_or(slack::DEFAULT_API_URL.to_string()),
+                )),
+                None => None,
+            };
+            Box::new(SlackNotifier { webhook_url: optional("URL"), bot })
+        },
+        "discord" => Box::new(DiscordNotifier { webhook_url: var("URL")? }),
+        "webhook" => Box::new(WebhookNotifier { url: var("URL")? }),
+        "email" => Box::new(EmailNotifier {
+            host: var("SMTP_HOST")?,
+            port: config::parse_var(&format!("{}_SMTP_PORT", prefix), "587")?,
+            user: optional("SMTP_USER"),
+            password: optional("SMTP_PASSWORD"),
+            from: var("FROM")?,
+            to: var("TO")?.split(',').map(|to| to.trim().to_string()).collect(),
+        }),
+        other => return Err(AppError::InvalidData(format!("Invalid {}_TYPE: {}", prefix, other))),
+    };
+
+    Ok(Route { name: name.to_string(), kinds, notifier })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    // 受け取った通知の種類を記録する。failならエラーを返す
+    struct RecordingNotifier {
+        sent: Arc<Mutex<Vec<EventKind>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        async fn send(&self, notification: &Notification) -> Result<(), AppError> {
+            if self.fail {
+                return Err(AppError::InvalidData("send failed".to_string()));
+            }
+            self.sent.lock().unwrap().push(notification.kind);
+            Ok(())
+        }
+    }
+
+    fn route(name: &str, kinds: &[EventKind], fail: bool) -> (Route, Arc<Mutex<Vec<EventKind>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let route = Route {
+            name: name.to_string(),
+            kinds: kinds.to_vec(),
+            notifier: Box::new(RecordingNotifier { sent: sent.clone(), fail }),
+        };
+        (route, sent)
+    }
+
+    #[tokio::test]
+    async fn notify_sends_only_to_routes_that_accept_the_kind() {
+        let (fills, fills_sent) = route("fills", &[EventKind::Fill], false);
+        let (ops, ops_sent) = route("ops", &[EventKind::Error, EventKind::Alert], false);
+        let router = NotifierRouter::new(vec![fills, ops]);
+
+        assert!(router.notify(&Notification::alert("alert", "text")).await);
+        assert!(!router.notify(&Notification::new(EventKind::DailyReport, "report", "text")).await);
+
+        assert!(fills_sent.lock().unwrap().is_empty());
+        assert_eq!(*ops_sent.lock().unwrap(), vec![EventKind::Alert]);
+    }
+
+    #[tokio::test]
+    async fn notify_is_delivered_when_any_route_succeeds() {
+        let (broken, _) = route("broken", &[EventKind::Error], true);
+        let (ops, ops_sent) = route("ops", &[EventKind::Error], false);
+
+        let router = NotifierRouter::new(vec![broken, ops]);
+        assert!(router.notify(&Notification::new(EventKind::Error, "error", "text")).await);
+        assert_eq!(*ops_sent.lock().unwrap(), vec![EventKind::Error]);
+
+        let (broken, _) = route("broken", &[EventKind::Error], true);
+        let router = NotifierRouter::new(vec![broken]);
+        assert!(!router.notify(&Notification::new(EventKind::Error, "error", "text")).await);
+    }
+
+    // テスト毎に{NAME}を分けて、並列実行でenvが干渉しないようにする
+    #[test]
+    fn route_from_env_filters_events() {
+        env::set_var("NOTIFY_ROUTERTESTA_TYPE", "webhook");
+        env::set_var("NOTIFY_ROUTERTESTA_URL", "http://localhost/hook");
+        env::set_var("NOTIFY_ROUTERTESTA_EVENTS", "fill, alert");
+
+        let route = route_from_env("routertesta").unwrap();
+        assert_eq!(route.name, "routertesta");
+        assert_eq!(route.kinds, vec![EventKind::Fill, EventKind::Alert]);
+    }
+
+    #[test]
+    fn route_from_env_defaults_to_all_events() {
+        env::set_var("NOTIFY_ROUTERTESTB_TYPE", "discord");
+        env::set_var("NOTIFY_ROUTERTESTB_URL", "http://localhost/hook");
+
+        assert_eq!(route_from_env("routertestb").unwrap().kinds, EventKind::ALL.to_vec());
+    }
+
+    #[test]
+    fn route_from_env_rejects_unknown_events() {
+        env::set_var("NOTIFY_ROUTERTESTC_TYPE", "webhook");
+        env::set_var("NOTIFY_ROUTERTESTC_URL", "http://localhost/hook");
+        env::set_var("NOTIFY_ROUTERTESTC_EVENTS", "fill,fills");
+
+        assert!(route_from_env("routertestc").is_err());
+    }
+
+    #[test]
+    fn route_from_env_rejects_unknown_types() {
+        env::set_var("NOTIFY_ROUTERTESTD_TYPE", "pager");
+
+        let Err(e) = route_from_env("routertestd") else {
+            panic!("unknown type was accepted");
+        };
+        assert!(e.to_string().contains("NOTIFY_ROUTERTESTD_TYPE"));
+    }
+
+    #[test]
+    fn route_from_env_rejects_an_invalid_smtp_port() {
+        env::set_var("NOTIFY_ROUTERTESTE_TYPE", "email");
+        env::set_var("NOTIFY_ROUTERTESTE_SMTP_HOST", "localhost");
+        env::set_var("NOTIFY_ROUTERTESTE_SMTP_PORT", "smtp");
+        env::set_var("NOTIFY_ROUTERTESTE_FROM", "bot@example.com");
+        env::set_var("NOTIFY_ROUTERTESTE_TO", "me@example.com");
+
+        assert!(route_from_env("routerteste").is_err());
+    }
+}
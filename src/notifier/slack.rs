@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use log::error;
+use serde_json::{json, Value};
+
+use crate::api::slack::{self, SlackBot};
+use crate::error::AppError;
+use crate::notifier::notification::{EventKind, Notification};
+use crate::notifier::notifier_trait::Notifier;
+
+/*
+ * Slackに送る。botがあればchat.postMessageで送ってPNGの添付もアップロードし、
+ * なければincoming webhookでテキストだけ送る。
+ */
+pub struct SlackNotifier {
+    pub webhook_url: Option<String>,
+    pub bot: Option<SlackBot>,
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), AppError> {
+        let payload = payload(notification);
+
+        let Some(bot) = self.bot.as_ref() else {
+            let url = self.webhook_url.as_ref()
+                .ok_or_else(|| AppError::InvalidData("Slack webhook url and bot token are missing".to_string()))?;
+            return slack::post_webhook(url, &payload).await;
+        };
+
+        bot.post_message(payload).await?;
+
+        let pngs: Vec<&Path> = notification.attachments.iter()
+            .map(|path| path.as_path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+            .collect();
+        // 本文は届いているので、チャートのアップロード失敗は送信失敗にしない
+        if !pngs.is_empty() {
+            if let Err(e) = bot.upload_files(&pngs, &format!("{} チャート", notification.title)).await {
+                error!("Slack chart upload failed ({}): {}", notification.title, e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/*
+ * Block Kitのpayload。fieldsは1つのsectionに10個までなので分けて入れる。
+ */
+pub fn payload(notification: &Notification) -> Value {
+    let emoji = match notification.kind {
+        EventKind::Fill => ":coin:",
+        EventKind::Error => ":rotating_light:",
+        EventKind::DailyReport => ":moneybag:",
+        EventKind::RiskHalt => ":octagonal_sign:",
+        EventKind::Alert => ":warning:",
+    };
+
+    let mut blocks = vec![json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": format!("{} *{}*\n{}", emoji, notification.title, notification.text),
+        }
+    })];
+
+    for chunk in notification.fields.chunks(10) {
+        let fields: Vec<Value> = chunk.iter()
+            .map(|field| json!({"type": "mrkdwn", "text": format!("*{}*\n{}", field.name, field.value)}))
+            .collect();
+        blocks.push(json!({"type": "section", "fields": fields}));
+    }
+
+    json!({
+        "text": notification.title,
+        "blocks": blocks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::notification::Field;
+
+    #[test]
+    fn payload_splits_fields_into_sections_of_ten() {
+        let mut notification = Notification::new(EventKind::DailyReport, "日次レポート", "text");
+        notification.fields = (0..23)
+            .map(|i| Field { name: format!("C{}", i), value: i.to_string() })
+            .collect();
+
+        let payload = payload(&notification);
+        let blocks = payload["blocks"].as_array().unwrap();
+
+        assert_eq!(blocks.len(), 4);
+        let sizes: Vec<usize> = blocks[1..].iter().map(|b| b["fields"].as_array().unwrap().len()).collect();
+        assert_eq!(sizes, vec![10, 10, 3]);
+        assert_eq!(blocks[3]["fields"][2]["text"], "*C22*\n22");
+    }
+
+    #[test]
+    fn payload_without_fields_has_only_the_text_section() {
+        let payload = payload(&Notification::alert("alert", "text"));
+
+        assert_eq!(payload["blocks"].as_array().unwrap().len(), 1);
+        assert_eq!(payload["text"], "alert");
+    }
+}
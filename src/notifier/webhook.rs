@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use crate::error::AppError;
+use crate::notifier::notification::Notification;
+use crate::notifier::notifier_trait::Notifier;
+
+/*
+ * 任意のURLに、Notificationをそのまま(kind, title, text, fields, attachments)JSONでPOSTする。
+ * 添付はファイルのパスだけを送る。
+ */
+pub struct WebhookNotifier {
+    pub url: String,
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), AppError> {
+        let client = Client::new();
+        client.post(&self.url).json(notification).send().await?.error_for_status()?;
+
+        Ok(())
+    }
+}
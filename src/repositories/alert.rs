@@ -0,0 +1,347 @@
+use std::future::Future;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use log::{info, error};
+
+use crate::{
+    db::establish_connection,
+    error::{AppError, ExchangeErrorKind},
+    models::{alert::{Alert, NewAlert}, job_run::{JobRun, NewJobRun}, ticker::Ticker},
+    notifier::{notification::Notification, router::NotifierRouter},
+    config,
+};
+
+/*
+ * ジョブの連続失敗、tickerの停止、注文の拒否、JPYの枯渇、異常なスプレッドを検知して、
+ * notifierのalertで知らせる。同じルールの同じ対象(通貨やジョブ)は、クールダウン中は送らない。
+ *
+ * [cron]
+ * 10分毎に、cargo run --bin alert_checkを実行して、tickerの停止とスプレッドを確認
+ * (orderの実行時にも同じ確認をする)
+ *
+ * [envの設定]
+ * ALERT_COOLDOWN_MINUTES=60
+ * ALERT_JOB_FAILURES=3 (同じジョブがこの回数続けて失敗したら知らせる)
+ * ALERT_TICKER_STALE_MINUTES=30 (最新のtickerがこれより古ければ知らせる)
+ * ALERT_SPREAD_MULTIPLIER=3 (スプレッドが過去の平均のこの倍を超えたら知らせる)
+ * ALERT_MIN_JPY=1000 (買いと判断した通貨があるのに、JPYがこれ未満なら知らせる)
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertRule {
+    JobFailure,
+    StaleTicker,
+    OrderRejected,
+    JpyExhausted,
+    UnusualSpread,
+}
+
+impl AlertRule {
+    // alerts.ruleに保存する名前
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertRule::JobFailure => "job_failure",
+            AlertRule::StaleTicker => "stale_ticker",
+            AlertRule::OrderRejected => "order_rejected",
+            AlertRule::JpyExhausted => "jpy_exhausted",
+            AlertRule::UnusualSpread => "unusual_spread",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            AlertRule::JobFailure => "ジョブの連続失敗",
+            AlertRule::StaleTicker => "tickerの更新停止",
+            AlertRule::OrderRejected => "注文の拒否",
+            AlertRule::JpyExhausted => "JPY残高の不足",
+            AlertRule::UnusualSpread => "異常なスプレッド",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    pub cooldown_minutes: i64,
+    pub job_failures: usize,
+    pub ticker_stale_minutes: i64,
+    pub spread_multiplier: f64,
+    pub min_jpy: f64,
+}
+
+impl AlertConfig {
+    pub fn from_env() -> Result<Self, AppError> {
+        Ok(Self {
+            cooldown_minutes: config::parse_var("ALERT_COOLDOWN_MINUTES", "60")?,
+            job_failures: config::parse_var("ALERT_JOB_FAILURES", "3")?,
+            ticker_stale_minutes: config::parse_var("ALERT_TICKER_STALE_MINUTES", "30")?,
+            spread_multiplier: config::parse_var("ALERT_SPREAD_MULTIPLIER", "3")?,
+            min_jpy: config::parse_var("ALERT_MIN_JPY", "1000")?,
+        })
+    }
+}
+
+/*
+ * 同じ(rule, key)を最後に送ってからクールダウンが過ぎていれば、送ってalertsに記録する。送ったらtrue。
+ * どの送り先にも届かなければ記録しないので、次の確認でもう一度送る。
+ */
+pub async fn raise(
+    conn: &mut PgConnection,
+    notifier: &NotifierRouter,
+    config: &AlertConfig,
+    rule: AlertRule,
+    key: &str,
+    message: &str,
+) -> Result<bool, AppError> {
+    let last_sent_at = Alert::last_sent_at(conn, rule.name(), key)?;
+    if !should_send(last_sent_at, Utc::now().naive_utc(), Duration::minutes(config.cooldown_minutes)) {
+        info!("alert [{}] {} はクールダウン中のため送らない: {}", rule.name(), key, message);
+        return Ok(false);
+    }
+
+    if !notifier.notify(&Notification::alert(rule.title(), message)).await {
+        error!("alert [{}] {} を送れなかったため記録しない: {}", rule.name(), key, message);
+        return Ok(false);
+    }
+
+    Alert::create(conn, &NewAlert {
+        rule: rule.name().to_string(),
+        key: key.to_string(),
+        message: message.to_string(),
+    })?;
+
+    Ok(true)
+}
+
+// 一度も送っていないか、最後に送ってからcooldownが過ぎていればtrue
+fn should_send(last_sent_at: Option<NaiveDateTime>, now: NaiveDateTime, cooldown: Duration) -> bool {
+    last_sent_at.is_none_or(|last_sent_at| now - last_sent_at >= cooldown)
+}
+
+/*
+ * 注文などの本来の処理を止めないように、アラートの設定の読み込みや確認の失敗はログに残すだけにする。
+ */
+pub fn config_or_log() -> Option<AlertConfig> {
+    AlertConfig::from_env()
+        .map_err(|e| error!("アラートの設定を読み込めないため、アラートなしで続ける: {}", e))
+        .ok()
+}
+
+pub fn log_failure(result: Result<(), AppError>) {
+    if let Err(e) = result {
+        error!("アラートの確認に失敗: {}", e);
+    }
+}
+
+/*
+ * alert_configが読み込めていればcheckを実行し、失敗はlog_failureでログに残す。
+ */
+pub async fn check_or_log<'a, F, Fut>(alert_config: Option<&'a AlertConfig>, check: F)
+where
+    F: FnOnce(&'a AlertConfig) -> Fut,
+    Fut: Future<Output = Result<(), AppError>>,
+{
+    if let Some(alert_config) = alert_config {
+        log_failure(check(alert_config).await);
+    }
+}
+
+/*
+ * binの実行結果をjob_runsに記録し、ALERT_JOB_FAILURES回続けて失敗していれば知らせる。
+ * 失敗した時は、DBに繋がらなくても届くように、記録の前にerrorとしても通知する。
+ * run()で作ったコネクションは使えないので、ここで作り直す。記録の失敗はログだけ。
+ */
+pub async fn record_job(job: &str, result: &Result<(), AppError>) {
+    if let Err(e) = result {
+        match NotifierRouter::from_env() {
+            Ok(notifier) => {
+                notifier.notify(&Notification::error(&format!("{}の実行失敗", job), e)).await;
+            },
+            Err(notify_error) => error!("Failed to build notifier for {}: {}", job, notify_error),
+        }
+    }
+
+    if let Err(e) = record_job_result(job, result).await {
+        error!("Failed to record job run {}: {}", job, e);
+    }
+}
+
+async fn record_job_result(job: &str, result: &Result<(), AppError>) -> Result<(), AppError> {
+    let pool = establish_connection();
+    let mut conn = pool.get()?;
+
+    JobRun::create(&mut conn, &NewJobRun {
+        job: job.to_string(),
+        success: result.is_ok(),
+        error: result.as_ref().err().map(|e| e.to_string()),
+    })?;
+
+    let Err(e) = result else {
+        return Ok(());
+    };
+
+    let config = AlertConfig::from_env()?;
+    let failures = JobRun::consecutive_failures(&mut conn, job)?;
+    if failures >= config.job_failures {
+        let notifier = NotifierRouter::from_env()?;
+        let message = format!("{}が{}回続けて失敗しています: {}", job, failures, e);
+        raise(&mut conn, &notifier, &config, AlertRule::JobFailure, job, &message).await?;
+    }
+
+    Ok(())
+}
+
+/*
+ * currencies毎に、最新のtickerがALERT_TICKER_STALE_MINUTESより古い(またはない)なら知らせる。
+ */
+pub async fn check_stale_tickers(
+    conn: &mut PgConnection,
+    notifier: &NotifierRouter,
+    config: &AlertConfig,
+    currencies: &[String],
+) -> Result<(), AppError> {
+    let stale_before = Utc::now().naive_utc() - Duration::minutes(config.ticker_stale_minutes);
+
+    for currency in currencies.iter() {
+        let message = match Ticker::latest_quote(conn, currency)? {
+            Some((at, _, _)) if at >= stale_before => continue,
+            Some((at, _, _)) => format!("[{}] 最新のtickerが{}のままです。ticker_fetcherを確認してください", currency, at.format("%Y-%m-%d %H:%M")),
+            None => format!("[{}] tickerがありません。ticker_fetcherを確認してください", currency),
+        };
+
+        raise(conn, notifier, config, AlertRule::StaleTicker, currency, &message).await?;
+    }
+
+    Ok(())
+}
+
+/*
+ * 今のスプレッドが、tickersの平均スプレッドのALERT_SPREAD_MULTIPLIER倍を超えていれば知らせる。
+ */
+pub async fn check_spread(
+    conn: &mut PgConnection,
+    notifier: &NotifierRouter,
+    config: &AlertConfig,
+    currency: &str,
+    bid: f64,
+    ask: f64,
+) -> Result<(), AppError> {
+    let Some(average) = Ticker::average_spread_pct(conn, currency)? else {
+        return Ok(());
+    };
+
+    if spread_exceeds(bid, ask, average, config.spread_multiplier) {
+        let spread_pct = (ask - bid) / bid * 100.0;
+        let message = format!("[{}] スプレッドが{:.3}%です (平均{:.3}%)", currency, spread_pct, average);
+        raise(conn, notifier, config, AlertRule::UnusualSpread, currency, &message).await?;
+    }
+
+    Ok(())
+}
+
+// bid / askのスプレッド(%)が、平均のmultiplier倍を超えているか。bidか平均が0以下なら判定しない
+fn spread_exceeds(bid: f64, ask: f64, average_pct: f64, multiplier: f64) -> bool {
+    if bid <= 0.0 || average_pct <= 0.0 {
+        return false;
+    }
+
+    (ask - bid) / bid * 100.0 > average_pct * multiplier
+}
+
+/*
+ * 取引所に注文を拒否された時。買いの残高不足ならJPYの枯渇として知らせる。
+ */
+pub async fn order_rejected(
+    conn: &mut PgConnection,
+    notifier: &NotifierRouter,
+    config: &AlertConfig,
+    currency: &str,
+    order_type: &str,
+    kind: ExchangeErrorKind,
+    message: &str,
+) -> Result<(), AppError> {
+    let (rule, key) = rejection_rule(kind, order_type, currency);
+
+    let message = format!("[{}] 注文が拒否されました: {:?}: {}", currency, kind, message);
+    raise(conn, notifier, config, rule, key, &message).await?;
+
+    Ok(())
+}
+
+// 買いの残高不足はJPYの枯渇(通貨に依らず"jpy")、それ以外は通貨毎の注文の拒否
+fn rejection_rule<'a>(kind: ExchangeErrorKind, order_type: &str, currency: &'a str) -> (AlertRule, &'a str) {
+    if kind == ExchangeErrorKind::InsufficientFunds && order_type == "market_buy" {
+        (AlertRule::JpyExhausted, "jpy")
+    } else {
+        (AlertRule::OrderRejected, currency)
+    }
+}
+
+/*
+ * 買いと判断した通貨があるのに、JPYがALERT_MIN_JPY未満なら知らせる。
+ */
+pub async fn check_jpy(
+    conn: &mut PgConnection,
+    notifier: &NotifierRouter,
+    config: &AlertConfig,
+    jpy_balance: f64,
+    buy_currencies: &[String],
+) -> Result<(), AppError> {
+    if buy_currencies.is_empty() || jpy_balance >= config.min_jpy {
+        return Ok(());
+    }
+
+    let message = format!("JPY残高が{:.0}円のため、{}を購入できません", jpy_balance, buy_currencies.join(", "));
+    raise(conn, notifier, config, AlertRule::JpyExhausted, "jpy", &message).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn should_send_when_never_sent() {
+        assert!(should_send(None, at(12, 0), Duration::minutes(60)));
+    }
+
+    #[test]
+    fn should_send_waits_for_the_cooldown() {
+        let cooldown = Duration::minutes(60);
+
+        assert!(!should_send(Some(at(11, 30)), at(12, 0), cooldown));
+        assert!(should_send(Some(at(11, 0)), at(12, 0), cooldown));
+        assert!(should_send(Some(at(9, 0)), at(12, 0), cooldown));
+    }
+
+    #[test]
+    fn spread_exceeds_compares_against_the_average() {
+        // スプレッド1%、平均0.2%の3倍は0.6%
+        assert!(spread_exceeds(100.0, 101.0, 0.2, 3.0));
+        // 平均0.5%の3倍は1.5%
+        assert!(!spread_exceeds(100.0, 101.0, 0.5, 3.0));
+    }
+
+    #[test]
+    fn spread_exceeds_ignores_missing_prices_and_averages() {
+        assert!(!spread_exceeds(0.0, 101.0, 0.2, 3.0));
+        assert!(!spread_exceeds(100.0, 101.0, 0.0, 3.0));
+    }
+
+    #[test]
+    fn insufficient_funds_on_a_buy_is_jpy_exhausted() {
+        assert_eq!(rejection_rule(ExchangeErrorKind::InsufficientFunds, "market_buy", "btc"), (AlertRule::JpyExhausted, "jpy"));
+    }
+
+    #[test]
+    fn other_rejections_are_per_currency() {
+        assert_eq!(rejection_rule(ExchangeErrorKind::InsufficientFunds, "market_sell", "btc"), (AlertRule::OrderRejected, "btc"));
+        assert_eq!(rejection_rule(ExchangeErrorKind::InvalidAmount, "market_buy", "eth"), (AlertRule::OrderRejected, "eth"));
+    }
+}
@@ -8,7 +8,7 @@ use crate::error::AppError;
 
 pub async fn my_currencies(client: &CoincheckClient) -> Result<Vec<String>, AppError> {
 
-    let balancies = coincheck::balance::find(&client).await?;
+    let balancies = coincheck::balance::find(client).await?;
     let currencies = balancies
         .as_object()
         .unwrap()
@@ -22,7 +22,7 @@ pub async fn my_currencies(client: &CoincheckClient) -> Result<Vec<String>, AppE
 
 #[allow(dead_code)]
 pub async fn my_managed_currencies(client: &CoincheckClient) -> Result<Vec<String>, AppError> {
-    let balancies = coincheck::balance::find(&client).await?;
+    let balancies = coincheck::balance::find(client).await?;
     let currencies = balancies
         .as_object()
         .unwrap()
@@ -36,7 +36,7 @@ pub async fn my_managed_currencies(client: &CoincheckClient) -> Result<Vec<Strin
 }
 
 pub async fn my_trading_currencies(client: &CoincheckClient) -> Result<Vec<String>, AppError> {
-    let balancies = coincheck::balance::find(&client).await?;
+    let balancies = coincheck::balance::find(client).await?;
     let currencies = balancies
         .as_object()
         .unwrap()
@@ -50,7 +50,7 @@ pub async fn my_trading_currencies(client: &CoincheckClient) -> Result<Vec<Strin
 }
 
 pub async fn my_balancies(client: &CoincheckClient) -> Result<Value, AppError> {
-    let balances = coincheck::balance::find(&client).await?;
+    let balances = coincheck::balance::find(client).await?;
     let my_balancies: serde_json::Map<String, Value> = balances
         .as_object()
         .unwrap()
@@ -0,0 +1,231 @@
+use std::env;
+use std::path::PathBuf;
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use log::{info, error};
+
+use crate::{
+    chart::{self, Marker, PriceChart},
+    error::AppError,
+    models::{self, order::Order, summary::Summary, summary_record::SummaryRecord, ticker::Ticker},
+    repositories,
+    config,
+    strategies::ma_optimizer::MaSelection,
+};
+
+/*
+ * tickers / orders / summariesからチャートを描いて、CHART_DIRに書き出す。
+ * 価格チャートのSMAは、CHART_MA_SHORT / CHART_MA_LONGがなければ最新の最適化結果、それもなければ5と20。
+ *
+ * [envの設定]
+ * CHART_DIR=chart_images
+ * CHART_FORMAT=png (png, svg)
+ * CHART_PRICE_HOURS=24 (価格チャートの期間)
+ * CHART_EQUITY_DAYS=30 (資産推移の期間)
+ * CHART_MA_SHORT=
+ * CHART_MA_LONG=
+ */
+pub struct ChartConfig {
+    pub dir: PathBuf,
+    pub format: String,
+    pub price_hours: i64,
+    pub equity_days: i64,
+}
+
+impl ChartConfig {
+    pub fn from_env() -> Result<Self, AppError> {
+        let format = env::var("CHART_FORMAT").unwrap_or("png".to_string());
+        if format != "png" && format != "svg" {
+            return Err(AppError::InvalidData(format!("Invalid CHART_FORMAT: {}", format)));
+        }
+
+        Ok(Self {
+            dir: PathBuf::from(env::var("CHART_DIR").unwrap_or("chart_images".to_string())),
+            format,
+            price_hours: config::parse_var("CHART_PRICE_HOURS", "24")?,
+            equity_days: config::parse_var("CHART_EQUITY_DAYS", "30")?,
+        })
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, self.format))
+    }
+}
+
+/*
+ * 全てのチャートを書き出して、書き出したパスを返す。データが足りないチャートは飛ばす。
+ * 描けなかった通貨はログに残して、他の通貨とチャートは書き出す。
+ */
+pub fn render_all(conn: &mut PgConnection) -> Result<Vec<PathBuf>, AppError> {
+    let config = ChartConfig::from_env()?;
+    std::fs::create_dir_all(&config.dir)?;
+
+    let since = Utc::now().naive_utc() - Duration::hours(config.price_hours);
+    let orders = Order::filled_market_since(conn, since)?;
+
+    let mut paths = Vec::new();
+    for pair_str in Ticker::pairs(conn)?.iter() {
+        match render_price(conn, &config, pair_str, &orders) {
+            Ok(path) => paths.extend(path),
+            Err(e) => {
+                error!("[{}] 価格チャートの書き出し失敗: {}", pair_str, e);
+                continue;
+            }
+        }
+    }
+    paths.extend(render_equity(conn, &config)?);
+    paths.extend(render_allocation(conn, &config)?);
+
+    Ok(paths)
+}
+
+/*
+ * ordersは約定した成行注文で、pair_strの分だけ買い・売りの位置として重ねる。
+ */
+pub fn render_price(
+    conn: &mut PgConnection,
+    config: &ChartConfig,
+    pair_str: &str,
+    orders: &[Order],
+) -> Result<Option<PathBuf>, AppError> {
+    let since = Utc::now().naive_utc() - Duration::hours(config.price_hours);
+    let series = Ticker::price_series_since(conn, pair_str, since)?;
+    if series.len() < 2 {
+        info!("[{}] 価格が2件未満のため、価格チャートなし", pair_str);
+        return Ok(None);
+    }
+
+    let (short, long) = ma_periods(conn, pair_str)?;
+    let markers = markers(orders, pair_str);
+
+    let path = config.path(&format!("{}_price", pair_str));
+    chart::price(&path, &PriceChart { pair: pair_str, series: &series, short, long, markers: &markers })?;
+    Ok(Some(path))
+}
+
+pub fn render_equity(
+    conn: &mut PgConnection,
+    config: &ChartConfig,
+) -> Result<Option<PathBuf>, AppError> {
+    let since = Utc::now().naive_utc() - Duration::days(config.equity_days);
+    let points = repositories::metrics::equity_curve(conn, since)?;
+    if points.len() < 2 {
+        info!("summariesが2件未満のため、資産推移チャートなし");
+        return Ok(None);
+    }
+
+    let path = config.path("equity");
+    chart::equity(&path, &points)?;
+    Ok(Some(path))
+}
+
+/*
+ * 最新のsummariesの、通貨毎の割合。
+ */
+pub fn render_allocation(
+    conn: &mut PgConnection,
+    config: &ChartConfig,
+) -> Result<Option<PathBuf>, AppError> {
+    let Some(summary) = Summary::latest(conn)? else {
+        info!("summariesがないため、配分チャートなし");
+        return Ok(None);
+    };
+    if summary.total_jpy_value <= 0.0 {
+        return Ok(None);
+    }
+
+    let allocations: Vec<(String, f64)> = SummaryRecord::for_summaries(conn, &[summary.id])?
+        .into_iter()
+        .map(|record| (record.currency, record.jpy_value / summary.total_jpy_value * 100.0))
+        .collect();
+
+    let path = config.path("allocation");
+    chart::allocation(&path, &allocations)?;
+    Ok(Some(path))
+}
+
+/*
+ * 買いはbuy_rate、売りはsell_rateの位置。レートが記録されていない注文は出さない。
+ */
+fn markers(orders: &[Order], pair_str: &str) -> Vec<Marker> {
+    orders.iter()
+        .filter(|order| order.pair == pair_str)
+        .filter_map(|order| {
+            let buy = order.order_type == "market_buy";
+            let price = if buy { order.buy_rate } else { order.sell_rate }?;
+            Some(Marker { at: order.created_at, price, buy })
+        })
+        .collect()
+}
+
+fn ma_periods(conn: &mut PgConnection, pair_str: &str) -> Result<(usize, usize), AppError> {
+    let short = config::optional_var::<usize>("CHART_MA_SHORT")?;
+    let long = config::optional_var::<usize>("CHART_MA_LONG")?;
+    if let (Some(short), Some(long)) = (short, long) {
+        if short == 0 || short >= long {
+            return Err(AppError::InvalidData(format!("CHART_MA_SHORT must be >= 1 and < CHART_MA_LONG: {} / {}", short, long)));
+        }
+        return Ok((short, long));
+    }
+
+    let selection = MaSelection::from_env(pair_str)?;
+    let best = models::optimized_ma::OptimizedMa::find_best_for_ma(conn, pair_str, &selection)?;
+    Ok(best.map(|b| (b.short_ma as usize, b.long_ma as usize)).unwrap_or((5, 20)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn order(pair: &str, order_type: &str, buy_rate: Option<f64>, sell_rate: Option<f64>) -> Order {
+        Order {
+            id: 1,
+            rate: 0.0,
+            crypto_amount: 0.0,
+            order_type: order_type.to_string(),
+            pair: pair.to_string(),
+            created_at: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+            buy_rate,
+            sell_rate,
+            spread_ratio: None,
+            jpy_amount: None,
+            comment: None,
+            spread_threshold: None,
+            api_call_success_at: None,
+            ma_short: None,
+            ma_long: None,
+            ma_win_rate: None,
+            contribution_jpy: None,
+            strategy_name: None,
+            strategy_version: None,
+            confidence: None,
+            position_fraction: None,
+            signal_metadata: None,
+            optimization_run_id: None,
+        }
+    }
+
+    #[test]
+    fn markers_use_the_buy_or_sell_rate_of_the_pair() {
+        let orders = [
+            order("btc", "market_buy", Some(100.0), Some(99.0)),
+            order("btc", "market_sell", Some(101.0), Some(100.5)),
+            order("eth", "market_buy", Some(5.0), Some(4.0)),
+        ];
+
+        let markers: Vec<(f64, bool)> = markers(&orders, "btc").iter().map(|m| (m.price, m.buy)).collect();
+        assert_eq!(markers, vec![(100.0, true), (100.5, false)]);
+    }
+
+    #[test]
+    fn markers_skip_orders_without_a_rate() {
+        let orders = [
+            order("btc", "market_buy", None, Some(99.0)),
+            order("btc", "market_sell", Some(101.0), None),
+        ];
+
+        assert!(markers(&orders, "btc").is_empty());
+    }
+}
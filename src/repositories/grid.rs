@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use log::{info, error};
+
+use crate::{
+    api::coincheck,
+    error::AppError,
+    models::{self, grid::{Grid, GridLevel, NewGrid}},
+    repositories,
+    strategies::grid::{self, GridConfig, GridStrategy},
+};
+
+/*
+ * GRID_CURRENCIESの通貨毎に、グリッドの約定を反映して指値を出し直す。
+ */
+pub async fn run(
+    conn: &mut PgConnection,
+    client: &coincheck::client::CoincheckClient,
+) -> Result<(), AppError> {
+    let currencies = GridConfig::currencies();
+    if currencies.is_empty() {
+        info!("GRID_CURRENCIESが未設定のため、グリッドなし");
+        return Ok(());
+    }
+
+    let open_orders = coincheck::order::find_open_orders(client).await?;
+    let balances = repositories::balance::my_balancies(client).await?;
+
+    for currency in currencies.iter() {
+        // 1通貨の設定ミスで、他の通貨のグリッドまで止めない
+        let config = match GridConfig::from_env(currency) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("#- [{}] グリッド設定の読み込み失敗: {}", currency, e);
+                continue;
+            }
+        };
+        let ticker = coincheck::ticker::find(client, currency).await?;
+        let crypto_available = repositories::balance::get_crypto_balance(&balances, currency)?;
+
+        let grid = find_or_create_grid(conn, client, &config, ticker.last, &open_orders).await?;
+        reconcile(conn, client, &config, &grid, &open_orders, crypto_available).await?;
+    }
+
+    Ok(())
+}
+
+/*
+ * 有効なグリッドを返す。設定が変わっていたら古いグリッドの指値を取り消して作り直す。
+ */
+async fn find_or_create_grid(
+    conn: &mut PgConnection,
+    client: &coincheck::client::CoincheckClient,
+    config: &GridConfig,
+    current_price: f64,
+    open_orders: &[coincheck::order::OpenOrder],
+) -> Result<Grid, AppError> {
+    if let Some(grid) = Grid::find_active(conn, &config.currency)? {
+        if config.matches(&grid) {
+            return Ok(grid);
+        }
+
+        info!("#- [{}] グリッド設定変更のため作り直し: grid_id={}", config.currency, grid.id);
+        let open_ids: HashSet<i64> = open_orders.iter().map(|o| o.id).collect();
+        for level in GridLevel::for_grid(conn, grid.id)?.iter() {
+            if let Some(order_id) = level.exchange_order_id.filter(|id| open_ids.contains(id)) {
+                coincheck::order::cancel_order(client, order_id).await?;
+            }
+        }
+        Grid::deactivate(conn, grid.id)?;
+    }
+
+    let new_grid = NewGrid {
+        pair: config.currency.clone(),
+        lower_price: config.lower,
+        upper_price: config.upper,
+        levels: config.levels,
+        order_amount: config.order_amount,
+    };
+    let new_levels = GridStrategy::initial_levels(config, current_price);
+
+    let grid = Grid::create(conn, &new_grid, new_levels)?;
+    info!("#- [{}] グリッド作成: grid_id={}", config.currency, grid.id);
+
+    Ok(grid)
+}
+
+/*
+ * 取引所の未約定注文と突き合わせる。
+ * armedなのに未約定一覧にない注文は、注文の状態を確認して、
+ * 約定していればordersに記録して反対側を出し直す。
+ * 取り消し・失効していれば、約定した分だけ記録して同じレベルに出し直す。
+ * 売りの指値は、crypto_available(指値で拘束されていない残高)で足りる分だけ出す。
+ */
+async fn reconcile(
+    conn: &mut PgConnection,
+    client: &coincheck::client::CoincheckClient,
+    config: &GridConfig,
+    grid: &Grid,
+    open_orders: &[coincheck::order::OpenOrder],
+    crypto_available: f64,
+) -> Result<(), AppError> {
+    let open_ids: HashSet<i64> = open_orders.iter().map(|o| o.id).collect();
+
+    let levels = GridLevel::for_grid(conn, grid.id)?;
+    for (i, level) in levels.iter().enumerate() {
+        let Some(order_id) = level.exchange_order_id else { continue; };
+        if level.state != "armed" || open_ids.contains(&order_id) {
+            continue;
+        }
+
+        let detail = match coincheck::order::find_order(client, order_id).await {
+            Ok(detail) => detail,
+            Err(AppError::Exchange { kind, message, status }) if !kind.is_fatal() => {
+                // armedのまま次回に再確認
+                error!("#- [{}] 注文状態の取得失敗 order_id={} [{}] {:?}: {}", grid.pair, order_id, status, kind, message);
+                continue;
+            },
+            Err(e) => return Err(e),
+        };
+
+        if detail.is_filled() {
+            record_fill(conn, grid, level, &detail)?;
+            GridLevel::update_state(conn, level.id, &level.side, "idle", None)?;
+
+            if let Some((target, side)) = GridStrategy::rearm_target(&levels, i) {
+                let target_level = &levels[target];
+                if target_level.state != "armed" {
+                    GridLevel::update_state(conn, target_level.id, side, "pending", None)?;
+                }
+            }
+        } else if detail.is_closed() {
+            info!("#- [{}] グリッドの注文が{}: order_id={}、出し直し", grid.pair, detail.status, order_id);
+            if detail.executed_amount() > 0.0 {
+                record_fill(conn, grid, level, &detail)?;
+            }
+            GridLevel::update_state(conn, level.id, &level.side, "pending", None)?;
+        } else {
+            info!("#- [{}] 未約定一覧にないが{}: order_id={}、次回に再確認", grid.pair, detail.status, order_id);
+        }
+    }
+
+    let levels = GridLevel::for_grid(conn, grid.id)?;
+    let placeable = grid::placeable_levels(&levels, grid.order_amount, crypto_available);
+    let pending = levels.iter().filter(|l| l.state == "pending").count();
+    if placeable.len() < pending {
+        // pendingのまま、残高が戻った時に出す
+        info!("#- [{}] 残高不足のため売りの指値{}件を見送り: 残高={}", grid.pair, pending - placeable.len(), crypto_available);
+    }
+
+    for level in placeable {
+        let order = coincheck::order::LimitOrderRequest {
+            pair: format!("{}_jpy", grid.pair),
+            order_type: level.side.clone(),
+            // 丸める前に作ったグリッドのレベルも、呼値に合わせて出す
+            rate: grid::round_to_tick(level.price, config.tick),
+            amount: grid.order_amount,
+        };
+
+        match coincheck::order::post_limit_order(client, &order).await {
+            Ok(order_id) => {
+                GridLevel::update_state(conn, level.id, &level.side, "armed", Some(order_id))?;
+                info!("#- [{}] 指値{} {}@{}: order_id={}", grid.pair, level.side, grid.order_amount, order.rate, order_id);
+            },
+            Err(AppError::Exchange { kind, message, status }) if !kind.is_fatal() => {
+                // pendingのまま次回に再挑戦
+                error!("#- [{}] 指値失敗 [{}] {:?}: {}", grid.pair, status, kind, message);
+            },
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+fn record_fill(
+    conn: &mut PgConnection,
+    grid: &Grid,
+    level: &GridLevel,
+    detail: &coincheck::order::OrderDetail,
+) -> Result<(), AppError> {
+    let amount = detail.executed_amount();
+    let rate = detail.rate.as_deref().and_then(|r| r.parse::<f64>().ok()).unwrap_or(level.price);
+
+    let mut new_order = models::order::NewOrder::new(grid.pair.clone());
+    new_order.order_type = format!("limit_{}", level.side);
+    new_order.rate = Some(rate);
+    new_order.crypto_amount = amount;
+    new_order.jpy_amount = rate * amount;
+    new_order.ma_short = None;
+    new_order.ma_long = None;
+    new_order.ma_win_rate = None;
+    new_order.strategy_name = Some("grid".to_string());
+    new_order.comment = Some(format!(
+        "grid_id={} level={} order_id={} 約定({})",
+        grid.id,
+        level.level_index,
+        level.exchange_order_id.unwrap_or_default(),
+        detail.status,
+    ));
+    new_order.api_call_success_at = Some(Utc::now().naive_utc());
+
+    info!("#- [{}] グリッド約定: {} {}@{}", grid.pair, new_order.order_type, amount, rate);
+    models::order::Order::create(conn, &new_order)?;
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::{
+    config,
+    error::AppError,
+    models::{order::Order, summary::Summary, summary_record::SummaryRecord, ticker::Ticker},
+    metrics::{EquityPoint, Metrics, Trade},
+};
+
+/*
+ * 実運用の履歴から成績の指標を計算する。データが足りなければNone。
+ * 資産推移はsummaries、売買はordersの成行注文、バイ&ホールドはtickersの価格を使う。
+ * summariesの総資産には入金も含まれるので、入金があった期間のリターンは高めに出る。
+ *
+ * [envの設定]
+ * METRICS_DAYS=30 (何日前からの履歴で計算するか)
+ * METRICS_BENCHMARK=btc (バイ&ホールドで比べる通貨)
+ */
+pub fn calculate(conn: &mut PgConnection) -> Result<Option<Metrics>, AppError> {
+    let days: i64 = config::parse_var("METRICS_DAYS", "30")?;
+    let benchmark: String = config::parse_var("METRICS_BENCHMARK", "btc")?;
+    let since = Utc::now().naive_utc() - Duration::days(days);
+
+    let equity = equity_curve(conn, since)?;
+    let trades = trades(&Order::filled_market_since(conn, since)?);
+    let prices = Ticker::price_series(conn, &benchmark)?;
+
+    Ok(Metrics::calculate(&equity, &trades, &prices))
+}
+
+/*
+ * summaries毎の総資産と、そのうちjpy以外で持っていた分。
+ */
+pub fn equity_curve(
+    conn: &mut PgConnection,
+    since: NaiveDateTime,
+) -> Result<Vec<EquityPoint>, AppError> {
+    let summaries = Summary::since(conn, since)?;
+    let ids: Vec<i32> = summaries.iter().map(|s| s.id).collect();
+
+    let mut invested: HashMap<i32, f64> = HashMap::new();
+    for record in SummaryRecord::for_summaries(conn, &ids)?.iter().filter(|r| r.currency != "jpy") {
+        *invested.entry(record.summary_id).or_insert(0.0) += record.jpy_value;
+    }
+
+    Ok(summaries.iter()
+        .map(|s| EquityPoint {
+            at: s.created_at,
+            value: s.total_jpy_value,
+            invested: invested.get(&s.id).copied().unwrap_or(0.0),
+        })
+        .collect())
+}
+
+/*
+ * 成行注文を通貨毎に先入れ先出しで突き合わせて、売買の一覧にする。
+ * 買いは注文額(JPY) / buy_rateで数量を推定し、売りはsell_rateで約定したとみなす。
+ * 期間より前に買った分の売りや、まだ売っていない買いは含めない。
+ */
+pub fn trades(orders: &[Order]) -> Vec<Trade> {
+    let mut lots: HashMap<&str, VecDeque<(NaiveDateTime, f64, f64)>> = HashMap::new();
+    let mut trades = Vec::new();
+
+    for order in orders.iter() {
+        match order.order_type.as_str() {
+            "market_buy" => {
+                let (Some(jpy), Some(price)) = (order.jpy_amount, order.buy_rate) else {
+                    continue;
+                };
+                if price > 0.0 && jpy > 0.0 {
+                    lots.entry(&order.pair).or_default().push_back((order.created_at, price, jpy / price));
+                }
+            },
+            "market_sell" => {
+                let Some(price) = order.sell_rate else {
+                    continue;
+                };
+                let queue = lots.entry(&order.pair).or_default();
+                let mut remaining = order.crypto_amount;
+
+                while remaining > 0.0 {
+                    let Some(lot) = queue.front_mut() else {
+                        break;
+                    };
+                    let amount = remaining.min(lot.2);
+                    trades.push(Trade {
+                        pair: order.pair.clone(),
+                        entry_at: lot.0,
+                        exit_at: order.created_at,
+                        entry_price: lot.1,
+                        exit_price: price,
+                        amount,
+                    });
+
+                    lot.2 -= amount;
+                    remaining -= amount;
+                    if lot.2 <= 0.0 {
+                        queue.pop_front();
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    trades
+}
@@ -4,3 +4,10 @@ pub mod balance;
 pub mod summary;
 pub mod order;
 pub mod optimized_ma;
+pub mod order_book;
+pub mod optimized_macd;
+pub mod grid;
+pub mod rebalance;
+pub mod metrics;
+pub mod chart;
+pub mod alert;
@@ -1,18 +1,88 @@
+use chrono::{Duration, Utc};
 use diesel::prelude::*;
+use log::{info, error};
 
 use crate::{
-    models,
+    api::coincheck,
+    models::{self, optimization_run::OptimizationRun, optimized_ma::OptimizedMa},
     error::AppError,
+    repositories,
+    config,
+    strategies::ma_optimizer::{MaSearchSpace, MaSelection, MaWalkForward},
 };
 
 #[allow(dead_code)]
 pub async fn calc_crossover(
     conn: &mut PgConnection,
+    client: &coincheck::client::CoincheckClient,
 ) -> Result<(), AppError> {
 
-    let pair_str = "btc";
-    let offset = 15;
-    models::optimized_ma::OptimizedMa::create(conn, pair_str, offset)?;
+    // 1通貨の設定ミスやデータ不足で、他の通貨の最適化まで止めない
+    for pair_str in repositories::balance::my_trading_currencies(client).await?.iter() {
+        match optimize(conn, pair_str) {
+            Ok((run_id, search_space)) => info!("optimized_mas updated: {} run_id={} {:?}", pair_str, run_id, search_space),
+            Err(e) => {
+                error!("#- [{}] maの最適化失敗: {}", pair_str, e);
+                continue;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn optimize(conn: &mut PgConnection, pair_str: &str) -> Result<(i32, MaSearchSpace), AppError> {
+    let search_space = MaSearchSpace::from_env(pair_str)?;
+    let walk_forward = MaWalkForward::from_env(pair_str)?;
+    let run_id = models::optimized_ma::OptimizedMa::create(conn, pair_str, &search_space, walk_forward.as_ref())?;
+
+    Ok((run_id, search_space))
+}
+
+/*
+ * 最適化の実行毎に、その時点の結果から選ばれるパラメータを並べて、ベストなパラメータの推移を表示する。
+ * 選び方(MA_OPT_OBJECTIVEなど)は今の設定を使う。
+ *
+ * [envの設定]
+ * OPTIMIZATION_DRIFT_DAYS=7 (何日前からの実行を表示するか)
+ */
+pub fn drift_report(conn: &mut PgConnection) -> Result<(), AppError> {
+    let days: i64 = config::parse_var("OPTIMIZATION_DRIFT_DAYS", "7")?;
+    let since = Utc::now().naive_utc() - Duration::days(days);
+
+    for pair_str in OptimizationRun::pairs(conn, models::optimized_ma::RUN_KIND)?.iter() {
+        let selection = MaSelection::from_env(pair_str)?;
+
+        info!("#");
+        info!("# [{}] ベストなMAの推移 ({:?}, 直近{}日)", pair_str, selection.objective, days);
+        info!("#");
+        info!("{:<8} {:<20} {:>6} {:>6} {:>7} {:>9} {:>11} {:>6}", "run_id", "created_at", "short", "long", "offset", "win_rate", "expectancy", "total");
+
+        let mut previous: Option<(i32, i32)> = None;
+        for run in OptimizationRun::since(conn, pair_str, models::optimized_ma::RUN_KIND, since)?.iter() {
+            let Some(best) = OptimizedMa::best_in_run(conn, run.id, &selection)? else {
+                info!("{:<8} {:<20} ベストなmaなし", run.id, run.created_at.format("%Y-%m-%d %H:%M"));
+                previous = None;
+                continue;
+            };
+
+            let changed = previous.is_some_and(|p| p != (best.short_ma, best.long_ma));
+            info!(
+                "{:<8} {:<20} {:>6} {:>6} {:>7} {:>9.2} {:>11.4} {:>6}{}",
+                run.id,
+                run.created_at.format("%Y-%m-%d %H:%M"),
+                best.short_ma,
+                best.long_ma,
+                best.offset_minutes,
+                best.win_rate_pct.unwrap_or(0.0),
+                best.expectancy_pct.unwrap_or(0.0),
+                best.total.unwrap_or(0),
+                if changed { " *変更" } else { "" },
+            );
+            previous = Some((best.short_ma, best.long_ma));
+        }
+        info!("");
+    }
 
     Ok(())
 }
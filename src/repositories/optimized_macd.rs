@@ -0,0 +1,62 @@
+use diesel::prelude::*;
+use log::{info, error};
+
+use crate::{
+    api::coincheck,
+    models::{optimized_macd::{NewOptimizedMacd, OptimizedMacd}, ticker::Ticker},
+    error::AppError,
+    repositories,
+    strategies::macd::{self, MacdParams, MacdSearchSpace},
+};
+
+/*
+ * 取引中の通貨毎に、tickersの価格系列を一度だけ読み込み、
+ * MACD_OPT_*の範囲のfast/slow/signalの組み合わせとoffset毎に成績を計算して、今回の実行として保存する。
+ */
+pub async fn calc_macd_crossover(
+    conn: &mut PgConnection,
+    client: &coincheck::client::CoincheckClient,
+) -> Result<(), AppError> {
+
+    // 1通貨の設定ミスやデータ不足で、他の通貨の最適化まで止めない
+    for pair_str in repositories::balance::my_trading_currencies(client).await?.iter() {
+        match optimize(conn, pair_str) {
+            Ok((run_id, search_space)) => info!("optimized_macds updated: {} run_id={} {:?}", pair_str, run_id, search_space),
+            Err(e) => {
+                error!("#- [{}] macdの最適化失敗: {}", pair_str, e);
+                continue;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn optimize(conn: &mut PgConnection, pair_str: &str) -> Result<(i32, MacdSearchSpace), AppError> {
+    let search_space = MacdSearchSpace::from_env(pair_str)?;
+    let base_params = MacdParams::from_env(pair_str)?;
+    let series = Ticker::price_series(conn, pair_str)?;
+    let spread_pct = Ticker::average_spread_pct(conn, pair_str)?.unwrap_or(0.0);
+
+    let new_optimized_macds: Vec<NewOptimizedMacd> = macd::sweep(&series, &base_params, &search_space, spread_pct)
+        .iter()
+        .map(|result| NewOptimizedMacd {
+            pair: pair_str.to_string(),
+            fast_period: result.fast as i32,
+            slow_period: result.slow as i32,
+            signal_period: result.signal as i32,
+            offset_minutes: result.offset_minutes,
+            win_rate_pct: result.stats.win_rate_pct,
+            total: result.stats.total,
+            wins: result.stats.wins,
+            avg_return_pct: result.stats.avg_return_pct,
+            profit_factor: result.stats.profit_factor,
+            expectancy_pct: result.stats.expectancy_pct,
+            run_id: None,
+        })
+        .collect();
+
+    let run_id = OptimizedMacd::create(conn, pair_str, &search_space, &new_optimized_macds)?;
+
+    Ok((run_id, search_space))
+}
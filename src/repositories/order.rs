@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::env;
 use log::{info, error};
 
@@ -5,24 +6,23 @@ use diesel::prelude::*;
 use serde_json::Value;
 
 use crate::{
-    api::{coincheck, slack}, 
+    api::coincheck, 
     error::AppError, 
     models::{self, order::NewOrder}, 
+    notifier::{notification::{EventKind, Notification}, router::NotifierRouter},
     repositories
 };
 
-use crate::strategies::strategy_trait::Strategy;
-use crate::strategies::ma_optimizer::MaOptimizerStrategy;
+use crate::models::signal_vote::{NewSignalVote, SignalVote};
+use crate::strategies::{position_sizer, registry};
 
 #[allow(dead_code)]
 pub async fn post_market_order(
     conn: &mut PgConnection,
     client: &coincheck::client::CoincheckClient,
+    notifier: &NotifierRouter,
 ) -> Result<(), AppError> {
 
-    // ストラテジーの切替え
-    let strategy = MaOptimizerStrategy;
-
     // 全体の資産情報の取得
     let Some((balances, my_managed_balances, my_trading_currency, jpy_balance)) = 
         fetch_balances(client).await? else {
@@ -31,9 +31,19 @@ pub async fn post_market_order(
 
     print_log_header(my_managed_balances);
 
+    // 古いtickerで判断しないように、ticker_fetcherが止まっていれば知らせる
+    // アラートの失敗では注文を止めない
+    let alert_config = repositories::alert::config_or_log();
+    repositories::alert::check_or_log(alert_config.as_ref(), |config| {
+        repositories::alert::check_stale_tickers(conn, notifier, config, &my_trading_currency)
+    }).await;
+
     // new_ordersに、通過毎のオーダーの内容をプッシュしてまとめていく
     let mut new_orders: Vec<models::order::NewOrder> = Vec::new();
 
+    // アンサンブルの各メンバーの投票。注文の保存時にsignal_votesに紐付ける
+    let mut signal_votes: HashMap<String, Vec<NewSignalVote>> = HashMap::new();
+
     // 通貨毎のオーダーの作成と、new_ordersにプッシュ
     for currency in my_trading_currency.iter() {
 
@@ -42,8 +52,22 @@ pub async fn post_market_order(
             fetch_ticker_and_crypto_balance(client, currency, &balances).await? else {
             continue;
         };
+        repositories::alert::check_or_log(alert_config.as_ref(), |config| {
+            repositories::alert::check_spread(conn, notifier, config, currency, ticker.bid, ticker.ask)
+        }).await;
+
+        // 通貨毎に割り当てられた戦略(STRATEGY_{CURRENCY}、未設定ならSTRATEGY)
+        let (entry, strategy) = match registry::build(currency) {
+            Ok(built) => built,
+            Err(e) => {
+                error!("#- [{}] 戦略の組み立て失敗: {}", currency, e);
+                continue;
+            }
+        };
 
         let mut new_order = models::order::NewOrder::new(currency.clone());
+        new_order.strategy_name = Some(entry.name.to_string());
+        new_order.strategy_version = Some(entry.version.to_string());
 
         // 戦略に合わせて、通貨毎に注文内容を決定して、new_owdersにプッシュ
         match strategy.determine_trade_signal(
@@ -56,6 +80,7 @@ pub async fn post_market_order(
             Ok(signal) => {
                 signal.apply_to(&mut new_order);
                 new_orders.push(new_order);
+                signal_votes.insert(currency.clone(), strategy.take_votes(currency));
             },
             Err(e) => {
                 error!("#- [{}] signal取得失敗: {}", currency, e);
@@ -64,8 +89,19 @@ pub async fn post_market_order(
         };
     };
 
-    // 購入と判断した注文数で、使えるJPYを等分
-    let jpy_amount_per_currency = get_buy_ratio(jpy_balance, new_orders.len() as i32)?;
+    let buy_currencies: Vec<String> = new_orders.iter()
+        .filter(|order| order.order_type == "market_buy")
+        .map(|order| order.pair.clone())
+        .collect();
+    repositories::alert::check_or_log(alert_config.as_ref(), |config| {
+        repositories::alert::check_jpy(conn, notifier, config, jpy_balance, &buy_currencies)
+    }).await;
+
+    // 積立はJPY残高の範囲に抑え、足りなければ見送る
+    fit_contributions(jpy_balance, &mut new_orders);
+
+    // 購入と判断した通貨に、使えるJPYを配分(POSITION_SIZER)
+    let buy_amounts = allocate_buy_amounts(conn, jpy_balance, &new_orders)?;
 
     // 売りが先にくるようにソート
     new_orders.sort_by_key(|order| {
@@ -73,37 +109,112 @@ pub async fn post_market_order(
     });
 
     let mut success_order_count = 0;
-    for mut new_order in new_orders.iter_mut() {
+    for new_order in new_orders.iter_mut() {
         let amount;
-        if new_order.order_type == "market_buy" {
-            new_order.jpy_amount = jpy_amount_per_currency;
-            amount = jpy_amount_per_currency;
+        if new_order.order_type == "market_buy" && new_order.contribution_jpy.is_some() {
+            // 積立は戦略が決めた額のまま購入
+            amount = new_order.jpy_amount;
+        } else if new_order.order_type == "market_buy" {
+            let jpy_amount = buy_amounts.get(&new_order.pair).copied().unwrap_or(0.0);
+            if jpy_amount <= 0.0 {
+                new_order.order_type = "hold".to_string();
+                new_order.comment = Some(format!("配分0のため見送り: {}", new_order.comment.clone().unwrap_or_default()));
+                print_log(new_order);
+                save_order(conn, new_order, &mut signal_votes)?;
+                continue;
+            }
+            new_order.jpy_amount = jpy_amount;
+            amount = jpy_amount;
         } else if new_order.order_type == "market_sell" {
             amount = new_order.crypto_amount;
         } else {
-            print_log(&new_order);
-            models::order::Order::create(conn, &new_order)?;
+            print_log(new_order);
+            save_order(conn, new_order, &mut signal_votes)?;
             continue;
         };
 
-        let mut orderd = coincheck::order::post_market_order(client, &mut new_order, amount).await?;
+        // 注文サイズに対して板が薄く、スリッページが大きすぎる場合は見送る
+        if let Some(reason) = repositories::order_book::check_slippage(
+            client,
+            &new_order.pair,
+            &new_order.order_type,
+            amount,
+        ).await? {
+            new_order.order_type = "hold".to_string();
+            new_order.comment = Some(reason);
+            print_log(new_order);
+            save_order(conn, new_order, &mut signal_votes)?;
+            continue;
+        }
 
-        if orderd.api_call_success_at.is_some() {
-            slack::send_orderd_information(&orderd).await?;
+        let mut orderd = match post_market_order_with_retry(client, new_order, amount).await {
+            Ok(orderd) => orderd,
+            Err(AppError::Exchange { kind, message, status }) => {
+                // 失敗した注文も、レスポンスをcommentに残して記録する
+                print_log(new_order);
+                save_order(conn, new_order, &mut signal_votes)?;
+
+                if kind.is_fatal() {
+                    let halt = format!("[{}] {:?}: {}。以降の注文を停止しました", new_order.pair, kind, message);
+                    notifier.notify(&Notification::risk_halt("注文停止", &halt)).await;
+                    return Err(AppError::Exchange { kind, message, status });
+                }
+
+                error!("#- [{}] 注文失敗のためスキップ: {:?}: {}", new_order.pair, kind, message);
+                repositories::alert::check_or_log(alert_config.as_ref(), |config| {
+                    repositories::alert::order_rejected(conn, notifier, config, &new_order.pair, &new_order.order_type, kind, &message)
+                }).await;
+                continue;
+            },
+            Err(e) => return Err(e),
+        };
 
-            let orderd_rate = coincheck::rate::find(client, orderd.pair.as_str()).await?;
-            orderd.buy_rate = Some(orderd_rate.buy_rate);
-            orderd.sell_rate = Some(orderd_rate.sell_rate);
-            orderd.spread_ratio = Some(orderd_rate.spread_ratio);
+        notifier.notify(&Notification::fill(&orderd)).await;
 
-            success_order_count += 1;
-        }
+        let orderd_rate = coincheck::rate::find(client, orderd.pair.as_str()).await?;
+        orderd.buy_rate = Some(orderd_rate.buy_rate);
+        orderd.sell_rate = Some(orderd_rate.sell_rate);
+        orderd.spread_ratio = Some(orderd_rate.spread_ratio);
+
+        success_order_count += 1;
 
         print_log(&orderd);
-        models::order::Order::create(conn, &orderd)?;
+        save_order(conn, &orderd, &mut signal_votes)?;
     }
 
-    if success_order_count > 0 { make_summary(conn, client).await?; }
+    if success_order_count > 0 { make_summary(conn, client, notifier).await?; }
+
+    Ok(())
+}
+
+/*
+ * メンテナンスやレート制限など、待てば通る可能性のある失敗は一度だけ再送する。
+ */
+async fn post_market_order_with_retry(
+    client: &coincheck::client::CoincheckClient,
+    new_order: &mut NewOrder,
+    amount: f64,
+) -> Result<NewOrder, AppError> {
+    match coincheck::order::post_market_order(client, new_order, amount).await {
+        Err(AppError::Exchange { kind, .. }) if kind.is_retryable() => {
+            info!("#- [{}] {:?}のため再送", new_order.pair, kind);
+            coincheck::client::sleep()?;
+            coincheck::order::post_market_order(client, new_order, amount).await
+        },
+        result => result,
+    }
+}
+
+fn save_order(
+    conn: &mut PgConnection,
+    new_order: &NewOrder,
+    signal_votes: &mut HashMap<String, Vec<NewSignalVote>>,
+) -> Result<(), AppError> {
+    let order_id = models::order::Order::create(conn, new_order)?;
+
+    if let Some(votes) = signal_votes.get_mut(&new_order.pair) {
+        SignalVote::create_for_order(conn, order_id, votes)?;
+    }
 
     Ok(())
 }
@@ -113,15 +224,15 @@ fn print_log_header(my_managed_balances: Value) {
     info!("# オーダー情報");
     info!("#");
     info!("balance: {:#?}", my_managed_balances);
-    println!("");
+    println!();
 }
 
 async fn fetch_balances(
     client: &coincheck::client::CoincheckClient
 ) -> Result<Option<(Value, Value, Vec<String>, f64)>, AppError> {
-    let balances = repositories::balance::my_balancies(&client).await?;
+    let balances = repositories::balance::my_balancies(client).await?;
     let my_managed_balances = repositories::balance::my_managed_balancies(&balances)?;
-    let my_trading_currency = repositories::balance::my_trading_currencies(&client).await?;
+    let my_trading_currency = repositories::balance::my_trading_currencies(client).await?;
     let jpy_balance = repositories::balance::get_jpy_balance(&balances)?;
 
     Ok(Some((balances, my_managed_balances, my_trading_currency, jpy_balance)))
@@ -157,24 +268,82 @@ fn print_log(new_order: &NewOrder) {
     info!("# crypt_amount: {}", new_order.crypto_amount);
     info!("# jpy_amount: {}", new_order.jpy_amount);
     info!("# comment: {:?}", new_order.comment);
-    println!("");
+    println!();
 }
 
 async fn make_summary(
     conn: &mut PgConnection, 
-    client: &coincheck::client::CoincheckClient
+    client: &coincheck::client::CoincheckClient,
+    notifier: &NotifierRouter,
 ) -> Result<(), AppError> {
-    let mut report = repositories::summary::make_report(conn, &client).await?;
+    let mut report = repositories::summary::make_report(conn, client).await?;
     models::summary::Summary::create(conn, &report.summary, &mut report.summary_records)?;
-    slack::send_summary("直近レポート", &report.summary, report.summary_records).await?;
+    notifier.notify(&Notification::report(
+        EventKind::Fill,
+        "直近レポート",
+        &report.summary,
+        &report.summary_records,
+        Vec::new(),
+    )).await;
 
     Ok(())
 }
 
 /*
- * 通貨毎に購入するJPYを算出(今は単純に等分している）。
+ * 積立の購入額の合計がJPY残高を超えないように、順に残りのJPYまでに抑える。
+ * 残りがなくなった積立はholdにして、理由をcommentに残す。
  */
-fn get_buy_ratio(jpy_balance: f64, new_orders_length: i32) -> Result<f64, AppError> {
+fn fit_contributions(jpy_balance: f64, new_orders: &mut [NewOrder]) {
+    let mut remaining = jpy_balance.max(0.0);
+
+    for new_order in new_orders.iter_mut().filter(|order| order.contribution_jpy.is_some()) {
+        let requested = new_order.jpy_amount;
+        if remaining <= 0.0 {
+            new_order.order_type = "hold".to_string();
+            new_order.jpy_amount = 0.0;
+            new_order.contribution_jpy = None;
+            new_order.comment = Some(format!("JPY残高不足のため積立見送り: {}", new_order.comment.clone().unwrap_or_default()));
+            continue;
+        }
+
+        if requested > remaining {
+            new_order.jpy_amount = remaining;
+            new_order.contribution_jpy = Some(remaining);
+            new_order.comment = Some(format!(
+                "JPY残高までに減額({} -> {}): {}",
+                requested, remaining, new_order.comment.clone().unwrap_or_default()
+            ));
+        }
+        remaining -= new_order.jpy_amount;
+    }
+}
+
+/*
+ * 購入(積立以外)と判断した通貨毎に、購入するJPYを算出。
+ * 積立で使う分を除いたJPYから、段階的な割合で使えるJPYを決めて、PositionSizerで配分する。
+ */
+fn allocate_buy_amounts(
+    conn: &mut PgConnection,
+    jpy_balance: f64,
+    new_orders: &[NewOrder],
+) -> Result<HashMap<String, f64>, AppError> {
+    let contribution_jpy: f64 = new_orders.iter().filter_map(|order| order.contribution_jpy).sum();
+    let budget = get_buy_budget((jpy_balance - contribution_jpy).max(0.0))?;
+
+    let buys: Vec<&NewOrder> = new_orders.iter()
+        .filter(|order| order.order_type == "market_buy" && order.contribution_jpy.is_none())
+        .collect();
+
+    let sizer = position_sizer::from_env()?;
+    let amounts = sizer.allocate(conn, budget, &buys)?;
+
+    Ok(buys.iter().map(|order| order.pair.clone()).zip(amounts).collect())
+}
+
+/*
+ * JPY残高のうち、今回の購入に使うJPYを算出。
+ */
+fn get_buy_budget(jpy_balance: f64) -> Result<f64, AppError> {
     let threshold_1 = env::var("BUY_THRESHOLD_1")?.parse::<f64>().unwrap();
     let threshold_2 = env::var("BUY_THRESHOLD_2")?.parse::<f64>().unwrap();
     let threshold_3 = env::var("BUY_THRESHOLD_3")?.parse::<f64>().unwrap();
@@ -194,8 +363,67 @@ fn get_buy_ratio(jpy_balance: f64, new_orders_length: i32) -> Result<f64, AppErr
         ratio_default
     };
 
-    let jpy_amount = jpy_balance * buy_ratio;
-    let jpy_amount_per_currency = jpy_amount / new_orders_length as f64;
+    Ok(jpy_balance * buy_ratio)
+}
 
-    Ok(jpy_amount_per_currency)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contribution(pair: &str, jpy_amount: f64) -> NewOrder {
+        let mut new_order = NewOrder::new(pair.to_string());
+        new_order.order_type = "market_buy".to_string();
+        new_order.jpy_amount = jpy_amount;
+        new_order.contribution_jpy = Some(jpy_amount);
+        new_order.comment = Some("積立".to_string());
+        new_order
+    }
+
+    #[test]
+    fn fit_contributions_keeps_amounts_within_the_balance() {
+        let mut new_orders = vec![contribution("btc", 1000.0), contribution("eth", 2000.0)];
+
+        fit_contributions(5000.0, &mut new_orders);
+
+        assert_eq!(new_orders[0].jpy_amount, 1000.0);
+        assert_eq!(new_orders[1].jpy_amount, 2000.0);
+        assert_eq!(new_orders[1].contribution_jpy, Some(2000.0));
+    }
+
+    #[test]
+    fn fit_contributions_caps_at_the_remaining_balance() {
+        let mut new_orders = vec![contribution("btc", 1000.0), contribution("eth", 2000.0)];
+
+        fit_contributions(1500.0, &mut new_orders);
+
+        assert_eq!(new_orders[0].jpy_amount, 1000.0);
+        assert_eq!(new_orders[1].order_type, "market_buy");
+        assert_eq!(new_orders[1].jpy_amount, 500.0);
+        assert_eq!(new_orders[1].contribution_jpy, Some(500.0));
+    }
+
+    #[test]
+    fn fit_contributions_holds_when_no_jpy_is_left() {
+        let mut new_orders = vec![contribution("btc", 1000.0), contribution("eth", 2000.0)];
+
+        fit_contributions(1000.0, &mut new_orders);
+
+        assert_eq!(new_orders[1].order_type, "hold");
+        assert_eq!(new_orders[1].jpy_amount, 0.0);
+        assert_eq!(new_orders[1].contribution_jpy, None);
+        assert!(new_orders[1].comment.as_deref().unwrap().starts_with("JPY残高不足"));
+    }
+
+    #[test]
+    fn fit_contributions_ignores_other_buys() {
+        let mut buy = NewOrder::new("xrp".to_string());
+        buy.order_type = "market_buy".to_string();
+        buy.jpy_amount = 9999.0;
+        let mut new_orders = vec![buy, contribution("btc", 1000.0)];
+
+        fit_contributions(1000.0, &mut new_orders);
+
+        assert_eq!(new_orders[0].jpy_amount, 9999.0);
+        assert_eq!(new_orders[1].jpy_amount, 1000.0);
+    }
 }
@@ -0,0 +1,58 @@
+use std::env;
+use dotenvy::dotenv;
+
+use diesel::prelude::*;
+
+use crate::{
+    api::coincheck,
+    config,
+    error::AppError,
+    models,
+};
+
+/*
+ * [envの設定]
+ * MAX_SLIPPAGE_PCT=0.5 (未設定ならスリッページのチェックをしない)
+ * ORDER_BOOK_SNAPSHOT_ENABLED=true (設定時のみ、order_book_snapshotsに板を保存)
+ */
+
+/*
+ * 注文サイズに対する想定スリッページを板から見積もり、上限を超えるなら見送りの理由を返す。
+ */
+pub async fn check_slippage(
+    client: &coincheck::client::CoincheckClient,
+    currency: &str,
+    order_type: &str,
+    amount: f64,
+) -> Result<Option<String>, AppError> {
+    let Some(max_slippage_pct) = max_slippage_pct()? else {
+        return Ok(None);
+    };
+
+    let order_book = coincheck::order_book::find(client, currency).await?;
+
+    Ok(order_book.slippage_rejection(order_type, amount, max_slippage_pct))
+}
+
+pub async fn save_snapshot(
+    conn: &mut PgConnection,
+    client: &coincheck::client::CoincheckClient,
+    currency: &str,
+) -> Result<(), AppError> {
+    dotenv().ok();
+
+    if env::var("ORDER_BOOK_SNAPSHOT_ENABLED").unwrap_or_default() != "true" {
+        return Ok(());
+    }
+
+    let order_book = coincheck::order_book::find(client, currency).await?;
+    if let Some(new_snapshot) = models::order_book::NewOrderBookSnapshot::from_order_book(&order_book) {
+        models::order_book::OrderBookSnapshot::create(conn, &new_snapshot)?;
+    }
+
+    Ok(())
+}
+
+fn max_slippage_pct() -> Result<Option<f64>, AppError> {
+    config::optional_var("MAX_SLIPPAGE_PCT")
+}
@@ -0,0 +1,111 @@
+use diesel::prelude::*;
+use log::{info, error};
+
+use crate::{
+    api::coincheck,
+    error::AppError,
+    models,
+    notifier::{notification::{EventKind, Notification}, router::NotifierRouter},
+    repositories,
+    strategies::rebalance::{self, RebalanceConfig},
+};
+
+/*
+ * 目標比率からずれた通貨を売買して戻す。dry_runの時は注文内容を表示するだけ。
+ */
+pub async fn run(
+    conn: &mut PgConnection,
+    client: &coincheck::client::CoincheckClient,
+    notifier: &NotifierRouter,
+    dry_run: bool,
+) -> Result<(), AppError> {
+    let config = RebalanceConfig::from_env()?;
+    let report = repositories::summary::make_report(conn, client).await?;
+
+    info!("#");
+    info!("# リバランス{}", if dry_run { "(dry-run)" } else { "" });
+    info!("#");
+    for record in report.summary_records.iter() {
+        let target = config.targets.iter().find(|(c, _)| *c == record.currency).map(|(_, w)| w * 100.0);
+        info!(
+            "# {}: {:.0}JPY ({:.2}%) 目標: {:?}%",
+            record.currency,
+            record.jpy_value,
+            record.jpy_value / report.summary.total_jpy_value * 100.0,
+            target,
+        );
+    }
+
+    let orders = rebalance::plan(&config, &report.summary_records)?;
+    if orders.is_empty() {
+        info!("# 全通貨が許容幅内のため、リバランスなし");
+        return Ok(());
+    }
+
+    for order in orders.iter() {
+        info!("#-- [ {} ] {} amount={} ({:.0}JPY): {}", order.order_type, order.pair, order.amount, order.jpy_value, order.reason);
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    let mut success_order_count = 0;
+    for order in orders.iter() {
+        let mut new_order = models::order::NewOrder::new(order.pair.clone());
+        new_order.order_type = order.order_type.clone();
+        new_order.strategy_name = Some("rebalance".to_string());
+        new_order.ma_short = None;
+        new_order.ma_long = None;
+        new_order.ma_win_rate = None;
+        new_order.comment = Some(order.reason.clone());
+        if order.order_type == "market_buy" {
+            new_order.jpy_amount = order.amount;
+        } else {
+            new_order.crypto_amount = order.amount;
+        }
+
+        match coincheck::order::post_market_order(client, &mut new_order, order.amount).await {
+            Ok(mut orderd) => {
+                notifier.notify(&Notification::fill(&orderd)).await;
+
+                let orderd_rate = coincheck::rate::find(client, orderd.pair.as_str()).await?;
+                orderd.buy_rate = Some(orderd_rate.buy_rate);
+                orderd.sell_rate = Some(orderd_rate.sell_rate);
+                orderd.spread_ratio = Some(orderd_rate.spread_ratio);
+
+                models::order::Order::create(conn, &orderd)?;
+                success_order_count += 1;
+            },
+            Err(AppError::Exchange { kind, message, status }) => {
+                models::order::Order::create(conn, &new_order)?;
+                if kind.is_fatal() {
+                    let halt = format!("[{}] {:?}: {}。リバランスを停止しました", order.pair, kind, message);
+                    notifier.notify(&Notification::risk_halt("リバランス停止", &halt)).await;
+                    return Err(AppError::Exchange { kind, message, status });
+                }
+                // 売りが失敗するとJPYが足りない可能性があるが、買いは取引所側で弾かれる
+                error!("#- [{}] リバランス注文失敗のためスキップ: {:?}: {}", order.pair, kind, message);
+                repositories::alert::check_or_log(repositories::alert::config_or_log().as_ref(), |config| {
+                    repositories::alert::order_rejected(conn, notifier, config, &order.pair, &order.order_type, kind, &message)
+                }).await;
+            },
+            Err(e) => return Err(e),
+        }
+    }
+
+    if success_order_count > 0 {
+        let mut report = repositories::summary::make_report(conn, client).await?;
+        models::summary::Summary::create(conn, &report.summary, &mut report.summary_records)?;
+        notifier.notify(&Notification::report(
+            EventKind::Fill,
+            "リバランス後レポート",
+            &report.summary,
+            &report.summary_records,
+            Vec::new(),
+        )).await;
+    }
+
+    Ok(())
+}
+
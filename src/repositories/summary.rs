@@ -1,4 +1,4 @@
-use log::info;
+use log::{info, error};
 
 use diesel::prelude::*;
 
@@ -9,6 +9,7 @@ use crate::{
     api,
 };
 use crate::error::AppError;
+use crate::notifier::{notification::{EventKind, Notification}, router::NotifierRouter};
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -21,12 +22,26 @@ pub struct Report {
 pub async fn reporing(
     conn: &mut PgConnection, 
     client: &api::coincheck::client::CoincheckClient,
+    notifier: &NotifierRouter,
 ) -> Result<(), AppError> {
 
-    let mut report = make_report(conn, &client).await?;
+    let mut report = make_report(conn, client).await?;
     models::summary::Summary::create(conn, &report.summary, &mut report.summary_records)?;
 
-    api::slack::send_summary("本日のレポート", &report.summary, report.summary_records).await?;
+    // チャートが描けなくてもレポートは送るので、失敗はログだけ
+    let charts = repositories::chart::render_all(conn).unwrap_or_else(|e| {
+        error!("Chart rendering failed: {}", e);
+        Vec::new()
+    });
+    info!("Charts rendered: {:?}", charts);
+
+    notifier.notify(&Notification::report(
+        EventKind::DailyReport,
+        "本日のレポート",
+        &report.summary,
+        &report.summary_records,
+        charts,
+    )).await;
 
     info!("Execute summary successful.");
     Ok(())
@@ -38,8 +53,8 @@ pub async fn make_report(
     client: &api::coincheck::client::CoincheckClient,
 ) -> Result<Report, AppError> {
 
-    let my_balancies = repositories::balance::my_balancies(&client).await?;
-    let my_trading_currencies = repositories::balance::my_trading_currencies(&client).await?;
+    let my_balancies = repositories::balance::my_balancies(client).await?;
+    let my_trading_currencies = repositories::balance::my_trading_currencies(client).await?;
 
     let mut new_summary_records: Vec<models::summary_record::NewSummaryRecord> = Vec::new();
     let mut total_jpy_value: f64 = my_balancies.get("jpy").unwrap().as_f64().unwrap_or(0.0);
@@ -48,7 +63,7 @@ pub async fn make_report(
         for currency in my_trading_currencies.iter() {
             if let Some(amount) = balances.get(currency).and_then(|v| v.as_f64()) {
 
-                let rate = api::coincheck::rate::find(&client, &currency).await?;
+                let rate = api::coincheck::rate::find(client, currency).await?;
                 let jpy_value = amount * rate.sell_rate;
 
                 new_summary_records.push(models::summary_record::NewSummaryRecord {
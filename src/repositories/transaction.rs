@@ -1,6 +1,5 @@
 use diesel::prelude::*;
 use diesel::pg::PgConnection;
-use diesel::dsl::sum;
 
 use crate::schema::transactions::dsl::*;
 use crate::error::AppError;
@@ -16,7 +15,7 @@ pub struct Summary {
 pub fn total_invested(conn: &mut PgConnection) -> Result<f64, AppError> {
     let invested: Option<f64> = transactions
         .filter(order_type.eq("buy"))
-        .select(sum(price))
+        .select(diesel::dsl::sum(price))
         .first(conn)?;
 
     Ok(invested.unwrap_or(0.0))
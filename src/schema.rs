@@ -1,5 +1,80 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    alerts (id) {
+        id -> Int4,
+        rule -> Text,
+        key -> Text,
+        message -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    grid_levels (id) {
+        id -> Int4,
+        grid_id -> Int4,
+        level_index -> Int4,
+        price -> Float8,
+        side -> Text,
+        state -> Text,
+        exchange_order_id -> Nullable<Int8>,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    grids (id) {
+        id -> Int4,
+        pair -> Text,
+        lower_price -> Float8,
+        upper_price -> Float8,
+        levels -> Int4,
+        order_amount -> Float8,
+        active -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    job_runs (id) {
+        id -> Int4,
+        job -> Text,
+        success -> Bool,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    optimization_runs (id) {
+        id -> Int4,
+        pair -> Text,
+        kind -> Text,
+        settings -> Nullable<Jsonb>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    optimized_macds (id) {
+        id -> Int4,
+        pair -> Text,
+        fast_period -> Int4,
+        slow_period -> Int4,
+        signal_period -> Int4,
+        offset_minutes -> Int4,
+        win_rate_pct -> Nullable<Float8>,
+        total -> Nullable<Int4>,
+        wins -> Nullable<Int4>,
+        created_at -> Timestamp,
+        avg_return_pct -> Nullable<Float8>,
+        profit_factor -> Nullable<Float8>,
+        expectancy_pct -> Nullable<Float8>,
+        run_id -> Nullable<Int4>,
+    }
+}
+
 diesel::table! {
     optimized_mas (id) {
         id -> Int4,
@@ -11,6 +86,28 @@ diesel::table! {
         total -> Nullable<Int4>,
         wins -> Nullable<Int4>,
         created_at -> Timestamp,
+        avg_return_pct -> Nullable<Float8>,
+        profit_factor -> Nullable<Float8>,
+        expectancy_pct -> Nullable<Float8>,
+        oos_total -> Nullable<Int4>,
+        oos_win_rate_pct -> Nullable<Float8>,
+        oos_avg_return_pct -> Nullable<Float8>,
+        oos_profit_factor -> Nullable<Float8>,
+        oos_expectancy_pct -> Nullable<Float8>,
+        promoted -> Nullable<Bool>,
+        run_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    order_book_snapshots (id) {
+        id -> Int4,
+        pair -> Text,
+        best_bid -> Float8,
+        best_ask -> Float8,
+        bids -> Jsonb,
+        asks -> Jsonb,
+        created_at -> Timestamp,
     }
 }
 
@@ -34,6 +131,25 @@ diesel::table! {
         ma_short -> Nullable<Int4>,
         ma_long -> Nullable<Int4>,
         ma_win_rate -> Nullable<Float8>,
+        contribution_jpy -> Nullable<Float8>,
+        strategy_name -> Nullable<Text>,
+        strategy_version -> Nullable<Text>,
+        confidence -> Nullable<Float8>,
+        position_fraction -> Nullable<Float8>,
+        signal_metadata -> Nullable<Jsonb>,
+        optimization_run_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    signal_votes (id) {
+        id -> Int4,
+        order_id -> Int4,
+        strategy -> Text,
+        vote -> Text,
+        weight -> Float8,
+        reason -> Nullable<Text>,
+        created_at -> Timestamp,
     }
 }
 
@@ -92,9 +208,23 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(grid_levels -> grids (grid_id));
+diesel::joinable!(optimized_macds -> optimization_runs (run_id));
+diesel::joinable!(optimized_mas -> optimization_runs (run_id));
+diesel::joinable!(orders -> optimization_runs (optimization_run_id));
+diesel::joinable!(signal_votes -> orders (order_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
+    alerts,
+    grid_levels,
+    grids,
+    job_runs,
+    optimization_runs,
+    optimized_macds,
     optimized_mas,
+    order_book_snapshots,
     orders,
+    signal_votes,
     summaries,
     summary_records,
     tickers,
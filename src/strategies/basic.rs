@@ -1,12 +1,13 @@
-use std::env;
 use dotenvy::dotenv;
 
 use async_trait::async_trait;
+use serde_json::{json, Value};
 
 use diesel::prelude::*;
 use diesel::sql_types::{Nullable, Double, Text, Integer};
 
 use crate::{
+    config,
     models,
     error::AppError,
     strategies::{
@@ -57,15 +58,13 @@ impl Strategy for BasicStrategy {
     ) -> Result<TradeSignal, AppError> {
         dotenv().ok();
     
-        let sma_short = env::var("MA_SHORT")?.parse::<i32>()
-            .map_err(|e| AppError::InvalidData(format!("Parse error: {}", e)))?;
-    
-        let sma_long = env::var("MA_LONG")?.parse::<i32>()
-            .map_err(|e| AppError::InvalidData(format!("Parse error: {}", e)))?;
+        let sma_short: i32 = config::require_param("MA_SHORT", currency)?;
+        let sma_long: i32 = config::require_param("MA_LONG", currency)?;
 
-        let sell_ratio = env::var("SELL_RATIO")?.parse::<f64>().unwrap();
+        let sell_ratio: f64 = config::require_param("SELL_RATIO", currency)?;
+        let metadata = json!({"ma_short": sma_short, "ma_long": sma_long});
     
-        let periods = vec![sma_short, sma_long];
+        let periods = [sma_short, sma_long];
         let mut results: Vec<Option<AvgResult>> = Vec::new();
     
         for period in periods.iter() {
@@ -87,8 +86,8 @@ impl Strategy for BasicStrategy {
             results.push(ma);
         }
     
-        let ma_short_avg = results[0].as_ref().map(|r| r.avg).flatten();
-        let ma_long_avg = results[1].as_ref().map(|r| r.avg).flatten();
+        let ma_short_avg = results[0].as_ref().and_then(|r| r.avg);
+        let ma_long_avg = results[1].as_ref().and_then(|r| r.avg);
     
         let a_spread_ratio = ((current_ask - current_bid) / current_bid) * 100.0;
         let a_spread_threshold = models::ticker::get_dynamic_spread_threshold(conn, currency).await?;
@@ -96,9 +95,9 @@ impl Strategy for BasicStrategy {
             return Ok(TradeSignal::Hold { 
                 spread_threshold: Some(a_spread_threshold),
                 spread_ratio: Some(a_spread_ratio),
-                ma_short: Some(sma_short),
-                ma_long: Some(sma_long),
-                ma_win_rate: None,
+                confidence: None,
+                position_fraction: None,
+                metadata: metadata.clone(),
                 reason: Some("スプレッド負け".to_string()) 
             });
         }
@@ -112,9 +111,9 @@ impl Strategy for BasicStrategy {
                     Ok(TradeSignal::MarcketBuy { 
                         spread_threshold: Some(a_spread_threshold),
                         spread_ratio: Some(a_spread_ratio),
-                        ma_short: Some(sma_short),
-                        ma_long: Some(sma_long),
-                        ma_win_rate: None,
+                        confidence: None,
+                        position_fraction: None,
+                        metadata: metadata.clone(),
                         amount: 0.0, reason: None 
                     })
     
@@ -125,9 +124,9 @@ impl Strategy for BasicStrategy {
                     Ok(TradeSignal::MarcketSell { 
                         spread_threshold: Some(a_spread_threshold),
                         spread_ratio: Some(a_spread_ratio),
-                        ma_short: Some(sma_short),
-                        ma_long: Some(sma_long),
-                        ma_win_rate: None,
+                        confidence: None,
+                        position_fraction: None,
+                        metadata: metadata.clone(),
                         amount, 
                         reason: None 
                     })
@@ -140,9 +139,9 @@ impl Strategy for BasicStrategy {
                     Ok(TradeSignal::Hold { 
                         spread_threshold: Some(a_spread_threshold),
                         spread_ratio: Some(a_spread_ratio),
-                        ma_short: Some(sma_short),
-                        ma_long: Some(sma_long),
-                        ma_win_rate: None,
+                        confidence: None,
+                        position_fraction: None,
+                        metadata: metadata.clone(),
                         reason: Some(reason) 
                     })
                 }
@@ -150,9 +149,9 @@ impl Strategy for BasicStrategy {
             _ => Ok(TradeSignal::InsufficientData { 
                 spread_threshold: None,
                 spread_ratio: None,
-                ma_short: None,
-                ma_long: None,
-                ma_win_rate: None,
+                confidence: None,
+                position_fraction: None,
+                metadata: Value::Null,
                 reason: Some("データ不足".to_string()) 
             })
         }
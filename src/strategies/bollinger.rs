@@ -0,0 +1,191 @@
+use dotenvy::dotenv;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use diesel::prelude::*;
+
+use crate::{
+    config,
+    models,
+    error::AppError,
+    strategies::{
+        indicator,
+        strategy_trait::Strategy,
+        trade_signal::TradeSignal,
+    },
+};
+
+/*
+ * [strategy]
+ * tickersからボリンジャーバンドを計算して売買する。
+ *
+ * reversion(逆張り): 下のバンドを割ったら買い、上のバンドを超えたら売り
+ * breakout(順張り):  上のバンドを超えたら買い、下のバンドを割ったら売り
+ *
+ * 売却量は、バンドからの乖離が大きいほど増やす。
+ * SELL_RATIOを基準に、バンド幅(middleからバンドまで)1つ分離れる毎にSELL_RATIO分を上乗せ(最大で全量)。
+ *
+ * [cron]
+ * 2分毎に、cargo run --bin ticker_fetcherを実行して、tickersに情報を蓄積
+ * 15毎に、cargo run --bin orderを実行して、注文
+ *
+ * [envの設定]
+ * BOLLINGER_MODE=reversion (reversion or breakout)
+ * BOLLINGER_PERIOD=20
+ * BOLLINGER_K=2.0
+ * SELL_RATIO=0.4
+ */
+
+pub struct BollingerStrategy;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BollingerMode {
+    Reversion,
+    Breakout,
+}
+
+impl BollingerMode {
+    pub fn from_env(currency: &str) -> Result<Self, AppError> {
+        match config::param("BOLLINGER_MODE", currency).unwrap_or("reversion".to_string()).as_str() {
+            "reversion" => Ok(BollingerMode::Reversion),
+            "breakout" => Ok(BollingerMode::Breakout),
+            other => Err(AppError::InvalidData(format!("Invalid BOLLINGER_MODE: {}", other))),
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for BollingerStrategy {
+    async fn determine_trade_signal(
+        &self,
+        conn: &mut PgConnection,
+        currency: &str,
+        current_bid: f64,
+        current_ask: f64,
+        crypto_balance: f64,
+    ) -> Result<TradeSignal, AppError> {
+        dotenv().ok();
+
+        let mode = BollingerMode::from_env(currency)?;
+        let period: usize = config::parse_param("BOLLINGER_PERIOD", currency, "20")?;
+        let k: f64 = config::parse_param("BOLLINGER_K", currency, "2.0")?;
+
+        let sell_ratio: f64 = config::require_param("SELL_RATIO", currency)?;
+
+        let prices = models::ticker::Ticker::recent_prices(conn, currency, period as i64)?;
+        let Some(bands) = indicator::bollinger(&prices, period, k) else {
+            return Ok(TradeSignal::InsufficientData {
+                spread_threshold: None,
+                spread_ratio: None,
+                confidence: None,
+                position_fraction: None,
+                metadata: Value::Null,
+                reason: Some("データ不足".to_string())
+            });
+        };
+        let metadata = json!({
+            "bollinger_mode": format!("{:?}", mode),
+            "bollinger_period": period,
+            "bollinger_k": k,
+            "bollinger_lower": bands.lower,
+            "bollinger_middle": bands.middle,
+            "bollinger_upper": bands.upper,
+        });
+
+        let a_spread_ratio = ((current_ask - current_bid) / current_bid) * 100.0;
+        let a_spread_threshold = models::ticker::get_dynamic_spread_threshold(conn, currency).await?;
+        if a_spread_ratio > a_spread_threshold {
+            return Ok(TradeSignal::Hold {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence: None,
+                position_fraction: None,
+                metadata: metadata.clone(),
+                reason: Some("スプレッド負け".to_string())
+            });
+        }
+
+        let price = (current_bid + current_ask) / 2.0;
+        let band_text = format!(
+            "{:?} price={:.2} band=[{:.2}, {:.2}, {:.2}]",
+            mode, price, bands.lower, bands.middle, bands.upper
+        );
+
+        let above = price > bands.upper;
+        let below = price < bands.lower;
+        let (buy, sell) = match mode {
+            BollingerMode::Reversion => (below, above),
+            BollingerMode::Breakout => (above, below),
+        };
+
+        if buy {
+            // 0.0の仮値をセット
+            // すべてjpyで購入なので、呼び出し元で他購入通貨とのバランスを計算して再セットする。
+            let reason = format!("{}、jpy_amount分{}を購入", band_text, currency);
+            Ok(TradeSignal::MarcketBuy {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence: None,
+                position_fraction: None,
+                metadata: metadata.clone(),
+                amount: 0.0,
+                reason: Some(reason)
+            })
+
+        } else if sell {
+            // TODO: マジックナンバー。0.001はbtc最低売却量のthreshold。マップでもたせる。
+            let amount = crypto_balance * scaled_sell_ratio(sell_ratio, price, &bands);
+            if amount < 0.001 {
+                let reason = format!("{}、最低売却量未満: {}", band_text, amount);
+                return Ok(TradeSignal::Hold {
+                    spread_threshold: Some(a_spread_threshold),
+                    spread_ratio: Some(a_spread_ratio),
+                    confidence: None,
+                    position_fraction: None,
+                    metadata: metadata.clone(),
+                    reason: Some(reason)
+                });
+            }
+            let reason = format!("{}、{}{}を売却", band_text, amount, currency);
+            Ok(TradeSignal::MarcketSell {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence: None,
+                position_fraction: None,
+                metadata: metadata.clone(),
+                amount,
+                reason: Some(reason)
+            })
+
+        } else {
+            Ok(TradeSignal::Hold {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence: None,
+                position_fraction: None,
+                metadata: metadata.clone(),
+                reason: Some(format!("{}、バンド内", band_text))
+            })
+        }
+    }
+}
+
+/*
+ * バンドの外側にどれだけ離れているかで売却割合を増やす。
+ * 乖離をバンド幅(middleからバンドまで)で割った分だけ、sell_ratioを上乗せする。
+ */
+fn scaled_sell_ratio(sell_ratio: f64, price: f64, bands: &indicator::Bands) -> f64 {
+    let half_width = bands.upper - bands.middle;
+    if half_width <= 0.0 {
+        return sell_ratio;
+    }
+
+    let distance = if price > bands.upper {
+        (price - bands.upper) / half_width
+    } else {
+        (bands.lower - price) / half_width
+    };
+
+    (sell_ratio * (1.0 + distance.max(0.0))).min(1.0)
+}
@@ -0,0 +1,122 @@
+use chrono::{Duration, NaiveDateTime};
+
+/*
+ * クロスの検出と、クロス後の値動きでの勝敗判定。
+ * optimized_mas / optimized_macdsの最適化で共通して使う。
+ */
+
+#[derive(Debug, Clone, Copy)]
+pub struct Cross {
+    // 価格系列でのindex
+    pub index: usize,
+    // trueならゴールデンクロス(下から上)、falseならデッドクロス
+    pub golden: bool,
+}
+
+/*
+ * diffs(短期 - 長期など)の符号が変わった位置をクロスとして返す。
+ * diffs[0]は価格系列のbase_index番目に対応する。
+ */
+pub fn find_crosses(diffs: &[f64], base_index: usize) -> Vec<Cross> {
+    diffs.windows(2)
+        .enumerate()
+        .filter_map(|(i, w)| {
+            let (prev, diff) = (w[0], w[1]);
+            if prev < 0.0 && diff >= 0.0 {
+                Some(Cross { index: base_index + i + 1, golden: true })
+            } else if prev > 0.0 && diff <= 0.0 {
+                Some(Cross { index: base_index + i + 1, golden: false })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/*
+ * クロスからoffset_minutes後の最初の価格で勝敗を判定して、(total, wins)を返す。
+ * GCは上がっていれば勝ち、DCは下がっていれば勝ち。判定できる価格がまだないクロスは数えない。
+ */
+pub fn evaluate(
+    series: &[(NaiveDateTime, f64)],
+    crosses: &[Cross],
+    offset_minutes: i64,
+) -> (i32, i32) {
+    let returns = returns(series, crosses, offset_minutes);
+    let wins = returns.iter().filter(|r| **r > 0.0).count();
+
+    (returns.len() as i32, wins as i32)
+}
+
+/*
+ * クロス毎の、offset_minutes後までのリターン(%)。
+ * GCは買っていた場合の値上がり率、DCは売っていた場合に避けられた値下がり率。
+ */
+pub fn returns(
+    series: &[(NaiveDateTime, f64)],
+    crosses: &[Cross],
+    offset_minutes: i64,
+) -> Vec<f64> {
+    indexed_returns(series, crosses, offset_minutes)
+        .into_iter()
+        .map(|(_, r)| r)
+        .collect()
+}
+
+// returnsに、クロスの価格系列でのindexを付けたもの
+pub fn indexed_returns(
+    series: &[(NaiveDateTime, f64)],
+    crosses: &[Cross],
+    offset_minutes: i64,
+) -> Vec<(usize, f64)> {
+    crosses.iter()
+        .filter_map(|cross| {
+            let (cross_time, cross_price) = series[cross.index];
+            let target = cross_time + Duration::minutes(offset_minutes);
+
+            let after = cross.index + series[cross.index..].partition_point(|(t, _)| *t < target);
+            let (_, after_price) = series.get(after)?;
+            if cross_price <= 0.0 {
+                return None;
+            }
+
+            let change = (after_price - cross_price) / cross_price * 100.0;
+            Some((cross.index, if cross.golden { change } else { -change }))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CrossStats {
+    pub total: i32,
+    pub wins: i32,
+    pub win_rate_pct: f64,
+    pub avg_return_pct: f64,
+    // 負けがなければNone
+    pub profit_factor: Option<f64>,
+    // 1回の売買でスプレッド分を払った後の、平均リターン(%)
+    pub expectancy_pct: f64,
+}
+
+impl CrossStats {
+    pub fn from_returns(returns: &[f64], spread_pct: f64) -> Option<Self> {
+        if returns.is_empty() {
+            return None;
+        }
+
+        let total = returns.len() as i32;
+        let wins = returns.iter().filter(|r| **r > 0.0).count() as i32;
+        let avg_return_pct = returns.iter().sum::<f64>() / total as f64;
+        let gross_profit: f64 = returns.iter().filter(|r| **r > 0.0).sum();
+        let gross_loss: f64 = -returns.iter().filter(|r| **r < 0.0).sum::<f64>();
+
+        Some(Self {
+            total,
+            wins,
+            win_rate_pct: (wins as f64 * 10000.0 / total as f64).round() / 100.0,
+            avg_return_pct,
+            profit_factor: if gross_loss > 0.0 { Some(gross_profit / gross_loss) } else { None },
+            expectancy_pct: avg_return_pct - spread_pct,
+        })
+    }
+}
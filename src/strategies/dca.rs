@@ -0,0 +1,124 @@
+use dotenvy::dotenv;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use chrono::{Duration, Utc};
+
+use diesel::prelude::*;
+
+use crate::{
+    config,
+    models,
+    error::AppError,
+    strategies::{
+        strategy_trait::Strategy,
+        trade_signal::TradeSignal,
+    },
+};
+
+/*
+ * [strategy]
+ * 積立(ドルコスト平均法)。通貨毎に、決まった間隔で決まったJPYを購入する。売却はしない。
+ * 価格がN日平均よりX%以上安い時は購入額を倍率分増やし、上限価格より高い時は見送る。
+ * 積立の注文は、ordersのcontribution_jpyに積立額を記録して、他の売買と区別する。
+ * 積立額がJPY残高を超える場合は、注文時に残高までに抑え、残高がなければ見送る。
+ *
+ * [cron]
+ * 2分毎に、cargo run --bin ticker_fetcherを実行して、tickersに情報を蓄積
+ * 15毎に、cargo run --bin orderを実行して、注文(前回の積立からDCA_INTERVAL_HOURS経過していれば購入)
+ *
+ * [envの設定]
+ * DCA_JPY_AMOUNT=1000
+ * DCA_JPY_AMOUNT_BTC=3000 (通貨毎に上書き、任意)
+ * DCA_INTERVAL_HOURS=24
+ * DCA_DIP_DAYS=7
+ * DCA_DIP_PCT=5.0
+ * DCA_DIP_MULTIPLIER=2.0 (未設定なら1.0で押し目買いなし)
+ * DCA_PRICE_CEILING_BTC=20000000 (任意、これより高い時は見送り)
+ */
+
+pub struct DcaStrategy;
+
+#[async_trait]
+impl Strategy for DcaStrategy {
+    async fn determine_trade_signal(
+        &self,
+        conn: &mut PgConnection,
+        currency: &str,
+        current_bid: f64,
+        current_ask: f64,
+        _crypto_balance: f64,
+    ) -> Result<TradeSignal, AppError> {
+        dotenv().ok();
+
+        let jpy_amount: f64 = config::require_param("DCA_JPY_AMOUNT", currency)?;
+        let interval_hours: f64 = config::parse_param("DCA_INTERVAL_HOURS", currency, "24")?;
+        let dip_days: f64 = config::parse_param("DCA_DIP_DAYS", currency, "7")?;
+        let dip_pct: f64 = config::parse_param("DCA_DIP_PCT", currency, "5.0")?;
+        let dip_multiplier: f64 = config::parse_param("DCA_DIP_MULTIPLIER", currency, "1.0")?;
+
+        // 前回の積立からinterval経っていなければ何もしない
+        let now = Utc::now().naive_utc();
+        if let Some(last_at) = models::order::Order::last_contribution_at(conn, currency)? {
+            let next_at = last_at + Duration::minutes((interval_hours * 60.0) as i64);
+            if now < next_at {
+                return Ok(TradeSignal::Hold {
+                    spread_threshold: None,
+                    spread_ratio: None,
+                    confidence: None,
+                    position_fraction: None,
+                    metadata: Value::Null,
+                    reason: Some(format!("積立は{}以降", next_at.format("%Y-%m-%d %H:%M")))
+                });
+            }
+        }
+
+        if let Some(ceiling) = config::optional_param::<f64>("DCA_PRICE_CEILING", currency)? {
+            if current_ask > ceiling {
+                return Ok(TradeSignal::Hold {
+                    spread_threshold: None,
+                    spread_ratio: None,
+                    confidence: None,
+                    position_fraction: None,
+                    metadata: Value::Null,
+                    reason: Some(format!("上限価格超え:[{} > {}]、積立見送り", current_ask, ceiling))
+                });
+            }
+        }
+
+        let a_spread_ratio = ((current_ask - current_bid) / current_bid) * 100.0;
+        let a_spread_threshold = models::ticker::get_dynamic_spread_threshold(conn, currency).await?;
+        if a_spread_ratio > a_spread_threshold {
+            return Ok(TradeSignal::Hold {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence: None,
+                position_fraction: None,
+                metadata: Value::Null,
+                reason: Some("スプレッド負け".to_string())
+            });
+        }
+
+        // N日平均よりX%以上安ければ、押し目として購入額を増やす
+        let since = now - Duration::days(dip_days as i64);
+        let average = models::ticker::Ticker::average_since(conn, currency, since)?;
+        let (amount, reason) = match average {
+            Some(average) if current_ask <= average * (1.0 - dip_pct / 100.0) => (
+                jpy_amount * dip_multiplier,
+                format!(
+                    "積立(押し目x{}): {:.2} <= {}日平均{:.2}の-{}%",
+                    dip_multiplier, current_ask, dip_days, average, dip_pct
+                ),
+            ),
+            _ => (jpy_amount, "積立".to_string()),
+        };
+
+        Ok(TradeSignal::Contribution {
+            spread_threshold: Some(a_spread_threshold),
+            spread_ratio: Some(a_spread_ratio),
+            metadata: json!({"dca_base_jpy": jpy_amount, "dca_dip_average": average}),
+            amount,
+            reason: Some(format!("{}、{}JPY分{}を購入", reason, amount, currency))
+        })
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use diesel::prelude::*;
+use log::{info, error};
+
+use crate::{
+    config,
+    error::AppError,
+    models::signal_vote::NewSignalVote,
+    strategies::{
+        registry,
+        strategy_trait::Strategy,
+        trade_signal::TradeSignal,
+    },
+};
+
+/*
+ * [strategy]
+ * 複数の戦略を動かして、それぞれのTradeSignalを投票として合算する。
+ *
+ * weighted:  買い(売り)の重みの合計が、全メンバーの重みの合計のENSEMBLE_THRESHOLD以上なら買い(売り)
+ * unanimous: 全メンバーが同じ判断の時だけ買い(売り)
+ *
+ * 売却量は、売りに投票したメンバーの売却量の重み付き平均。
+ * 判断に失敗したメンバーは棄権(vote=error)として、残りのメンバーで合算する。
+ * 各メンバーの投票はsignal_votesに、注文(orders)と紐付けて保存する。
+ *
+ * [envの設定]
+ * ENSEMBLE_MEMBERS=ma_optimizer:2.0,rsi:1.0,macd:1.0 (戦略名:重み)
+ * ENSEMBLE_MODE=weighted (weighted or unanimous)
+ * ENSEMBLE_THRESHOLD=0.5
+ * ENSEMBLE_MEMBERS_BTC=macd:1.0,rsi:1.0 (通貨毎に上書き、任意)
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnsembleMode {
+    Weighted,
+    Unanimous,
+}
+
+impl FromStr for EnsembleMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "weighted" => Ok(EnsembleMode::Weighted),
+            "unanimous" => Ok(EnsembleMode::Unanimous),
+            other => Err(format!("Invalid mode: {}", other)),
+        }
+    }
+}
+
+pub struct EnsembleMember {
+    pub name: String,
+    pub weight: f64,
+    pub strategy: Box<dyn Strategy + Send + Sync>,
+}
+
+pub struct EnsembleStrategy {
+    pub members: Vec<EnsembleMember>,
+    pub mode: EnsembleMode,
+    pub threshold: f64,
+    votes: Mutex<HashMap<String, Vec<NewSignalVote>>>,
+}
+
+impl EnsembleStrategy {
+    pub fn new(members: Vec<EnsembleMember>, mode: EnsembleMode, threshold: f64) -> Self {
+        Self {
+            members,
+            mode,
+            threshold,
+            votes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env(currency: &str) -> Result<Self, AppError> {
+        let mode = config::parse_param("ENSEMBLE_MODE", currency, "weighted")?;
+        let threshold = config::parse_param("ENSEMBLE_THRESHOLD", currency, "0.5")?;
+        let entries: String = config::require_param("ENSEMBLE_MEMBERS", currency)?;
+
+        let mut members = Vec::new();
+        for entry in entries.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            let (name, weight) = match entry.split_once(':') {
+                Some((name, weight)) => (
+                    name,
+                    weight.trim().parse::<f64>()
+                        .map_err(|e| AppError::InvalidData(format!("Parse error: ENSEMBLE_MEMBERS: {}: {}", entry, e)))?,
+                ),
+                None => (entry, 1.0),
+            };
+
+            members.push(EnsembleMember {
+                name: name.to_string(),
+                weight,
+                strategy: member_strategy(name, currency)?,
+            });
+        }
+
+        if members.is_empty() {
+            return Err(AppError::InvalidData("ENSEMBLE_MEMBERS is empty".to_string()));
+        }
+
+        Ok(Self::new(members, mode, threshold))
+    }
+
+    /*
+     * メンバーの(重み, シグナル)から、アンサンブルとしてのシグナルを決める。
+     */
+    pub fn combine(&self, signals: &[(f64, TradeSignal)]) -> TradeSignal {
+        let total_weight: f64 = signals.iter().map(|(w, _)| w).sum();
+        let weight_of = |order_type: &str| -> f64 {
+            signals.iter()
+                .filter(|(_, s)| s.order_type() == order_type)
+                .map(|(w, _)| w)
+                .sum()
+        };
+        let buy_weight = weight_of("market_buy");
+        let sell_weight = weight_of("market_sell");
+
+        let (spread_threshold, spread_ratio) = signals.iter()
+            .map(|(_, s)| s.spread())
+            .find(|(threshold, _)| threshold.is_some())
+            .unwrap_or((None, None));
+
+        let tally = format!(
+            "{:?} buy={:.2} sell={:.2} total={:.2}",
+            self.mode, buy_weight, sell_weight, total_weight
+        );
+        let metadata = json!({
+            "ensemble_mode": format!("{:?}", self.mode),
+            "buy_weight": buy_weight,
+            "sell_weight": sell_weight,
+            "total_weight": total_weight,
+        });
+        // 賛成した重みの割合を確信度とする
+        let share = |weight: f64| if total_weight > 0.0 { Some(weight / total_weight) } else { None };
+
+        if signals.iter().all(|(_, s)| s.order_type() == "insufficient_data") {
+            return TradeSignal::InsufficientData {
+                spread_threshold,
+                spread_ratio,
+                confidence: None,
+                position_fraction: None,
+                metadata: metadata.clone(),
+                reason: Some(format!("全メンバーがデータ不足: {}", tally))
+            };
+        }
+
+        let passes = |weight: f64| match self.mode {
+            EnsembleMode::Weighted => total_weight > 0.0 && weight / total_weight >= self.threshold,
+            EnsembleMode::Unanimous => weight == total_weight,
+        };
+
+        if buy_weight > sell_weight && passes(buy_weight) {
+            // 0.0の仮値をセット
+            // すべてjpyで購入なので、呼び出し元で他購入通貨とのバランスを計算して再セットする。
+            TradeSignal::MarcketBuy {
+                spread_threshold,
+                spread_ratio,
+                confidence: share(buy_weight),
+                position_fraction: None,
+                metadata: metadata.clone(),
+                amount: 0.0,
+                reason: Some(format!("アンサンブル買い: {}", tally))
+            }
+        } else if sell_weight > buy_weight && passes(sell_weight) {
+            let amount = signals.iter()
+                .filter(|(_, s)| s.order_type() == "market_sell")
+                .map(|(w, s)| w * s.amount())
+                .sum::<f64>() / sell_weight;
+
+            TradeSignal::MarcketSell {
+                spread_threshold,
+                spread_ratio,
+                confidence: share(sell_weight),
+                position_fraction: None,
+                metadata: metadata.clone(),
+                amount,
+                reason: Some(format!("アンサンブル売り: {}", tally))
+            }
+        } else {
+            TradeSignal::Hold {
+                spread_threshold,
+                spread_ratio,
+                confidence: None,
+                position_fraction: None,
+                metadata: metadata.clone(),
+                reason: Some(format!("アンサンブル見送り: {}", tally))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for EnsembleStrategy {
+    async fn determine_trade_signal(
+        &self,
+        conn: &mut PgConnection,
+        currency: &str,
+        current_bid: f64,
+        current_ask: f64,
+        crypto_balance: f64,
+    ) -> Result<TradeSignal, AppError> {
+        let mut signals = Vec::new();
+        let mut votes = Vec::new();
+
+        for member in self.members.iter() {
+            let signal = match member.strategy
+                .determine_trade_signal(conn, currency, current_bid, current_ask, crypto_balance)
+                .await
+            {
+                Ok(signal) => signal,
+                Err(e) => {
+                    error!("#- [{}] {}({}): 判断に失敗したため棄権: {}", currency, member.name, member.weight, e);
+                    votes.push(NewSignalVote {
+                        order_id: 0,
+                        strategy: member.name.clone(),
+                        vote: "error".to_string(),
+                        weight: member.weight,
+                        reason: Some(e.to_string()),
+                    });
+                    continue;
+                },
+            };
+
+            info!("#- [{}] {}({}): {} {:?}", currency, member.name, member.weight, signal.order_type(), signal.reason());
+            votes.push(NewSignalVote {
+                order_id: 0,
+                strategy: member.name.clone(),
+                vote: signal.order_type().to_string(),
+                weight: member.weight,
+                reason: signal.reason().map(|r| r.to_string()),
+            });
+            signals.push((member.weight, signal));
+        }
+
+        if signals.is_empty() {
+            return Err(AppError::InvalidData(format!("[{}] 全メンバーの判断に失敗しました", currency)));
+        }
+
+        if let Ok(mut all_votes) = self.votes.lock() {
+            all_votes.insert(currency.to_string(), votes);
+        }
+
+        Ok(self.combine(&signals))
+    }
+
+    fn take_votes(&self, currency: &str) -> Vec<NewSignalVote> {
+        self.votes
+            .lock()
+            .ok()
+            .and_then(|mut votes| votes.remove(currency))
+            .unwrap_or_default()
+    }
+}
+
+// アンサンブルの入れ子は無限に組み立ててしまうので不可
+fn member_strategy(name: &str, currency: &str) -> Result<Box<dyn Strategy + Send + Sync>, AppError> {
+    if name == "ensemble" {
+        return Err(AppError::InvalidData("ensemble cannot be an ensemble member".to_string()));
+    }
+
+    let entry = registry::find(name)?;
+    (entry.build)(currency)
+}
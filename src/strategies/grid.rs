@@ -0,0 +1,221 @@
+use std::env;
+use dotenvy::dotenv;
+
+use crate::{
+    error::AppError,
+    models::grid::{Grid, GridLevel, NewGridLevel},
+    config,
+};
+
+/*
+ * [strategy]
+ * レンジ相場向けのグリッドトレード。
+ * lower〜upperを等間隔にlevels本に分け、現在価格より下に指値の買い、上に指値の売りを並べる。
+ * 各レベルの価格は、取引所に拒否されないように通貨の呼値(tick)に丸める。
+ * 買いが約定したら1つ上のレベルに売りを、売りが約定したら1つ下のレベルに買いを出し直す。
+ *
+ * 成行のTradeSignalではなく指値を複数出すので、Strategyトレイトではなく
+ * repositories::grid::runから呼び出す。グリッドの状態はgrids / grid_levelsに保存する。
+ *
+ * [cron]
+ * 5分毎に、cargo run --bin gridを実行して、約定の反映と指値の出し直し
+ *
+ * [envの設定]
+ * GRID_CURRENCIES=xrp,eth
+ * GRID_LOWER_XRP=80
+ * GRID_UPPER_XRP=100
+ * GRID_LEVELS_XRP=11
+ * GRID_ORDER_AMOUNT_XRP=10 (1レベルあたりの仮想通貨の量)
+ * GRID_TICK_XRP=0.001 (呼値。未設定なら1円)
+ */
+
+pub struct GridStrategy;
+
+#[derive(Debug, Clone)]
+pub struct GridConfig {
+    pub currency: String,
+    pub lower: f64,
+    pub upper: f64,
+    pub levels: i32,
+    pub order_amount: f64,
+    pub tick: f64,
+}
+
+impl GridConfig {
+    pub fn currencies() -> Vec<String> {
+        dotenv().ok();
+
+        env::var("GRID_CURRENCIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|c| c.trim().to_lowercase())
+            .filter(|c| !c.is_empty())
+            .collect()
+    }
+
+    pub fn from_env(currency: &str) -> Result<Self, AppError> {
+        // 範囲と量は通貨毎に必須なので、デフォルトなし
+        let config = Self {
+            currency: currency.to_string(),
+            lower: config::require_param("GRID_LOWER", currency)?,
+            upper: config::require_param("GRID_UPPER", currency)?,
+            levels: config::require_param("GRID_LEVELS", currency)?,
+            order_amount: config::require_param("GRID_ORDER_AMOUNT", currency)?,
+            tick: config::parse_param("GRID_TICK", currency, "1")?,
+        };
+
+        if config.levels < 2 || config.order_amount <= 0.0 || config.lower >= config.upper || config.tick <= 0.0 {
+            return Err(AppError::InvalidData(format!("Invalid grid config: {:?}", config)));
+        }
+
+        // 丸めで隣のレベルと同じ価格にならないように
+        if (config.upper - config.lower) / ((config.levels - 1) as f64) < config.tick {
+            return Err(AppError::InvalidData(format!("Grid step is smaller than the tick: {:?}", config)));
+        }
+
+        Ok(config)
+    }
+
+    // 保存済みのグリッドが、今の設定と同じか
+    pub fn matches(&self, grid: &Grid) -> bool {
+        grid.lower_price == self.lower
+            && grid.upper_price == self.upper
+            && grid.levels == self.levels
+            && grid.order_amount == self.order_amount
+    }
+}
+
+impl GridStrategy {
+    pub fn level_prices(lower: f64, upper: f64, levels: i32, tick: f64) -> Vec<f64> {
+        let step = (upper - lower) / (levels - 1) as f64;
+        (0..levels).map(|i| round_to_tick(lower + step * i as f64, tick)).collect()
+    }
+
+    /*
+     * 新しいグリッドのレベルを作る。
+     * 現在価格に一番近いレベルは空けておき、下は買い、上は売りをpendingにする。
+     */
+    pub fn initial_levels(config: &GridConfig, current_price: f64) -> Vec<NewGridLevel> {
+        let prices = Self::level_prices(config.lower, config.upper, config.levels, config.tick);
+        let nearest = prices.iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| (*a - current_price).abs().total_cmp(&(*b - current_price).abs()))
+            .map(|(i, _)| i);
+
+        prices.iter()
+            .enumerate()
+            .map(|(i, price)| {
+                let side = if *price < current_price { "buy" } else { "sell" };
+                let state = if Some(i) == nearest { "idle" } else { "pending" };
+                NewGridLevel {
+                    grid_id: 0,
+                    level_index: i as i32,
+                    price: *price,
+                    side: side.to_string(),
+                    state: state.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    /*
+     * levels[filled]の注文が約定した時に、次に指値を出すレベルのindexとsideを返す。
+     * 買いが約定したら1つ上に売り、売りが約定したら1つ下に買い。端の場合はNone。
+     */
+    pub fn rearm_target(levels: &[GridLevel], filled: usize) -> Option<(usize, &'static str)> {
+        match levels.get(filled)?.side.as_str() {
+            "buy" if filled + 1 < levels.len() => Some((filled + 1, "sell")),
+            "sell" if filled > 0 => Some((filled - 1, "buy")),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * pendingのレベルのうち、今指値を出せるものを返す。
+ * 売りは、指値で拘束されていない仮想通貨の残高からorder_amountずつ引き当て、足りなくなったら出さない。
+ */
+pub fn placeable_levels(levels: &[GridLevel], order_amount: f64, crypto_available: f64) -> Vec<&GridLevel> {
+    let mut remaining = crypto_available;
+
+    levels.iter()
+        .filter(|level| level.state == "pending")
+        .filter(|level| {
+            if level.side != "sell" {
+                return true;
+            }
+            if remaining < order_amount {
+                return false;
+            }
+            remaining -= order_amount;
+            true
+        })
+        .collect()
+}
+
+/*
+ * priceを一番近いtickの倍数に丸める。
+ * 81.8181...のような価格や、0.1 * 3 = 0.30000000000000004のような誤差を、tickの桁数で切り揃える。
+ */
+pub fn round_to_tick(price: f64, tick: f64) -> f64 {
+    let decimals = tick.to_string().split('.').nth(1).map(|d| d.len()).unwrap_or(0) as i32;
+    let factor = 10f64.powi(decimals);
+
+    ((price / tick).round() * tick * factor).round() / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_prices_are_rounded_to_the_tick() {
+        assert_eq!(
+            GridStrategy::level_prices(80.0, 100.0, 12, 0.001),
+            vec![80.0, 81.818, 83.636, 85.455, 87.273, 89.091, 90.909, 92.727, 94.545, 96.364, 98.182, 100.0],
+        );
+        assert_eq!(GridStrategy::level_prices(10_000_000.0, 10_100_000.0, 4, 1.0)[1], 10_033_333.0);
+    }
+
+    fn level(level_index: i32, side: &str, state: &str) -> GridLevel {
+        GridLevel {
+            id: level_index,
+            grid_id: 1,
+            level_index,
+            price: 100.0 + level_index as f64,
+            side: side.to_string(),
+            state: state.to_string(),
+            exchange_order_id: None,
+            updated_at: chrono::NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn placeable_levels_skip_sells_beyond_the_crypto_balance() {
+        let levels = vec![
+            level(0, "buy", "pending"),
+            level(1, "sell", "pending"),
+            level(2, "sell", "armed"),
+            level(3, "sell", "pending"),
+            level(4, "sell", "pending"),
+        ];
+
+        let placeable: Vec<i32> = placeable_levels(&levels, 10.0, 25.0).iter().map(|l| l.level_index).collect();
+        assert_eq!(placeable, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn placeable_levels_keep_buys_without_crypto() {
+        let levels = vec![level(0, "buy", "pending"), level(1, "sell", "pending")];
+
+        let placeable: Vec<i32> = placeable_levels(&levels, 10.0, 0.0).iter().map(|l| l.level_index).collect();
+        assert_eq!(placeable, vec![0]);
+    }
+
+    #[test]
+    fn round_to_tick_removes_float_noise() {
+        assert_eq!(round_to_tick(0.30000000000000004, 0.1), 0.3);
+        assert_eq!(round_to_tick(81.81818181, 0.5), 82.0);
+        assert_eq!(round_to_tick(12345.6, 5.0), 12345.0);
+    }
+}
@@ -0,0 +1,143 @@
+/*
+ * 戦略で使うテクニカル指標の計算。pricesは全て古い順。
+ */
+
+/*
+ * RSI(Cutler's RSI)。直近period本の上昇幅と下落幅の単純平均から計算する。
+ * データがperiod+1本に満たない場合はNone。
+ */
+pub fn rsi(prices: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || prices.len() < period + 1 {
+        return None;
+    }
+
+    let window = &prices[prices.len() - (period + 1)..];
+    let (gain, loss) = window.windows(2).fold((0.0, 0.0), |(gain, loss), w| {
+        let diff = w[1] - w[0];
+        if diff > 0.0 { (gain + diff, loss) } else { (gain, loss - diff) }
+    });
+
+    if gain + loss == 0.0 {
+        return Some(50.0);
+    }
+
+    Some(100.0 * gain / (gain + loss))
+}
+
+/*
+ * 直近period本の単純移動平均。
+ */
+pub fn sma(prices: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || prices.len() < period {
+        return None;
+    }
+
+    let window = &prices[prices.len() - period..];
+    Some(window.iter().sum::<f64>() / period as f64)
+}
+
+/*
+ * SMAの系列。戻り値の長さはprices.len() - period + 1で、末尾がpricesの末尾に対応する。
+ */
+pub fn sma_series(prices: &[f64], period: usize) -> Vec<f64> {
+    if period == 0 || prices.len() < period {
+        return Vec::new();
+    }
+
+    let mut sum: f64 = prices[..period].iter().sum();
+    let mut series = Vec::with_capacity(prices.len() - period + 1);
+    series.push(sum / period as f64);
+
+    for i in period..prices.len() {
+        sum += prices[i] - prices[i - period];
+        series.push(sum / period as f64);
+    }
+
+    series
+}
+
+pub struct Bands {
+    pub lower: f64,
+    pub middle: f64,
+    pub upper: f64,
+}
+
+/*
+ * ボリンジャーバンド。middleはperiod本のSMA、上下はk倍の標準偏差(母標準偏差)。
+ */
+pub fn bollinger(prices: &[f64], period: usize, k: f64) -> Option<Bands> {
+    let middle = sma(prices, period)?;
+
+    let window = &prices[prices.len() - period..];
+    let variance = window.iter().map(|p| (p - middle).powi(2)).sum::<f64>() / period as f64;
+    let width = k * variance.sqrt();
+
+    Some(Bands {
+        lower: middle - width,
+        middle,
+        upper: middle + width,
+    })
+}
+
+/*
+ * EMAの系列。最初のperiod本のSMAを起点にする。
+ * 戻り値の長さはprices.len() - period + 1で、末尾がpricesの末尾に対応する。
+ */
+pub fn ema_series(prices: &[f64], period: usize) -> Vec<f64> {
+    if period == 0 || prices.len() < period {
+        return Vec::new();
+    }
+
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut series = Vec::with_capacity(prices.len() - period + 1);
+    let mut ema = prices[..period].iter().sum::<f64>() / period as f64;
+    series.push(ema);
+
+    for price in prices[period..].iter() {
+        ema = alpha * price + (1.0 - alpha) * ema;
+        series.push(ema);
+    }
+
+    series
+}
+
+pub struct MacdPoint {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+/*
+ * MACDの系列。末尾がpricesの末尾に対応する。
+ * データがslow + signal - 1本に満たない場合は空。
+ */
+pub fn macd_series(prices: &[f64], fast: usize, slow: usize, signal: usize) -> Vec<MacdPoint> {
+    if fast == 0 || fast >= slow {
+        return Vec::new();
+    }
+
+    let fast_ema = ema_series(prices, fast);
+    let slow_ema = ema_series(prices, slow);
+    if slow_ema.is_empty() {
+        return Vec::new();
+    }
+
+    // fast_emaの方が長いので、末尾を揃える
+    let offset = fast_ema.len() - slow_ema.len();
+    let macd_line: Vec<f64> = slow_ema.iter()
+        .enumerate()
+        .map(|(i, slow)| fast_ema[i + offset] - slow)
+        .collect();
+
+    let signal_line = ema_series(&macd_line, signal);
+    let offset = macd_line.len().saturating_sub(signal_line.len());
+
+    signal_line.iter()
+        .enumerate()
+        .map(|(i, signal)| MacdPoint {
+            macd: macd_line[i + offset],
+            signal: *signal,
+            histogram: macd_line[i + offset] - signal,
+        })
+        .collect()
+}
@@ -1,15 +1,20 @@
-use std::env;
+use std::str::FromStr;
+
 use dotenvy::dotenv;
 
 use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Value};
 
 use diesel::prelude::*;
 use diesel::sql_types::{Nullable, Double, Text, Integer};
 
 use crate::{
+    config,
     models,
     error::AppError,
     strategies::{
+        crossover,
         strategy_trait::Strategy,
         trade_signal::TradeSignal,
     },
@@ -17,13 +22,31 @@ use crate::{
 
 /*
  * [strategy]
- * optimized_masテーブルから勝率の高い、ma_shortとma_longを読み込んでcrossoverを計算。
+ * optimized_masテーブルからMA_OPT_OBJECTIVEの指標で選んだ、ma_shortとma_longを読み込んでcrossoverを計算。
+ * 選んだパラメータのMA_OPT_OBJECTIVEの値に優位性がなければ(has_edge)見送る。
  *
  * [cron]
  * 2分毎に、cargo run --bin ticker_fetcherを実行して、tickersに情報を蓄積
  * 15毎に、cargo run --bin orderを実行して、注文
+ * 1時間毎に、cargo run --bin optimized_maを実行して、取引中の通貨毎にパラメータを最適化
  * 
  * [envの設定]
+ * ORDER_INTERVAL_MINUTES=15 (orderのcron間隔。これに最も近いoffsetの最適化結果を使う)
+ * MA_OPT_SHORT_MIN=5
+ * MA_OPT_SHORT_MAX=10
+ * MA_OPT_SHORT_STEP=1
+ * MA_OPT_LONG_GAP=5 (longはshort + MA_OPT_LONG_GAPから)
+ * MA_OPT_LONG_MAX=30
+ * MA_OPT_LONG_STEP=1
+ * MA_OPT_OFFSET_MINUTES=15,30,60 (クロス後、何分後の値動きで勝敗を判定するか)
+ * MA_OPT_OBJECTIVE=expectancy (win_rate, avg_return, profit_factor, expectancy)
+ * MA_OPT_MIN_SAMPLES=10 (これよりクロスが少ない組み合わせは選ばない)
+ * MA_OPT_WALK_FORWARD=false (trueでウォークフォワード検証し、検証期間でも成績が残ったパラメータだけ使う)
+ * MA_OPT_TRAIN_HOURS=72 (ウォークフォワードの学習期間)
+ * MA_OPT_TEST_HOURS=24 (ウォークフォワードの検証期間。この幅ずつずらす)
+ *   (tickersが学習期間+検証期間分ない時はエラー。TICKER_RETENTION_ROWSで保持件数を増やす)
+ * MA_OPT_MIN_OOS_SAMPLES=3 (検証期間のクロスがこれより少なければ採用しない)
+ * (MA_OPT_*は、MA_OPT_SHORT_MAX_BTCのように通貨毎に上書き可)
  * BUY_THRESHOLD_1=20000
  * BUY_RATIO_1=0.9
  * BUY_THRESHOLD_2=50000
@@ -35,6 +58,171 @@ use crate::{
 
 pub struct MaOptimizerStrategy;
 
+// 最適化で試す(short, long)の範囲と、評価するoffset_minutes
+#[derive(Debug, Clone, Serialize)]
+pub struct MaSearchSpace {
+    pub short_min: i32,
+    pub short_max: i32,
+    pub short_step: usize,
+    pub long_gap: i32,
+    pub long_max: i32,
+    pub long_step: usize,
+    pub offsets: Vec<i32>,
+}
+
+// 最適化結果から、どの指標で、どのoffsetのパラメータを選ぶか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaObjective {
+    WinRate,
+    AvgReturn,
+    ProfitFactor,
+    Expectancy,
+}
+
+impl FromStr for MaObjective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "win_rate" => Ok(MaObjective::WinRate),
+            "avg_return" => Ok(MaObjective::AvgReturn),
+            "profit_factor" => Ok(MaObjective::ProfitFactor),
+            "expectancy" => Ok(MaObjective::Expectancy),
+            other => Err(format!("Invalid objective: {}", other)),
+        }
+    }
+}
+
+impl MaObjective {
+    pub fn score_stats(&self, stats: &crossover::CrossStats) -> f64 {
+        match self {
+            MaObjective::WinRate => stats.win_rate_pct,
+            MaObjective::AvgReturn => stats.avg_return_pct,
+            MaObjective::ProfitFactor => stats.profit_factor.unwrap_or(f64::INFINITY),
+            MaObjective::Expectancy => stats.expectancy_pct,
+        }
+    }
+
+    pub fn score(&self, record: &models::optimized_ma::OptimizedMa) -> Option<f64> {
+        self.score_columns(record.win_rate_pct, record.avg_return_pct, record.profit_factor, record.expectancy_pct)
+    }
+
+    // 保存済みの最適化結果(optimized_mas, optimized_macds)の列から
+    pub fn score_columns(
+        &self,
+        win_rate_pct: Option<f64>,
+        avg_return_pct: Option<f64>,
+        profit_factor: Option<f64>,
+        expectancy_pct: Option<f64>,
+    ) -> Option<f64> {
+        match self {
+            MaObjective::WinRate => win_rate_pct,
+            MaObjective::AvgReturn => avg_return_pct,
+            // 負けなしはNULLで保存されているので、最大として扱う
+            MaObjective::ProfitFactor => avg_return_pct.map(|_| profit_factor.unwrap_or(f64::INFINITY)),
+            MaObjective::Expectancy => expectancy_pct,
+        }
+    }
+
+    // 勝率なら50%以上、利益系ならプラス(profit_factorは1超)のものだけを候補にする
+    pub fn has_edge(&self, score: f64) -> bool {
+        match self {
+            MaObjective::WinRate => score >= 50.0,
+            MaObjective::AvgReturn | MaObjective::Expectancy => score > 0.0,
+            MaObjective::ProfitFactor => score > 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MaSelection {
+    pub horizon_minutes: i32,
+    pub objective: MaObjective,
+    pub min_samples: i32,
+    // ウォークフォワードで採用(promoted)されたものだけから選ぶ
+    pub promoted_only: bool,
+}
+
+impl MaSelection {
+    pub fn from_env(currency: &str) -> Result<Self, AppError> {
+        let horizon_minutes = config::parse_param("ORDER_INTERVAL_MINUTES", currency, "15")?;
+        let min_samples = config::parse_param("MA_OPT_MIN_SAMPLES", currency, "10")?;
+        let objective = config::parse_param("MA_OPT_OBJECTIVE", currency, "expectancy")?;
+
+        let promoted_only = config::param("MA_OPT_WALK_FORWARD", currency).unwrap_or_default() == "true";
+
+        Ok(Self { horizon_minutes, objective, min_samples, promoted_only })
+    }
+}
+
+/*
+ * ウォークフォワード検証の設定。
+ * 学習期間で最も成績の良いパラメータを選び、直後の検証期間での成績を記録して、検証期間の幅ずつずらす。
+ */
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MaWalkForward {
+    pub train_hours: i64,
+    pub test_hours: i64,
+    pub min_oos_samples: i32,
+    pub selection: MaSelection,
+}
+
+impl MaWalkForward {
+    // MA_OPT_WALK_FORWARDがtrueでなければNone
+    pub fn from_env(currency: &str) -> Result<Option<Self>, AppError> {
+        let selection = MaSelection::from_env(currency)?;
+        if !selection.promoted_only {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            train_hours: config::parse_param("MA_OPT_TRAIN_HOURS", currency, "72")?,
+            test_hours: config::parse_param::<i64>("MA_OPT_TEST_HOURS", currency, "24")?.max(1),
+            min_oos_samples: config::parse_param("MA_OPT_MIN_OOS_SAMPLES", currency, "3")?,
+            selection,
+        }))
+    }
+}
+
+impl MaSearchSpace {
+    pub fn from_env(currency: &str) -> Result<Self, AppError> {
+        let offsets = config::param("MA_OPT_OFFSET_MINUTES", currency)
+            .unwrap_or("15".to_string())
+            .split(',')
+            .map(|o| o.trim().parse::<i32>())
+            .collect::<Result<Vec<i32>, _>>()
+            .map_err(|e| AppError::InvalidData(format!("Parse error: MA_OPT_OFFSET_MINUTES: {}", e)))?;
+
+        let space = Self {
+            short_min: config::parse_param("MA_OPT_SHORT_MIN", currency, "5")?,
+            short_max: config::parse_param("MA_OPT_SHORT_MAX", currency, "10")?,
+            short_step: config::parse_param::<usize>("MA_OPT_SHORT_STEP", currency, "1")?.max(1),
+            long_gap: config::parse_param("MA_OPT_LONG_GAP", currency, "5")?,
+            long_max: config::parse_param("MA_OPT_LONG_MAX", currency, "30")?,
+            long_step: config::parse_param::<usize>("MA_OPT_LONG_STEP", currency, "1")?.max(1),
+            offsets,
+        };
+
+        if space.short_min < 1 || space.long_gap < 1 {
+            return Err(AppError::InvalidData("MA_OPT_SHORT_MIN and MA_OPT_LONG_GAP must be >= 1".to_string()));
+        }
+
+        Ok(space)
+    }
+
+    pub fn combinations(&self) -> Vec<(i32, i32)> {
+        (self.short_min..=self.short_max)
+            .step_by(self.short_step)
+            .flat_map(|short| {
+                ((short + self.long_gap)..=self.long_max)
+                    .step_by(self.long_step)
+                    .map(move |long| (short, long))
+            })
+            .collect()
+    }
+}
+
 #[derive(QueryableByName)]
 #[allow(dead_code)]
 pub struct AvgResult {
@@ -55,38 +243,47 @@ impl Strategy for MaOptimizerStrategy {
     ) -> Result<TradeSignal, AppError> {
         dotenv().ok();
 
-        let ma_border_threshold_ratio = env::var("MA_BORDER_THRESHOLD_RATIO")
-            .unwrap_or("60.0".to_string())
-            .parse::<f64>()
-            .map_err(|e| AppError::InvalidData(format!("Parse error: {}", e)))?;
+        let selection = MaSelection::from_env(currency)?;
+        let objective = selection.objective;
 
-        let (sma_short, sma_long, win_rate_pct) = match models::optimized_ma::OptimizedMa::find_best_for_ma(conn, currency)? {
-            Some((short, long, win_rate)) if win_rate >= ma_border_threshold_ratio => (short, long, win_rate),
-            Some((short, long, win_rate)) => {
-                let reason = format!("クロスの勝率:[{}% < {}%]、見送り", win_rate, ma_border_threshold_ratio);
+        let best = models::optimized_ma::OptimizedMa::find_best_for_ma(conn, currency, &selection)?
+            .map(|best| (best.short_ma, best.long_ma, best.win_rate_pct.unwrap_or(0.0), best.run_id, objective.score(&best)));
+        let (sma_short, sma_long, win_rate_pct, run_id) = match best {
+            Some((short, long, win_rate, run_id, Some(score))) if objective.has_edge(score) => (short, long, win_rate, run_id),
+            Some((short, long, win_rate, run_id, score)) => {
+                let reason = format!("{:?}:[{:?}]に優位性なし、見送り", objective, score);
                 return Ok(
                     TradeSignal::Hold { 
                         spread_threshold: None,
                         spread_ratio: None,
-                        ma_short: Some(short),
-                        ma_long: Some(long),
-                        ma_win_rate: Some(win_rate),
+                        confidence: Some(win_rate / 100.0),
+                        position_fraction: None,
+                        metadata: json!({"ma_short": short, "ma_long": long, "ma_win_rate": win_rate, "optimization_run_id": run_id}),
                         reason: Some(reason) 
                     });
             },
             None => return Ok(TradeSignal::Hold { 
                 spread_threshold: None,
                 spread_ratio: None,
-                ma_short: None,
-                ma_long: None,
-                ma_win_rate: None,
+                confidence: None,
+                position_fraction: None,
+                metadata: Value::Null,
                 reason: Some("ベストなmaなし".to_string()) 
             }),
         };
 
-        let sell_ratio = env::var("SELL_RATIO")?.parse::<f64>().unwrap();
+        let sell_ratio: f64 = config::require_param("SELL_RATIO", currency)?;
+
+        // 最適化結果の勝率を確信度とする
+        let confidence = Some(win_rate_pct / 100.0);
+        let metadata = json!({
+            "ma_short": sma_short,
+            "ma_long": sma_long,
+            "ma_win_rate": win_rate_pct,
+            "optimization_run_id": run_id,
+        });
     
-        let periods = vec![sma_short, sma_long];
+        let periods = [sma_short, sma_long];
         let mut results: Vec<Option<AvgResult>> = Vec::new();
     
         for period in periods.iter() {
@@ -108,8 +305,8 @@ impl Strategy for MaOptimizerStrategy {
             results.push(ma);
         }
     
-        let ma_short_avg = results[0].as_ref().map(|r| r.avg).flatten();
-        let ma_long_avg = results[1].as_ref().map(|r| r.avg).flatten();
+        let ma_short_avg = results[0].as_ref().and_then(|r| r.avg);
+        let ma_long_avg = results[1].as_ref().and_then(|r| r.avg);
     
         let a_spread_ratio = ((current_ask - current_bid) / current_bid) * 100.0;
         let a_spread_threshold = models::ticker::get_dynamic_spread_threshold(conn, currency).await?;
@@ -117,9 +314,9 @@ impl Strategy for MaOptimizerStrategy {
             return Ok(TradeSignal::Hold { 
                 spread_threshold: Some(a_spread_threshold),
                 spread_ratio: Some(a_spread_ratio),
-                ma_short: Some(sma_short),
-                ma_long: Some(sma_long),
-                ma_win_rate: Some(win_rate_pct),
+                confidence,
+                position_fraction: None,
+                metadata: metadata.clone(),
                 reason: Some("スプレッド負け".to_string()) 
             });
         }
@@ -133,9 +330,9 @@ impl Strategy for MaOptimizerStrategy {
                     Ok(TradeSignal::MarcketBuy { 
                         spread_threshold: Some(a_spread_threshold),
                         spread_ratio: Some(a_spread_ratio),
-                        ma_short: Some(sma_short),
-                        ma_long: Some(sma_long),
-                        ma_win_rate: Some(win_rate_pct),
+                        confidence,
+                        position_fraction: None,
+                        metadata: metadata.clone(),
                         amount: 0.0, 
                         reason: Some(reason) 
                     })
@@ -150,9 +347,9 @@ impl Strategy for MaOptimizerStrategy {
                         return Ok(TradeSignal::Hold { 
                             spread_threshold: Some(a_spread_threshold),
                             spread_ratio: Some(a_spread_ratio),
-                            ma_short: Some(sma_short),
-                            ma_long: Some(sma_long),
-                            ma_win_rate: Some(win_rate_pct),
+                            confidence,
+                            position_fraction: None,
+                            metadata: metadata.clone(),
                             reason: Some(reason) 
                         })
                     }
@@ -160,9 +357,9 @@ impl Strategy for MaOptimizerStrategy {
                     Ok(TradeSignal::MarcketSell { 
                         spread_threshold: Some(a_spread_threshold),
                         spread_ratio: Some(a_spread_ratio),
-                        ma_short: Some(sma_short),
-                        ma_long: Some(sma_long),
-                        ma_win_rate: Some(win_rate_pct),
+                        confidence,
+                        position_fraction: None,
+                        metadata: metadata.clone(),
                         amount, reason: Some(reason) 
                     })
     
@@ -174,9 +371,9 @@ impl Strategy for MaOptimizerStrategy {
                     Ok(TradeSignal::Hold { 
                         spread_threshold: Some(a_spread_threshold),
                         spread_ratio: Some(a_spread_ratio),
-                        ma_short: Some(sma_short),
-                        ma_long: Some(sma_long),
-                        ma_win_rate: Some(win_rate_pct),
+                        confidence,
+                        position_fraction: None,
+                        metadata: metadata.clone(),
                         reason: Some(reason) 
                     })
                 }
@@ -184,9 +381,9 @@ impl Strategy for MaOptimizerStrategy {
             _ => Ok(TradeSignal::InsufficientData { 
                 spread_threshold: None,
                 spread_ratio: None,
-                ma_short: None,
-                ma_long: None,
-                ma_win_rate: None,
+                confidence: None,
+                position_fraction: None,
+                metadata: Value::Null,
                 reason: Some("データ不足".to_string()) 
             })
         }
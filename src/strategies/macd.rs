@@ -0,0 +1,366 @@
+use dotenvy::dotenv;
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use diesel::prelude::*;
+
+use crate::{
+    config,
+    models,
+    error::AppError,
+    strategies::{
+        crossover::{self, Cross, CrossStats},
+        indicator,
+        ma_optimizer::MaObjective,
+        strategy_trait::Strategy,
+        trade_signal::TradeSignal,
+    },
+};
+
+/*
+ * [strategy]
+ * MACDとシグナルラインのクロスで売買する。
+ * ノイズのクロスを避けるため、クロス後にヒストグラムが一定以上(価格比%)に開いたものだけ採用する。
+ * fast/slow/signalは、最新の最適化(optimized_macds)からMACD_OPT_OBJECTIVEの指標で選んだものがあればそれを使い、
+ * なければenvの値を使う。最適化結果は、サンプル数がMACD_OPT_MIN_SAMPLES以上で優位性(has_edge)のあるものだけを使う。
+ *
+ * [cron]
+ * 2分毎に、cargo run --bin ticker_fetcherを実行して、tickersに情報を蓄積
+ * 15毎に、cargo run --bin orderを実行して、注文
+ * 1時間毎に、cargo run --bin optimized_macdを実行して、取引中の通貨毎にパラメータを最適化
+ *
+ * [envの設定]
+ * MACD_FAST=12
+ * MACD_SLOW=26
+ * MACD_SIGNAL=9
+ * MACD_MIN_HISTOGRAM_PCT=0.01
+ * MACD_CROSS_LOOKBACK=8 (クロスから何本以内なら有効とするか。order間隔 / ticker間隔が目安)
+ * ORDER_INTERVAL_MINUTES=15 (orderのcron間隔。これに最も近いoffsetの最適化結果を使う)
+ * MACD_OPT_FAST_MIN=6 / MACD_OPT_FAST_MAX=14 / MACD_OPT_FAST_STEP=2
+ * MACD_OPT_SLOW_MIN=20 / MACD_OPT_SLOW_MAX=32 / MACD_OPT_SLOW_STEP=3
+ * MACD_OPT_SIGNAL_MIN=5 / MACD_OPT_SIGNAL_MAX=11 / MACD_OPT_SIGNAL_STEP=2
+ * MACD_OPT_OFFSET_MINUTES=15 (クロス後、何分後の値動きで勝敗を判定するか。カンマ区切りで複数可)
+ * MACD_OPT_OBJECTIVE=expectancy (win_rate, avg_return, profit_factor, expectancy)
+ * MACD_OPT_MIN_SAMPLES=10 (これよりクロスが少ない組み合わせは選ばない)
+ * (MACD_OPT_*は、MACD_OPT_FAST_MAX_BTCのように通貨毎に上書き可)
+ * SELL_RATIO=0.4
+ */
+
+pub struct MacdStrategy;
+
+#[derive(Debug, Clone, Copy)]
+pub struct MacdParams {
+    pub fast: usize,
+    pub slow: usize,
+    pub signal: usize,
+    pub min_histogram_pct: f64,
+    pub lookback: usize,
+}
+
+impl MacdParams {
+    pub fn from_env(currency: &str) -> Result<Self, AppError> {
+        Ok(Self {
+            fast: config::parse_param("MACD_FAST", currency, "12")?,
+            slow: config::parse_param("MACD_SLOW", currency, "26")?,
+            signal: config::parse_param("MACD_SIGNAL", currency, "9")?,
+            min_histogram_pct: config::parse_param("MACD_MIN_HISTOGRAM_PCT", currency, "0.01")?,
+            lookback: config::parse_param("MACD_CROSS_LOOKBACK", currency, "8")?,
+        })
+    }
+
+    // EMAが十分収束するように、必要本数の3倍を読み込む
+    pub fn history_len(&self) -> usize {
+        (self.slow + self.signal) * 3
+    }
+}
+
+// 最適化結果から、どの指標で、どのoffsetのパラメータを選ぶか
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MacdSelection {
+    pub horizon_minutes: i32,
+    pub objective: MaObjective,
+    pub min_samples: i32,
+}
+
+impl MacdSelection {
+    pub fn from_env(currency: &str) -> Result<Self, AppError> {
+        Ok(Self {
+            horizon_minutes: config::parse_param("ORDER_INTERVAL_MINUTES", currency, "15")?,
+            objective: config::parse_param("MACD_OPT_OBJECTIVE", currency, "expectancy")?,
+            min_samples: config::parse_param("MACD_OPT_MIN_SAMPLES", currency, "10")?,
+        })
+    }
+}
+
+// 最適化で試す(fast, slow, signal)の範囲と、評価するoffset_minutes
+#[derive(Debug, Clone, Serialize)]
+pub struct MacdSearchSpace {
+    pub fast: (usize, usize, usize),
+    pub slow: (usize, usize, usize),
+    pub signal: (usize, usize, usize),
+    pub offsets: Vec<i32>,
+}
+
+impl MacdSearchSpace {
+    pub fn from_env(currency: &str) -> Result<Self, AppError> {
+        // (min, max, step)
+        let range = |name: &str, min: &str, max: &str, step: &str| -> Result<(usize, usize, usize), AppError> {
+            Ok((
+                config::parse_param(&format!("MACD_OPT_{}_MIN", name), currency, min)?,
+                config::parse_param(&format!("MACD_OPT_{}_MAX", name), currency, max)?,
+                config::parse_param::<usize>(&format!("MACD_OPT_{}_STEP", name), currency, step)?.max(1),
+            ))
+        };
+
+        let offsets = config::param("MACD_OPT_OFFSET_MINUTES", currency)
+            .unwrap_or("15".to_string())
+            .split(',')
+            .map(|o| o.trim().parse::<i32>())
+            .collect::<Result<Vec<i32>, _>>()
+            .map_err(|e| AppError::InvalidData(format!("Parse error: MACD_OPT_OFFSET_MINUTES: {}", e)))?;
+
+        let space = Self {
+            fast: range("FAST", "6", "14", "2")?,
+            slow: range("SLOW", "20", "32", "3")?,
+            signal: range("SIGNAL", "5", "11", "2")?,
+            offsets,
+        };
+
+        if space.fast.0 < 1 || space.signal.0 < 1 {
+            return Err(AppError::InvalidData("MACD_OPT_FAST_MIN and MACD_OPT_SIGNAL_MIN must be >= 1".to_string()));
+        }
+
+        Ok(space)
+    }
+
+    // fast < slowの組み合わせだけ
+    pub fn combinations(&self) -> Vec<(usize, usize, usize)> {
+        let steps = |(min, max, step): (usize, usize, usize)| (min..=max).step_by(step);
+
+        steps(self.fast)
+            .flat_map(|fast| steps(self.slow).filter(move |slow| fast < *slow).map(move |slow| (fast, slow)))
+            .flat_map(|(fast, slow)| steps(self.signal).map(move |signal| (fast, slow, signal)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MacdSweepResult {
+    pub fast: usize,
+    pub slow: usize,
+    pub signal: usize,
+    pub offset_minutes: i32,
+    pub stats: CrossStats,
+}
+
+/*
+ * 価格系列に対して、search_spaceの組み合わせ毎に確定したクロスを求め、
+ * offset分後の値動きの成績を、spread_pctを払った後の期待値も含めて計算する。
+ * クロスが一度もない組み合わせは返さない。
+ * min_histogram_pctとlookbackはbaseの値を使う。
+ */
+pub fn sweep(
+    series: &[(NaiveDateTime, f64)],
+    base: &MacdParams,
+    search_space: &MacdSearchSpace,
+    spread_pct: f64,
+) -> Vec<MacdSweepResult> {
+    let prices: Vec<f64> = series.iter().map(|(_, p)| *p).collect();
+
+    let mut results = Vec::new();
+    for (fast, slow, signal) in search_space.combinations() {
+        let params = MacdParams { fast, slow, signal, ..*base };
+
+        let histogram: Vec<f64> = indicator::macd_series(&prices, fast, slow, signal)
+            .iter()
+            .map(|p| p.histogram)
+            .collect();
+        let base_index = prices.len() - histogram.len();
+        let crosses = confirmed_crosses(&histogram, &prices, base_index, &params);
+
+        for offset in search_space.offsets.iter() {
+            let returns = crossover::returns(series, &crosses, *offset as i64);
+            let Some(stats) = CrossStats::from_returns(&returns, spread_pct) else {
+                continue;
+            };
+
+            results.push(MacdSweepResult { fast, slow, signal, offset_minutes: *offset, stats });
+        }
+    }
+
+    results
+}
+
+#[async_trait]
+impl Strategy for MacdStrategy {
+    async fn determine_trade_signal(
+        &self,
+        conn: &mut PgConnection,
+        currency: &str,
+        current_bid: f64,
+        current_ask: f64,
+        crypto_balance: f64,
+    ) -> Result<TradeSignal, AppError> {
+        dotenv().ok();
+
+        let sell_ratio: f64 = config::require_param("SELL_RATIO", currency)?;
+
+        let selection = MacdSelection::from_env(currency)?;
+
+        let mut params = MacdParams::from_env(currency)?;
+        let mut win_rate_pct = None;
+        let mut run_id = None;
+        if let Some(best) = models::optimized_macd::OptimizedMacd::find_best(conn, currency, &selection)? {
+            params.fast = best.fast_period as usize;
+            params.slow = best.slow_period as usize;
+            params.signal = best.signal_period as usize;
+            win_rate_pct = best.win_rate_pct;
+            run_id = best.run_id;
+        }
+
+        let prices = models::ticker::Ticker::recent_prices(conn, currency, params.history_len() as i64)?;
+        let histogram: Vec<f64> = indicator::macd_series(&prices, params.fast, params.slow, params.signal)
+            .iter()
+            .map(|p| p.histogram)
+            .collect();
+
+        let Some(latest) = histogram.last().copied() else {
+            return Ok(TradeSignal::InsufficientData {
+                spread_threshold: None,
+                spread_ratio: None,
+                confidence: None,
+                position_fraction: None,
+                metadata: Value::Null,
+                reason: Some("データ不足".to_string())
+            });
+        };
+        let metadata = json!({
+            "macd_fast": params.fast,
+            "macd_slow": params.slow,
+            "macd_signal": params.signal,
+            "macd_histogram": latest,
+            "macd_win_rate": win_rate_pct,
+            "optimization_run_id": run_id,
+        });
+        // 最適化結果を使う時は、その勝率を確信度とする
+        let confidence = win_rate_pct.map(|w| w / 100.0);
+
+        let a_spread_ratio = ((current_ask - current_bid) / current_bid) * 100.0;
+        let a_spread_threshold = models::ticker::get_dynamic_spread_threshold(conn, currency).await?;
+        if a_spread_ratio > a_spread_threshold {
+            return Ok(TradeSignal::Hold {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence,
+                position_fraction: None,
+                metadata: metadata.clone(),
+                reason: Some("スプレッド負け".to_string())
+            });
+        }
+
+        let macd_text = format!(
+            "MACD({},{},{}) histogram={:.4}",
+            params.fast, params.slow, params.signal, latest
+        );
+
+        // 直近lookback本以内に、ヒストグラムの開きで確定したクロスがあるか
+        let base_index = prices.len() - histogram.len();
+        let recent_cross = confirmed_crosses(&histogram, &prices, base_index, &params)
+            .into_iter()
+            .last()
+            .filter(|cross| cross.index + params.lookback >= prices.len());
+
+        match recent_cross {
+            Some(cross) if cross.golden => {
+                // 0.0の仮値をセット
+                // すべてjpyで購入なので、呼び出し元で他購入通貨とのバランスを計算して再セットする。
+                let reason = format!("{}、シグナル上抜け、jpy_amount分{}を購入", macd_text, currency);
+                Ok(TradeSignal::MarcketBuy {
+                    spread_threshold: Some(a_spread_threshold),
+                    spread_ratio: Some(a_spread_ratio),
+                    confidence,
+                    position_fraction: None,
+                    metadata: metadata.clone(),
+                    amount: 0.0,
+                    reason: Some(reason)
+                })
+            },
+            Some(_) => {
+                // TODO: マジックナンバー。0.001はbtc最低売却量のthreshold。マップでもたせる。
+                let amount = crypto_balance * sell_ratio;
+                if amount < 0.001 {
+                    let reason = format!("{}、最低売却量未満: {}", macd_text, amount);
+                    return Ok(TradeSignal::Hold {
+                        spread_threshold: Some(a_spread_threshold),
+                        spread_ratio: Some(a_spread_ratio),
+                        confidence,
+                        position_fraction: None,
+                        metadata: metadata.clone(),
+                        reason: Some(reason)
+                    });
+                }
+                let reason = format!("{}、シグナル下抜け、{}{}を売却", macd_text, amount, currency);
+                Ok(TradeSignal::MarcketSell {
+                    spread_threshold: Some(a_spread_threshold),
+                    spread_ratio: Some(a_spread_ratio),
+                    confidence,
+                    position_fraction: None,
+                    metadata: metadata.clone(),
+                    amount,
+                    reason: Some(reason)
+                })
+            },
+            None => {
+                Ok(TradeSignal::Hold {
+                    spread_threshold: Some(a_spread_threshold),
+                    spread_ratio: Some(a_spread_ratio),
+                    confidence,
+                    position_fraction: None,
+                    metadata: metadata.clone(),
+                    reason: Some(format!("{}、有効なクロスなし", macd_text))
+                })
+            },
+        }
+    }
+}
+
+/*
+ * シグナルラインとのクロスのうち、lookback本以内にヒストグラムが
+ * 価格比min_histogram_pct%以上に開いたものを、開いた時点のクロスとして返す。
+ * histogram[0]はpricesのbase_index番目に対応する。
+ */
+pub fn confirmed_crosses(
+    histogram: &[f64],
+    prices: &[f64],
+    base_index: usize,
+    params: &MacdParams,
+) -> Vec<Cross> {
+    let mut confirmed = Vec::new();
+
+    for i in 1..histogram.len() {
+        let golden = histogram[i - 1] < 0.0 && histogram[i] >= 0.0;
+        let dead = histogram[i - 1] > 0.0 && histogram[i] <= 0.0;
+        if !golden && !dead {
+            continue;
+        }
+
+        let end = (i + params.lookback).min(histogram.len());
+        for (j, value) in histogram.iter().enumerate().take(end).skip(i) {
+            // 確定前に逆方向へ戻ったら、そのクロスは無効
+            if (golden && *value < 0.0) || (dead && *value > 0.0) {
+                break;
+            }
+
+            let price = prices[base_index + j];
+            if price > 0.0 && value.abs() / price * 100.0 >= params.min_histogram_pct {
+                confirmed.push(Cross { index: base_index + j, golden });
+                break;
+            }
+        }
+    }
+
+    confirmed
+}
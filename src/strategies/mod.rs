@@ -1,4 +1,15 @@
 pub mod strategy_trait;
 pub mod trade_signal;
+pub mod indicator;
 pub mod basic;
 pub mod ma_optimizer;
+pub mod rsi;
+pub mod bollinger;
+pub mod crossover;
+pub mod macd;
+pub mod dca;
+pub mod grid;
+pub mod ensemble;
+pub mod registry;
+pub mod position_sizer;
+pub mod rebalance;
@@ -0,0 +1,287 @@
+use std::env;
+use dotenvy::dotenv;
+
+use diesel::prelude::*;
+
+use crate::{
+    error::AppError,
+    models::{self, order::NewOrder},
+    config,
+};
+
+/*
+ * 購入(market_buy)と判断した通貨に、使えるJPY(budget)をどう配分するか。
+ * budgetは、JPY残高にBUY_THRESHOLD/BUY_RATIOの段階的な割合を掛けたもの。
+ * 積立(contribution_jpy)は戦略が決めた額で買うので、ここには渡さない。
+ *
+ * [envの設定]
+ * POSITION_SIZER=equal (equal, inverse_volatility, fixed_fraction, kelly)
+ * SIZER_VOLATILITY_WINDOW=100 (inverse_volatility: ボラティリティを測るtickerの本数)
+ * SIZER_FIXED_FRACTION=0.1 (fixed_fraction: 1通貨あたりbudgetの何割か。シグナルのposition_fractionがあればそちら)
+ * KELLY_FRACTION=0.5 (kelly: ケリー基準の何倍を使うか)
+ * KELLY_PAYOFF_RATIO=1.0 (kelly: 勝った時の利益 / 負けた時の損失)
+ */
+pub trait PositionSizer {
+    // buysと同じ順で、通貨毎の購入JPYを返す。合計はbudgetを超えない
+    fn allocate(
+        &self,
+        conn: &mut PgConnection,
+        budget: f64,
+        buys: &[&NewOrder],
+    ) -> Result<Vec<f64>, AppError>;
+}
+
+pub fn from_env() -> Result<Box<dyn PositionSizer>, AppError> {
+    dotenv().ok();
+
+    let name = env::var("POSITION_SIZER").unwrap_or("equal".to_string());
+    match name.as_str() {
+        "equal" => Ok(Box::new(EqualSizer)),
+        "inverse_volatility" => Ok(Box::new(InverseVolatilitySizer {
+            window: config::parse_var("SIZER_VOLATILITY_WINDOW", "100")?,
+        })),
+        "fixed_fraction" => Ok(Box::new(FixedFractionSizer {
+            fraction: config::parse_var("SIZER_FIXED_FRACTION", "0.1")?,
+        })),
+        "kelly" => Ok(Box::new(KellySizer {
+            fraction: config::parse_var("KELLY_FRACTION", "0.5")?,
+            payoff_ratio: config::parse_var("KELLY_PAYOFF_RATIO", "1.0")?,
+        })),
+        other => Err(AppError::InvalidData(format!("Invalid POSITION_SIZER: {}", other))),
+    }
+}
+
+/*
+ * 実際に購入する通貨の数で等分する。
+ */
+pub struct EqualSizer;
+
+impl PositionSizer for EqualSizer {
+    fn allocate(
+        &self,
+        _conn: &mut PgConnection,
+        budget: f64,
+        buys: &[&NewOrder],
+    ) -> Result<Vec<f64>, AppError> {
+        if buys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(vec![budget / buys.len() as f64; buys.len()])
+    }
+}
+
+/*
+ * 値動きの小さい通貨ほど多く買う。重みは直近の対数リターンの標準偏差の逆数。
+ * データ不足で測れない通貨は、測れた通貨の平均の重みとする。
+ */
+pub struct InverseVolatilitySizer {
+    pub window: i64,
+}
+
+impl PositionSizer for InverseVolatilitySizer {
+    fn allocate(
+        &self,
+        conn: &mut PgConnection,
+        budget: f64,
+        buys: &[&NewOrder],
+    ) -> Result<Vec<f64>, AppError> {
+        let mut volatilities = Vec::new();
+        for buy in buys.iter() {
+            let prices = models::ticker::Ticker::recent_prices(conn, &buy.pair, self.window)?;
+            volatilities.push(volatility(&prices));
+        }
+
+        Ok(normalize(budget, &inverse_volatility_weights(&volatilities)))
+    }
+}
+
+/*
+ * 1通貨あたりbudgetの一定割合を買う。合計がbudgetを超える時は比率を保って縮める。
+ */
+pub struct FixedFractionSizer {
+    pub fraction: f64,
+}
+
+impl PositionSizer for FixedFractionSizer {
+    fn allocate(
+        &self,
+        _conn: &mut PgConnection,
+        budget: f64,
+        buys: &[&NewOrder],
+    ) -> Result<Vec<f64>, AppError> {
+        let fractions: Vec<f64> = buys.iter()
+            .map(|buy| buy.position_fraction.unwrap_or(self.fraction).clamp(0.0, 1.0))
+            .collect();
+
+        Ok(cap(budget, &fractions))
+    }
+}
+
+/*
+ * 最適化結果の勝率(なければシグナルの確信度)から、ケリー基準で買う割合を決める。
+ * f = p - (1 - p) / b に KELLY_FRACTION を掛ける。fが0以下の通貨は買わない。
+ */
+pub struct KellySizer {
+    pub fraction: f64,
+    pub payoff_ratio: f64,
+}
+
+impl PositionSizer for KellySizer {
+    fn allocate(
+        &self,
+        _conn: &mut PgConnection,
+        budget: f64,
+        buys: &[&NewOrder],
+    ) -> Result<Vec<f64>, AppError> {
+        let fractions: Vec<f64> = buys.iter()
+            .map(|buy| {
+                buy.ma_win_rate
+                    .map(|w| w / 100.0)
+                    .or(buy.confidence)
+                    .map_or(0.0, |p| kelly_fraction(p, self.payoff_ratio, self.fraction))
+            })
+            .collect();
+
+        Ok(cap(budget, &fractions))
+    }
+}
+
+// ケリー基準にfractionを掛けた、budgetに対する割合。payoff_ratioが0以下なら買わない
+fn kelly_fraction(p: f64, payoff_ratio: f64, fraction: f64) -> f64 {
+    if payoff_ratio <= 0.0 {
+        return 0.0;
+    }
+
+    let kelly = p - (1.0 - p) / payoff_ratio;
+    (kelly * fraction).clamp(0.0, 1.0)
+}
+
+// ボラティリティの逆数の重み。測れない通貨は、測れた通貨の平均の重み(全て測れなければ等分)
+fn inverse_volatility_weights(volatilities: &[Option<f64>]) -> Vec<f64> {
+    let weights: Vec<Option<f64>> = volatilities.iter()
+        .map(|v| v.filter(|v| *v > 0.0).map(|v| 1.0 / v))
+        .collect();
+
+    let known: Vec<f64> = weights.iter().flatten().copied().collect();
+    let fallback = if known.is_empty() { 1.0 } else { known.iter().sum::<f64>() / known.len() as f64 };
+
+    weights.into_iter().map(|w| w.unwrap_or(fallback)).collect()
+}
+
+// 対数リターンの標準偏差
+fn volatility(prices: &[f64]) -> Option<f64> {
+    let returns: Vec<f64> = prices.windows(2)
+        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+        .map(|w| (w[1] / w[0]).ln())
+        .collect();
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+// 重みの比でbudgetを配分
+fn normalize(budget: f64, weights: &[f64]) -> Vec<f64> {
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return vec![0.0; weights.len()];
+    }
+
+    weights.iter().map(|w| budget * w / total).collect()
+}
+
+// budgetに対する割合。合計が1を超える時だけ縮める
+fn cap(budget: f64, fractions: &[f64]) -> Vec<f64> {
+    let total: f64 = fractions.iter().sum();
+    let scale = if total > 1.0 { 1.0 / total } else { 1.0 };
+
+    fractions.iter().map(|f| budget * f * scale).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn volatility_is_the_sample_stdev_of_log_returns() {
+        let prices = [100.0, 110.0, 99.0, 108.9];
+        let returns = [(1.1f64).ln(), (0.9f64).ln(), (1.1f64).ln()];
+        let mean = returns.iter().sum::<f64>() / 3.0;
+        let expected = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 2.0).sqrt();
+
+        assert!((volatility(&prices).unwrap() - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn volatility_needs_two_returns() {
+        assert_eq!(volatility(&[]), None);
+        assert_eq!(volatility(&[100.0, 101.0]), None);
+        // 0以下の価格は飛ばす
+        assert_eq!(volatility(&[100.0, 0.0, 101.0]), None);
+    }
+
+    #[test]
+    fn inverse_volatility_weights_favor_calm_pairs() {
+        assert_close(&inverse_volatility_weights(&[Some(0.01), Some(0.02)]), &[100.0, 50.0]);
+    }
+
+    #[test]
+    fn inverse_volatility_weights_use_the_average_for_unknown_pairs() {
+        assert_close(&inverse_volatility_weights(&[Some(0.01), None, Some(0.02), Some(0.0)]), &[100.0, 75.0, 50.0, 75.0]);
+    }
+
+    #[test]
+    fn inverse_volatility_weights_split_equally_when_all_are_unknown() {
+        let weights = inverse_volatility_weights(&[None, None]);
+        assert_close(&normalize(1000.0, &weights), &[500.0, 500.0]);
+    }
+
+    #[test]
+    fn normalize_splits_the_budget_by_weight() {
+        assert_close(&normalize(1000.0, &[1.0, 3.0]), &[250.0, 750.0]);
+        assert_close(&normalize(1000.0, &[0.0, 0.0]), &[0.0, 0.0]);
+        assert!(normalize(1000.0, &[]).is_empty());
+    }
+
+    #[test]
+    fn cap_keeps_fractions_that_fit_the_budget() {
+        assert_close(&cap(1000.0, &[0.1, 0.2]), &[100.0, 200.0]);
+    }
+
+    #[test]
+    fn cap_scales_down_fractions_that_sum_above_one() {
+        let amounts = cap(1000.0, &[0.6, 0.9]);
+        assert_close(&amounts, &[400.0, 600.0]);
+        assert!((amounts.iter().sum::<f64>() - 1000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn kelly_fraction_follows_the_kelly_criterion() {
+        // p=0.6, b=1 -> f=0.2、その半分
+        assert!((kelly_fraction(0.6, 1.0, 0.5) - 0.1).abs() < 1e-12);
+        // p=0.5, b=2 -> f=0.25
+        assert!((kelly_fraction(0.5, 2.0, 1.0) - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn kelly_fraction_does_not_buy_without_an_edge() {
+        assert_eq!(kelly_fraction(0.4, 1.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn kelly_fraction_does_not_buy_with_a_zero_payoff_ratio() {
+        assert_eq!(kelly_fraction(0.6, 0.0, 0.5), 0.0);
+        assert_eq!(kelly_fraction(1.0, 0.0, 0.5), 0.0);
+    }
+}
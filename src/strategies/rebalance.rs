@@ -0,0 +1,237 @@
+use crate::{
+    config,
+    error::AppError,
+    models::summary_record::NewSummaryRecord,
+};
+
+/*
+ * [strategy]
+ * 通貨毎の目標比率(JPY含む)に対して、取引所の残高と売値の評価額から現在の比率を計算し、
+ * 許容幅を超えてずれた通貨だけを、売り→買いの順に目標比率まで戻す。
+ * 目標に書かれていない通貨はリバランスの対象外で、評価額の合計にも含めない。
+ *
+ * [cron]
+ * 1日1回など、cargo run --bin rebalanceを実行 (--dry-runで注文せずに内容だけ表示)
+ *
+ * [envの設定]
+ * REBALANCE_TARGETS=btc:0.5,eth:0.2,jpy:0.3 (合計1.0)
+ * REBALANCE_TOLERANCE_PCT=5.0 (目標比率から何%ポイントずれたら戻すか)
+ * REBALANCE_MIN_BUY_JPY=500 (これ未満の買いはしない)
+ * REBALANCE_MIN_SELL_BTC=0.001 (通貨毎の最低売却量。未設定なら0.001)
+ */
+
+#[derive(Debug, Clone)]
+pub struct RebalanceConfig {
+    pub targets: Vec<(String, f64)>,
+    pub tolerance_pct: f64,
+    pub min_buy_jpy: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RebalanceOrder {
+    pub pair: String,
+    pub order_type: String,
+    // market_buyはJPY、market_sellは通貨の量
+    pub amount: f64,
+    pub jpy_value: f64,
+    pub reason: String,
+}
+
+impl RebalanceConfig {
+    pub fn from_env() -> Result<Self, AppError> {
+        let entries: String = config::parse_var("REBALANCE_TARGETS", "")?;
+
+        let mut targets = Vec::new();
+        for entry in entries.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            let Some((currency, weight)) = entry.split_once(':') else {
+                return Err(AppError::InvalidData(format!("Invalid REBALANCE_TARGETS entry: {}", entry)));
+            };
+            let weight = weight.trim().parse::<f64>()
+                .map_err(|e| AppError::InvalidData(format!("Parse error: REBALANCE_TARGETS: {}: {}", entry, e)))?;
+            targets.push((currency.trim().to_lowercase(), weight));
+        }
+
+        let total: f64 = targets.iter().map(|(_, w)| w).sum();
+        if targets.is_empty() || (total - 1.0).abs() > 1e-6 {
+            return Err(AppError::InvalidData(format!("REBALANCE_TARGETS must sum to 1.0: {}", total)));
+        }
+
+        let tolerance_pct = config::parse_var("REBALANCE_TOLERANCE_PCT", "5.0")?;
+        let min_buy_jpy = config::parse_var("REBALANCE_MIN_BUY_JPY", "500")?;
+
+        Ok(Self { targets, tolerance_pct, min_buy_jpy })
+    }
+
+    // TODO: 0.001はbtc最低売却量。通貨毎にenvで上書きする。
+    pub fn min_sell(&self, currency: &str) -> Result<f64, AppError> {
+        config::parse_var(&format!("REBALANCE_MIN_SELL_{}", currency.to_uppercase()), "0.001")
+    }
+}
+
+/*
+ * 目標の通貨毎の評価額から、目標比率に戻すための注文を作る。売りが先、買いが後。
+ * 評価額のない目標の通貨があればエラー(0円として扱うと、売りすぎ・買いすぎになる)。
+ * 買いは、JPY残高と売りで得る見込みのJPYの範囲に収まるように縮める。
+ */
+pub fn plan(config: &RebalanceConfig, records: &[NewSummaryRecord]) -> Result<Vec<RebalanceOrder>, AppError> {
+    if let Some((currency, _)) = config.targets.iter().find(|(currency, _)| !records.iter().any(|r| r.currency == *currency)) {
+        return Err(AppError::InvalidData(format!("REBALANCE_TARGETSの{}の評価額がありません", currency)));
+    }
+    let value_of = |currency: &str| -> (f64, f64) {
+        records.iter()
+            .find(|r| r.currency == currency)
+            .map(|r| (r.jpy_value, r.rate))
+            .unwrap_or((0.0, 0.0))
+    };
+
+    let total: f64 = config.targets.iter().map(|(currency, _)| value_of(currency).0).sum();
+    if total <= 0.0 {
+        return Ok(Vec::new());
+    }
+
+    let mut sells = Vec::new();
+    let mut buys = Vec::new();
+    for (currency, target) in config.targets.iter().filter(|(currency, _)| currency != "jpy") {
+        let (jpy_value, rate) = value_of(currency);
+        let current = jpy_value / total;
+        let drift_pct = (current - target) * 100.0;
+        if drift_pct.abs() <= config.tolerance_pct {
+            continue;
+        }
+
+        let delta_jpy = (target - current) * total;
+        let reason = format!(
+            "リバランス: {} {:.2}% -> {:.2}% (許容{}%)",
+            currency, current * 100.0, target * 100.0, config.tolerance_pct
+        );
+
+        if delta_jpy < 0.0 {
+            if rate <= 0.0 {
+                continue;
+            }
+            let amount = -delta_jpy / rate;
+            if amount < config.min_sell(currency)? {
+                continue;
+            }
+            sells.push(RebalanceOrder {
+                pair: currency.clone(),
+                order_type: "market_sell".to_string(),
+                amount,
+                jpy_value: -delta_jpy,
+                reason,
+            });
+        } else {
+            buys.push(RebalanceOrder {
+                pair: currency.clone(),
+                order_type: "market_buy".to_string(),
+                amount: delta_jpy,
+                jpy_value: delta_jpy,
+                reason,
+            });
+        }
+    }
+
+    let available_jpy = value_of("jpy").0 + sells.iter().map(|o| o.jpy_value).sum::<f64>();
+    let buy_total: f64 = buys.iter().map(|o| o.amount).sum();
+    if buy_total > available_jpy {
+        let scale = available_jpy / buy_total;
+        for buy in buys.iter_mut() {
+            buy.amount *= scale;
+            buy.jpy_value = buy.amount;
+        }
+    }
+    buys.retain(|o| o.amount >= config.min_buy_jpy);
+
+    sells.extend(buys);
+    Ok(sells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(targets: &[(&str, f64)]) -> RebalanceConfig {
+        RebalanceConfig {
+            targets: targets.iter().map(|(c, w)| (c.to_string(), *w)).collect(),
+            tolerance_pct: 5.0,
+            min_buy_jpy: 500.0,
+        }
+    }
+
+    fn record(currency: &str, amount: f64, rate: f64) -> NewSummaryRecord {
+        NewSummaryRecord {
+            summary_id: None,
+            currency: currency.to_string(),
+            amount,
+            rate,
+            jpy_value: if currency == "jpy" { amount } else { amount * rate },
+        }
+    }
+
+    #[test]
+    fn plan_skips_drift_within_the_tolerance() {
+        // btc 54%、目標50%で許容5%以内
+        let records = [record("btc", 0.0054, 10_000_000.0), record("jpy", 46_000.0, 0.0)];
+
+        assert!(plan(&config(&[("btc", 0.5), ("jpy", 0.5)]), &records).unwrap().is_empty());
+    }
+
+    #[test]
+    fn plan_sells_before_buying_back_to_the_targets() {
+        // btc 70% / xrp 10% / jpy 20%
+        let records = [
+            record("btc", 0.007, 10_000_000.0),
+            record("xrp", 100.0, 100.0),
+            record("jpy", 20_000.0, 0.0),
+        ];
+
+        let orders = plan(&config(&[("btc", 0.5), ("xrp", 0.3), ("jpy", 0.2)]), &records).unwrap();
+
+        assert_eq!(orders.len(), 2);
+        assert_eq!((orders[0].pair.as_str(), orders[0].order_type.as_str()), ("btc", "market_sell"));
+        assert!((orders[0].amount - 0.002).abs() < 1e-9);
+        assert_eq!((orders[1].pair.as_str(), orders[1].order_type.as_str()), ("xrp", "market_buy"));
+        assert!((orders[1].amount - 20_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn plan_skips_sells_below_the_minimum_amount() {
+        // 売り0.0008btcは最低売却量0.001未満
+        let records = [record("btc", 0.0058, 1_000_000.0), record("jpy", 4_200.0, 0.0)];
+
+        let orders = plan(&config(&[("btc", 0.5), ("jpy", 0.5)]), &records).unwrap();
+        assert!(orders.is_empty());
+    }
+
+    #[test]
+    fn plan_skips_buys_below_the_minimum_jpy() {
+        // btc 40%で許容を超えるが、買い400JPYは最低購入額500未満
+        let records = [record("btc", 0.00016, 10_000_000.0), record("jpy", 2_400.0, 0.0)];
+
+        let orders = plan(&config(&[("btc", 0.5), ("jpy", 0.5)]), &records).unwrap();
+        assert!(orders.is_empty());
+    }
+
+    #[test]
+    fn plan_scales_buys_to_the_available_jpy() {
+        // 目標の合計が100%を超えていても、買いはJPY残高と売りの見込みまで
+        let records = [
+            record("btc", 0.001, 10_000_000.0),
+            record("xrp", 100.0, 100.0),
+            record("jpy", 10_000.0, 0.0),
+        ];
+
+        let orders = plan(&config(&[("btc", 0.8), ("xrp", 0.8), ("jpy", 0.0)]), &records).unwrap();
+
+        let buy_total: f64 = orders.iter().filter(|o| o.order_type == "market_buy").map(|o| o.amount).sum();
+        assert_eq!(orders.len(), 2);
+        assert!((buy_total - 10_000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn plan_errors_when_a_target_has_no_valuation() {
+        let records = [record("btc", 0.001, 10_000_000.0), record("jpy", 10_000.0, 0.0)];
+
+        assert!(plan(&config(&[("btc", 0.4), ("eth", 0.3), ("jpy", 0.3)]), &records).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{
+    config,
+    error::AppError,
+    strategies::{
+        basic::BasicStrategy,
+        bollinger::BollingerStrategy,
+        dca::DcaStrategy,
+        ensemble::EnsembleStrategy,
+        ma_optimizer::MaOptimizerStrategy,
+        macd::MacdStrategy,
+        rsi::RsiStrategy,
+        strategy_trait::Strategy,
+    },
+};
+
+/*
+ * 戦略名から戦略を組み立てる。
+ * 通貨毎にSTRATEGY_{CURRENCY}で戦略を割り当て、未設定ならSTRATEGY、それも未設定ならma_optimizer。
+ * 戦略のパラメータも、{KEY}_{CURRENCY}があれば通貨毎に上書きできる(crate::config)。
+ *
+ * ordersには、注文を決めた戦略の名前とバージョンを記録する。
+ * 戦略のロジックを変えた時はversionを上げて、変更前後の成績を区別できるようにする。
+ *
+ * [envの設定]
+ * STRATEGY=ma_optimizer
+ * STRATEGY_XRP=rsi
+ * RSI_PERIOD_XRP=7
+ */
+
+pub const DEFAULT_STRATEGY: &str = "ma_optimizer";
+
+pub struct StrategyEntry {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub build: fn(&str) -> Result<Box<dyn Strategy + Send + Sync>, AppError>,
+}
+
+pub const STRATEGIES: &[StrategyEntry] = &[
+    StrategyEntry { name: "basic", version: "1", build: |_| Ok(Box::new(BasicStrategy)) },
+    StrategyEntry { name: "ma_optimizer", version: "1", build: |_| Ok(Box::new(MaOptimizerStrategy)) },
+    StrategyEntry { name: "rsi", version: "1", build: |_| Ok(Box::new(RsiStrategy)) },
+    StrategyEntry { name: "bollinger", version: "1", build: |_| Ok(Box::new(BollingerStrategy)) },
+    StrategyEntry { name: "macd", version: "1", build: |_| Ok(Box::new(MacdStrategy)) },
+    StrategyEntry { name: "dca", version: "1", build: |_| Ok(Box::new(DcaStrategy)) },
+    StrategyEntry { name: "ensemble", version: "1", build: |currency| Ok(Box::new(EnsembleStrategy::from_env(currency)?)) },
+];
+
+pub fn find(name: &str) -> Result<&'static StrategyEntry, AppError> {
+    STRATEGIES
+        .iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| AppError::InvalidData(format!("Unknown strategy: {}", name)))
+}
+
+// 通貨に割り当てられた戦略
+pub fn assignment_for(currency: &str) -> Result<&'static StrategyEntry, AppError> {
+    let name = config::param("STRATEGY", currency).unwrap_or(DEFAULT_STRATEGY.to_string());
+    find(name.trim())
+}
+
+pub fn build(currency: &str) -> Result<(&'static StrategyEntry, Box<dyn Strategy + Send + Sync>), AppError> {
+    let entry = assignment_for(currency)?;
+    Ok((entry, (entry.build)(currency)?))
+}
@@ -0,0 +1,135 @@
+use dotenvy::dotenv;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use diesel::prelude::*;
+
+use crate::{
+    config,
+    models,
+    error::AppError,
+    strategies::{
+        indicator,
+        strategy_trait::Strategy,
+        trade_signal::TradeSignal,
+    },
+};
+
+/*
+ * [strategy]
+ * tickersから直近のRSIを計算して、売られすぎで買い、買われすぎで売る(逆張り)。
+ * レンジ相場でMAクロスが負ける場面向け。
+ *
+ * [cron]
+ * 2分毎に、cargo run --bin ticker_fetcherを実行して、tickersに情報を蓄積
+ * 15毎に、cargo run --bin orderを実行して、注文
+ *
+ * [envの設定]
+ * RSI_PERIOD=14
+ * RSI_OVERBOUGHT=70
+ * RSI_OVERSOLD=30
+ * SELL_RATIO=0.4
+ */
+
+pub struct RsiStrategy;
+
+#[async_trait]
+impl Strategy for RsiStrategy {
+    async fn determine_trade_signal(
+        &self,
+        conn: &mut PgConnection,
+        currency: &str,
+        current_bid: f64,
+        current_ask: f64,
+        crypto_balance: f64,
+    ) -> Result<TradeSignal, AppError> {
+        dotenv().ok();
+
+        let period: usize = config::parse_param("RSI_PERIOD", currency, "14")?;
+        let overbought: f64 = config::parse_param("RSI_OVERBOUGHT", currency, "70")?;
+        let oversold: f64 = config::parse_param("RSI_OVERSOLD", currency, "30")?;
+
+        let sell_ratio: f64 = config::require_param("SELL_RATIO", currency)?;
+
+        let prices = models::ticker::Ticker::recent_prices(conn, currency, period as i64 + 1)?;
+        let Some(rsi) = indicator::rsi(&prices, period) else {
+            return Ok(TradeSignal::InsufficientData {
+                spread_threshold: None,
+                spread_ratio: None,
+                confidence: None,
+                position_fraction: None,
+                metadata: Value::Null,
+                reason: Some("データ不足".to_string())
+            });
+        };
+        let metadata = json!({"rsi": rsi, "rsi_period": period, "overbought": overbought, "oversold": oversold});
+
+        let a_spread_ratio = ((current_ask - current_bid) / current_bid) * 100.0;
+        let a_spread_threshold = models::ticker::get_dynamic_spread_threshold(conn, currency).await?;
+        if a_spread_ratio > a_spread_threshold {
+            return Ok(TradeSignal::Hold {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence: None,
+                position_fraction: None,
+                metadata: metadata.clone(),
+                reason: Some(format!("スプレッド負け: RSI({})={:.2}", period, rsi))
+            });
+        }
+
+        if rsi < oversold {
+            // 売られすぎ(0.0の仮値をセット)
+            // すべてjpyで購入なので、呼び出し元で他購入通貨とのバランスを計算して再セットする。
+            // 閾値より深く売られているほど確信度を上げる
+            let reason = format!("RSI({})={:.2} < {}、jpy_amount分{}を購入", period, rsi, oversold, currency);
+            Ok(TradeSignal::MarcketBuy {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence: Some(((oversold - rsi) / oversold).clamp(0.0, 1.0)),
+                position_fraction: None,
+                metadata: metadata.clone(),
+                amount: 0.0,
+                reason: Some(reason)
+            })
+
+        } else if rsi > overbought {
+            // 買われすぎ
+            // TODO: マジックナンバー。0.001はbtc最低売却量のthreshold。マップでもたせる。
+            let sell_confidence = ((rsi - overbought) / (100.0 - overbought)).clamp(0.0, 1.0);
+            let amount = crypto_balance * sell_ratio;
+            if amount < 0.001 {
+                let reason = format!("RSI({})={:.2} > {}、最低売却量未満: {}", period, rsi, overbought, amount);
+                return Ok(TradeSignal::Hold {
+                    spread_threshold: Some(a_spread_threshold),
+                    spread_ratio: Some(a_spread_ratio),
+                    confidence: None,
+                    position_fraction: None,
+                    metadata: metadata.clone(),
+                    reason: Some(reason)
+                });
+            }
+            let reason = format!("RSI({})={:.2} > {}、{}{}を売却", period, rsi, overbought, amount, currency);
+            Ok(TradeSignal::MarcketSell {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence: Some(sell_confidence),
+                position_fraction: None,
+                metadata: metadata.clone(),
+                amount,
+                reason: Some(reason)
+            })
+
+        } else {
+            let reason = format!("RSI({})={:.2}、{}〜{}の範囲内", period, rsi, oversold, overbought);
+            Ok(TradeSignal::Hold {
+                spread_threshold: Some(a_spread_threshold),
+                spread_ratio: Some(a_spread_ratio),
+                confidence: None,
+                position_fraction: None,
+                metadata: metadata.clone(),
+                reason: Some(reason)
+            })
+        }
+    }
+}
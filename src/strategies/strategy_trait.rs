@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use diesel::prelude::*;
 
 use crate::error::AppError;
+use crate::models::signal_vote::NewSignalVote;
 use crate::strategies::trade_signal::TradeSignal;
 
 #[async_trait]
@@ -14,4 +15,12 @@ pub trait Strategy {
         current_ask: f64,
         crypto_balance: f64,
     ) -> Result<TradeSignal, AppError>;
+
+    /*
+     * 直前のdetermine_trade_signalで、currencyについて集めた投票を取り出す。
+     * アンサンブルのように、複数戦略の判断を記録したい戦略だけが実装する。
+     */
+    fn take_votes(&self, _currency: &str) -> Vec<NewSignalVote> {
+        Vec::new()
+    }
 }
@@ -1,82 +1,168 @@
+use serde_json::Value;
+
 use crate::models::order::NewOrder;
 
+/*
+ * 戦略の判断結果。
+ *
+ * confidence:        判断の確信度(0.0〜1.0)。戦略が計算できなければNone
+ * position_fraction: 戦略が提案する、使えるJPYのうち注文に充てる割合(0.0〜1.0)。任意
+ * metadata:          判断に使った指標の値など(JSONのオブジェクト)。ordersのsignal_metadataに保存する
+ *
+ * metadataにma_short/ma_long/ma_win_rate/optimization_run_idがあれば、ordersの同名カラムにも記録する。
+ */
 #[allow(dead_code)]
 pub enum TradeSignal {
     MarcketBuy {
         spread_threshold: Option<f64>,
         spread_ratio: Option<f64>,
-        ma_short: Option<i32>,
-        ma_long: Option<i32>,
-        ma_win_rate: Option<f64>,
+        confidence: Option<f64>,
+        position_fraction: Option<f64>,
+        metadata: Value,
         amount: f64,
         reason: Option<String>,
     },
     MarcketSell {
         spread_threshold: Option<f64>,
         spread_ratio: Option<f64>,
-        ma_short: Option<i32>,
-        ma_long: Option<i32>,
-        ma_win_rate: Option<f64>,
+        confidence: Option<f64>,
+        position_fraction: Option<f64>,
+        metadata: Value,
+        amount: f64,
+        reason: Option<String>,
+    },
+    // 積立(DCA)の買い。呼び出し元でJPYを按分せず、amountのまま購入する
+    Contribution {
+        spread_threshold: Option<f64>,
+        spread_ratio: Option<f64>,
+        metadata: Value,
         amount: f64,
         reason: Option<String>,
     },
     Hold {
         spread_threshold: Option<f64>,
         spread_ratio: Option<f64>,
-        ma_short: Option<i32>,
-        ma_long: Option<i32>,
-        ma_win_rate: Option<f64>,
+        confidence: Option<f64>,
+        position_fraction: Option<f64>,
+        metadata: Value,
         reason: Option<String>,
     },
     InsufficientData {
         spread_threshold: Option<f64>,
         spread_ratio: Option<f64>,
-        ma_short: Option<i32>,
-        ma_long: Option<i32>,
-        ma_win_rate: Option<f64>,
+        confidence: Option<f64>,
+        position_fraction: Option<f64>,
+        metadata: Value,
         reason: Option<String>,
     },
 }
 
 impl TradeSignal {
+    // ordersのorder_typeと同じ表記
+    pub fn order_type(&self) -> &'static str {
+        match self {
+            TradeSignal::MarcketBuy { .. } | TradeSignal::Contribution { .. } => "market_buy",
+            TradeSignal::MarcketSell { .. } => "market_sell",
+            TradeSignal::Hold { .. } => "hold",
+            TradeSignal::InsufficientData { .. } => "insufficient_data",
+        }
+    }
+
+    pub fn amount(&self) -> f64 {
+        match self {
+            TradeSignal::MarcketBuy { amount, .. }
+            | TradeSignal::MarcketSell { amount, .. }
+            | TradeSignal::Contribution { amount, .. } => *amount,
+            _ => 0.0,
+        }
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            TradeSignal::MarcketBuy { reason, .. }
+            | TradeSignal::MarcketSell { reason, .. }
+            | TradeSignal::Contribution { reason, .. }
+            | TradeSignal::Hold { reason, .. }
+            | TradeSignal::InsufficientData { reason, .. } => reason.as_deref(),
+        }
+    }
+
+    // (spread_threshold, spread_ratio)
+    pub fn spread(&self) -> (Option<f64>, Option<f64>) {
+        match self {
+            TradeSignal::MarcketBuy { spread_threshold, spread_ratio, .. }
+            | TradeSignal::MarcketSell { spread_threshold, spread_ratio, .. }
+            | TradeSignal::Contribution { spread_threshold, spread_ratio, .. }
+            | TradeSignal::Hold { spread_threshold, spread_ratio, .. }
+            | TradeSignal::InsufficientData { spread_threshold, spread_ratio, .. } => (*spread_threshold, *spread_ratio),
+        }
+    }
+
+    pub fn confidence(&self) -> Option<f64> {
+        match self {
+            TradeSignal::MarcketBuy { confidence, .. }
+            | TradeSignal::MarcketSell { confidence, .. }
+            | TradeSignal::Hold { confidence, .. }
+            | TradeSignal::InsufficientData { confidence, .. } => *confidence,
+            TradeSignal::Contribution { .. } => None,
+        }
+    }
+
+    pub fn position_fraction(&self) -> Option<f64> {
+        match self {
+            TradeSignal::MarcketBuy { position_fraction, .. }
+            | TradeSignal::MarcketSell { position_fraction, .. }
+            | TradeSignal::Hold { position_fraction, .. }
+            | TradeSignal::InsufficientData { position_fraction, .. } => *position_fraction,
+            TradeSignal::Contribution { .. } => None,
+        }
+    }
+
+    pub fn metadata(&self) -> &Value {
+        match self {
+            TradeSignal::MarcketBuy { metadata, .. }
+            | TradeSignal::MarcketSell { metadata, .. }
+            | TradeSignal::Contribution { metadata, .. }
+            | TradeSignal::Hold { metadata, .. }
+            | TradeSignal::InsufficientData { metadata, .. } => metadata,
+        }
+    }
+
     pub fn apply_to(&self, new_order: &mut NewOrder) {
+        let (spread_threshold, spread_ratio) = self.spread();
+        let metadata = self.metadata();
+
+        new_order.order_type = self.order_type().to_string();
+        new_order.spread_threshold = spread_threshold;
+        new_order.spread_ratio = spread_ratio;
+        new_order.confidence = self.confidence();
+        new_order.position_fraction = self.position_fraction();
+        new_order.signal_metadata = if metadata.is_null() { None } else { Some(metadata.clone()) };
+        new_order.comment = self.reason().map(|r| r.to_string());
+
+        // MA系の戦略が使っていた既存カラムは、metadataから埋める
+        new_order.ma_short = metadata.get("ma_short").and_then(Value::as_i64).map(|v| v as i32);
+        new_order.ma_long = metadata.get("ma_long").and_then(Value::as_i64).map(|v| v as i32);
+        new_order.ma_win_rate = metadata.get("ma_win_rate").and_then(Value::as_f64);
+        new_order.optimization_run_id = metadata.get("optimization_run_id").and_then(Value::as_i64).map(|v| v as i32);
+
         match self {
-            TradeSignal::MarcketBuy { spread_threshold, spread_ratio, ma_short, ma_long, ma_win_rate, amount, reason } => {
-                new_order.order_type = "market_buy".to_string();
-                new_order.spread_threshold = *spread_threshold;
-                new_order.spread_ratio = *spread_ratio;
-                new_order.ma_short = *ma_short;
-                new_order.ma_long = *ma_long;
-                new_order.ma_win_rate = *ma_win_rate;
+            TradeSignal::MarcketBuy { amount, .. } => {
                 new_order.jpy_amount = *amount;
                 new_order.crypto_amount = 0.0;
-                new_order.comment = reason.clone();
             },
-            TradeSignal::MarcketSell { spread_threshold, spread_ratio, ma_short, ma_long, ma_win_rate, amount, reason } => {
-                new_order.order_type = "market_sell".to_string();
-                new_order.spread_threshold = *spread_threshold;
-                new_order.spread_ratio = *spread_ratio;
-                new_order.ma_short = *ma_short;
-                new_order.ma_long = *ma_long;
-                new_order.ma_win_rate = *ma_win_rate;
+            TradeSignal::MarcketSell { amount, .. } => {
                 new_order.jpy_amount = 0.0;
                 new_order.crypto_amount = *amount;
-                new_order.comment = reason.clone();
             },
-            TradeSignal::Hold { spread_threshold, spread_ratio, ma_short, ma_long, ma_win_rate, reason }
-            | TradeSignal::InsufficientData { spread_threshold, spread_ratio, ma_short, ma_long, ma_win_rate, reason } => {
-                new_order.order_type = match self {
-                    TradeSignal::Hold { .. } => "hold",
-                    _ => "insufficient_data",
-                }.to_string();
-                new_order.spread_threshold = *spread_threshold;
-                new_order.spread_ratio = *spread_ratio;
-                new_order.ma_short = *ma_short;
-                new_order.ma_long = *ma_long;
-                new_order.ma_win_rate = *ma_win_rate;
+            TradeSignal::Contribution { amount, .. } => {
+                new_order.jpy_amount = *amount;
+                new_order.crypto_amount = 0.0;
+                new_order.contribution_jpy = Some(*amount);
+            },
+            TradeSignal::Hold { .. } | TradeSignal::InsufficientData { .. } => {
                 new_order.jpy_amount = 0.0;
                 new_order.crypto_amount = 0.0;
-                new_order.comment = reason.clone();
             },
         }
     }